@@ -0,0 +1,402 @@
+//! Discovery of the registered oracles and their queues over RPC.
+//!
+//! Queues are found with `getProgramAccounts` on their discriminator, both on the program and
+//! on the delegation program for queues delegated to an ephemeral rollup, and matched to their
+//! oracle by deriving their PDA. The requests of delegated queues are served on the ephemeral
+//! rollup, their load is unknown on the base layer.
+
+use crate::prelude::*;
+use core::cmp::Reverse;
+use ephemeral_rollups_sdk::consts::DELEGATION_PROGRAM_ID;
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::client_error::{ClientError, ClientErrorKind};
+use solana_client::rpc_client::RpcClient;
+use solana_client::rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig};
+use solana_client::rpc_filter::{Memcmp, MemcmpEncodedBytes, RpcFilterType};
+use solana_program::sysvar;
+
+/// Health of a queue, as of the slot it was fetched in.
+#[derive(Clone, Debug, PartialEq)]
+pub struct QueueHealth {
+    pub address: Pubkey,
+    /// Oracle owning the queue, `None` for queues shared by the registered oracles.
+    pub oracle: Option<Pubkey>,
+    pub index: u8,
+    /// The queue is delegated to an ephemeral rollup, its requests are served there.
+    pub delegated: bool,
+    /// Size of the queue account in bytes.
+    pub size: usize,
+    /// The queue holds requests in the layout used before `QUEUE_VERSION` and rejects new
+    /// requests until converted by `MigrateQueue`, its load is unknown until then.
+    pub needs_migration: bool,
+    /// Requests of the queue, `None` if unknown: the base layer copy of a delegated queue is
+    /// stale, its requests are only known to the ephemeral rollup, and the requests of a queue
+    /// needing migration can't be decoded.
+    pub load: Option<QueueLoad>,
+}
+
+/// Requests held by a queue.
+#[derive(Clone, Debug, PartialEq)]
+pub struct QueueLoad {
+    pub item_count: u32,
+    /// Bytes used by the discriminator, the header and the queued items.
+    pub used_bytes: usize,
+    pub items: Vec<QueueItem>,
+}
+
+impl QueueHealth {
+    /// Decodes a queue account owned by the program or delegated, `None` if it isn't a queue.
+    pub fn from_account(address: Pubkey, owner: &Pubkey, data: &[u8]) -> Option<Self> {
+        if owner.ne(&crate::ID) && owner.ne(&DELEGATION_PROGRAM_ID) {
+            return None;
+        }
+        let header = *Queue::try_from_bytes(data).ok()?;
+        let delegated = owner.eq(&DELEGATION_PROGRAM_ID);
+        let size = data.len();
+        if QueueAccount::is_legacy(&data[8..]) {
+            return Some(Self {
+                address,
+                oracle: None,
+                index: header.index,
+                delegated,
+                size,
+                needs_migration: true,
+                load: None,
+            });
+        }
+        let mut data = data.to_vec();
+        let queue_acc = QueueAccount::load(&mut data[8..]).ok()?;
+        Some(Self {
+            address,
+            oracle: None,
+            index: queue_acc.header.index,
+            delegated,
+            size,
+            needs_migration: false,
+            load: (!delegated).then(|| QueueLoad {
+                item_count: queue_acc.header.item_count,
+                used_bytes: 8 + queue_acc.header.cursor as usize,
+                items: queue_acc.iter_items().collect(),
+            }),
+        })
+    }
+
+    /// Slots the longest waiting due request has been waiting for, counted like the reassign
+    /// delay (see `QueueItem::waiting_since_slot`). `None` if no request is due or the load is
+    /// unknown.
+    pub fn oldest_item_age(&self, slot: u64, timestamp: i64) -> Option<u64> {
+        self.load
+            .as_ref()?
+            .items
+            .iter()
+            .filter(|item| item.is_due(slot, timestamp) && !item.is_expired(slot, timestamp))
+            .map(|item| slot.saturating_sub(item.waiting_since_slot()))
+            .max()
+    }
+
+    /// A queue is stalled when one of its requests waited long enough to be reassigned (see
+    /// `ReassignRequest`), its oracle is likely down. `after_ttl_bps` is the reassign delay of
+    /// the admin account. Queues of unknown load are not reported as stalled.
+    pub fn is_stalled(&self, slot: u64, timestamp: i64, after_ttl_bps: u64) -> bool {
+        self.load.as_ref().is_some_and(|load| {
+            load.items
+                .iter()
+                .any(|item| item.is_reassignable(slot, timestamp, after_ttl_bps))
+        })
+    }
+
+    pub fn is_shared(&self) -> bool {
+        self.oracle.is_none()
+    }
+}
+
+/// A registered oracle with its queues.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RegisteredOracle {
+    pub identity: Pubkey,
+    pub vrf_pubkey: [u8; 32],
+    pub registration_slot: u64,
+    pub queues: Vec<QueueHealth>,
+}
+
+/// Snapshot of the registered oracles and the queues they serve.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OracleRegistry {
+    /// Slot the snapshot was taken in.
+    pub slot: u64,
+    /// Unix timestamp of `slot`.
+    pub timestamp: i64,
+    /// Share of the TTL after which requests can be reassigned, set in the admin account.
+    pub reassign_after_ttl_bps: u64,
+    pub oracles: Vec<RegisteredOracle>,
+    pub shared_queues: Vec<QueueHealth>,
+}
+
+impl OracleRegistry {
+    /// Fetch the registered oracles and all their queues, including delegated and shared ones.
+    pub fn fetch(rpc_client: &RpcClient) -> Result<Self, ClientError> {
+        let clock: Clock = bincode::deserialize(&rpc_client.get_account_data(&sysvar::clock::ID)?)
+            .map_err(|err| ClientErrorKind::Custom(format!("Invalid clock sysvar: {err}")))?;
+        let reassign_after_ttl_bps =
+            Admin::try_from_bytes(&rpc_client.get_account_data(&admin_pda().0)?)
+                .map_err(|err| ClientErrorKind::Custom(format!("Invalid admin account: {err}")))?
+                .reassign_after_ttl_bps;
+        let oracles = Oracles::try_from_bytes_with_discriminator(
+            &rpc_client.get_account_data(&oracles_pda().0)?,
+        )
+        .map_err(|err| ClientErrorKind::Custom(format!("Invalid oracles account: {err}")))?
+        .oracles;
+
+        let oracle_data_keys: Vec<Pubkey> = oracles
+            .iter()
+            .map(|identity| oracle_data_pda(identity).0)
+            .collect();
+        let mut registered = Vec::with_capacity(oracles.len());
+        for (chunk, keys) in oracles.chunks(100).zip(oracle_data_keys.chunks(100)) {
+            let accounts = rpc_client.get_multiple_accounts(keys)?;
+            for (identity, account) in chunk.iter().zip(accounts) {
+                let Some(oracle) = account.and_then(|account| {
+                    account
+                        .owner
+                        .eq(&crate::ID)
+                        .then(|| Oracle::try_from_bytes(&account.data).ok().copied())
+                        .flatten()
+                }) else {
+                    continue;
+                };
+                registered.push(RegisteredOracle {
+                    identity: *identity,
+                    vrf_pubkey: oracle.vrf_pubkey.0,
+                    registration_slot: oracle.registration_slot,
+                    queues: Vec::new(),
+                });
+            }
+        }
+
+        let mut shared_queues = Vec::new();
+        for program_id in [crate::ID, DELEGATION_PROGRAM_ID] {
+            for mut queue in fetch_queues(rpc_client, &program_id)? {
+                let address = queue.address;
+                if shared_queue_pda(queue.index).0.eq(&address) {
+                    shared_queues.push(queue);
+                } else if let Some(oracle) = registered.iter_mut().find(|oracle| {
+                    oracle_queue_pda(&oracle.identity, queue.index)
+                        .0
+                        .eq(&address)
+                }) {
+                    queue.oracle = Some(oracle.identity);
+                    oracle.queues.push(queue);
+                }
+            }
+        }
+
+        Ok(Self {
+            slot: clock.slot,
+            timestamp: clock.unix_timestamp,
+            reassign_after_ttl_bps,
+            oracles: registered,
+            shared_queues,
+        })
+    }
+
+    /// All the queues of the registered oracles, then the shared queues.
+    pub fn queues(&self) -> impl Iterator<Item = &QueueHealth> {
+        self.oracles
+            .iter()
+            .flat_map(|oracle| oracle.queues.iter())
+            .chain(self.shared_queues.iter())
+    }
+
+    /// Best queue to send requests to, see `pick_best_queue`.
+    pub fn best_queue(&self, delegated: bool) -> Option<&QueueHealth> {
+        pick_best_queue(
+            self.queues(),
+            self.slot,
+            self.timestamp,
+            self.reassign_after_ttl_bps,
+            delegated,
+        )
+    }
+}
+
+/// Pick the queue to send requests to among `queues`: the least loaded among the queues that
+/// aren't stalled as of `slot` and `timestamp`, delegated or not as requested, `None` if all
+/// are stalled. Queues needing migration reject requests and are skipped.
+///
+/// Load is compared by queued requests, then by free space, so that fresh requests are
+/// served first and don't fail on a full queue. Queues of unknown load come last.
+pub fn pick_best_queue<'a>(
+    queues: impl IntoIterator<Item = &'a QueueHealth>,
+    slot: u64,
+    timestamp: i64,
+    after_ttl_bps: u64,
+    delegated: bool,
+) -> Option<&'a QueueHealth> {
+    queues
+        .into_iter()
+        .filter(|queue| {
+            queue.delegated == delegated
+                && !queue.needs_migration
+                && !queue.is_stalled(slot, timestamp, after_ttl_bps)
+        })
+        .min_by_key(|queue| {
+            let load = queue.load.as_ref();
+            (
+                load.is_none(),
+                load.map(|load| load.item_count),
+                Reverse(load.map(|load| queue.size.saturating_sub(load.used_bytes))),
+                queue.address,
+            )
+        })
+}
+
+/// Fetch the queue accounts owned by `program_id`.
+fn fetch_queues(
+    rpc_client: &RpcClient,
+    program_id: &Pubkey,
+) -> Result<Vec<QueueHealth>, ClientError> {
+    let config = RpcProgramAccountsConfig {
+        account_config: RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::Base64),
+            ..Default::default()
+        },
+        filters: Some(vec![RpcFilterType::Memcmp(Memcmp::new(
+            0,
+            MemcmpEncodedBytes::Bytes(AccountDiscriminator::Queue.to_bytes().to_vec()),
+        ))]),
+        ..Default::default()
+    };
+    Ok(rpc_client
+        .get_program_accounts_with_config(program_id, config)?
+        .into_iter()
+        .filter_map(|(address, account)| {
+            QueueHealth::from_account(address, &account.owner, &account.data)
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queue_account(items: &[QueueItem]) -> Vec<u8> {
+        let mut data = vec![0u8; 4096];
+        data[..8].copy_from_slice(&AccountDiscriminator::Queue.to_bytes());
+        let mut queue_acc = QueueAccount::load(&mut data[8..]).unwrap();
+        for item in items {
+            queue_acc.add_item(item, &[], &[], &[], &[]).unwrap();
+        }
+        data
+    }
+
+    fn item(id: u8, slot: u64) -> QueueItem {
+        QueueItem {
+            id: [id; 32],
+            slot,
+            ..QueueItem::default()
+        }
+    }
+
+    #[test]
+    fn stalled_queues_hold_a_reassignable_request() {
+        let scheduled = QueueItem {
+            not_before_slot: 1_000,
+            ..item(1, 100)
+        };
+        let reassigned = QueueItem {
+            reassigned_slot: 200,
+            ..item(2, 100)
+        };
+        let data = queue_account(&[scheduled, reassigned]);
+        let queue = QueueHealth::from_account(Pubkey::new_unique(), &crate::ID, &data).unwrap();
+        assert_eq!(queue.load.as_ref().unwrap().item_count, 2);
+
+        // The scheduled request isn't due, the reassigned one waits from its reassignment
+        assert_eq!(queue.oldest_item_age(300, 0), Some(100));
+        assert!(!queue.is_stalled(300, 0, 5_000));
+        assert!(queue.is_stalled(320, 0, 5_000));
+        assert!(queue.is_stalled(300, 0, 4_000));
+        // The TTL of the scheduled request counts from its slot
+        assert_eq!(queue.oldest_item_age(1_100, 0), Some(100));
+        assert!(queue.is_stalled(1_120, 0, 5_000));
+    }
+
+    #[test]
+    fn delegated_queues_have_an_unknown_load() {
+        let data = queue_account(&[item(1, 100)]);
+        let queue =
+            QueueHealth::from_account(Pubkey::new_unique(), &DELEGATION_PROGRAM_ID, &data).unwrap();
+        assert!(queue.delegated);
+        assert_eq!(queue.load, None);
+        assert_eq!(queue.oldest_item_age(1_000, 0), None);
+        assert!(!queue.is_stalled(1_000, 0, 5_000));
+    }
+
+    #[test]
+    fn legacy_queues_are_reported_as_needing_migration() {
+        // Header of a queue created before `QUEUE_VERSION`, still holding a request
+        let mut data = vec![0u8; 4096];
+        data[..8].copy_from_slice(&AccountDiscriminator::Queue.to_bytes());
+        let header = Queue {
+            item_count: 1,
+            cursor: 512,
+            index: 2,
+            ..Queue::default()
+        };
+        data[8..8 + size_of::<Queue>()].copy_from_slice(bytemuck::bytes_of(&header));
+
+        let legacy = QueueHealth::from_account(Pubkey::new_unique(), &crate::ID, &data).unwrap();
+        assert!(legacy.needs_migration);
+        assert_eq!(legacy.index, 2);
+        assert_eq!(legacy.load, None);
+        assert!(!legacy.is_stalled(1_000, 0, 5_000));
+
+        // Requests to the queue fail until it is migrated
+        let fresh = QueueHealth::from_account(
+            Pubkey::new_unique(),
+            &crate::ID,
+            &queue_account(&[item(1, 150)]),
+        )
+        .unwrap();
+        assert!(!fresh.needs_migration);
+        assert_eq!(pick_best_queue([&legacy], 150, 0, 5_000, false), None);
+        let queues = [legacy, fresh];
+        assert_eq!(
+            pick_best_queue(&queues, 150, 0, 5_000, false),
+            Some(&queues[1])
+        );
+    }
+
+    #[test]
+    fn best_queue_is_the_least_loaded_of_the_queues_that_are_not_stalled() {
+        let stalled = QueueHealth::from_account(
+            Pubkey::new_unique(),
+            &crate::ID,
+            &queue_account(&[item(1, 0)]),
+        )
+        .unwrap();
+        let loaded = QueueHealth::from_account(
+            Pubkey::new_unique(),
+            &crate::ID,
+            &queue_account(&[item(2, 150), item(3, 150)]),
+        )
+        .unwrap();
+        let fresh = QueueHealth::from_account(
+            Pubkey::new_unique(),
+            &crate::ID,
+            &queue_account(&[item(4, 150)]),
+        )
+        .unwrap();
+        let queues = [stalled, loaded, fresh];
+        assert_eq!(
+            pick_best_queue(&queues, 150, 0, 5_000, false),
+            Some(&queues[2])
+        );
+        assert_eq!(
+            pick_best_queue(&queues[..2], 150, 0, 5_000, false),
+            Some(&queues[1])
+        );
+        assert_eq!(pick_best_queue(&queues[..1], 150, 0, 5_000, false), None);
+        assert_eq!(pick_best_queue(&queues, 150, 0, 5_000, true), None);
+    }
+}
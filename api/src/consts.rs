@@ -5,6 +5,9 @@ use solana_program::pubkey::Pubkey;
 /// seed of the oracles account PDA.
 pub const ORACLES: &[u8] = b"oracles";
 
+/// Seed of the admin account PDA.
+pub const ADMIN: &[u8] = b"admin";
+
 /// seed of the oracle data account PDA.
 pub const ORACLE_DATA: &[u8] = b"oracle";
 
@@ -13,16 +16,95 @@ pub const IDENTITY: &[u8] = b"identity";
 
 /// Seed of the queue account PDA.
 pub const QUEUE: &[u8] = b"queue";
+
+/// Seed of the queue account PDA shared by the registered oracles.
+pub const SHARED_QUEUE: &[u8] = b"shared-queue";
+
+/// Seed of the token fee configuration PDA of a queue.
+pub const TOKEN_FEE_CONFIG: &[u8] = b"token-fee";
+
+/// Seed of the token fee escrow account PDA of a queue.
+pub const TOKEN_FEE_ESCROW: &[u8] = b"token-escrow";
+
+/// Seed of the ephemeral balance PDA of a payer.
+pub const EPHEMERAL_BALANCE: &[u8] = b"balance";
+
+/// Seed of the sponsor PDA.
+pub const SPONSOR: &[u8] = b"sponsor";
+
+/// Seed of the per-user request counter PDA of a sponsor.
+pub const SPONSOR_USAGE: &[u8] = b"sponsor-usage";
+
+/// Seed of the pull-mode randomness result PDA.
+pub const RANDOMNESS_RESULT: &[u8] = b"result";
+
+/// Seed of the receipt PDA of a request.
+pub const RECEIPT: &[u8] = b"receipt";
+
+/// Seed of the subscription PDA of a recurring request.
+pub const SUBSCRIPTION: &[u8] = b"subscription";
+
+/// Seed of the randomness beacon PDA of an oracle.
+pub const BEACON: &[u8] = b"beacon";
+
+/// Seed of the PDA signed for by the VRF program when invoking a callback.
+pub const CALLBACK_SIGNER: &[u8] = b"callback-signer";
+
 pub const VRF_PREFIX_CHALLENGE: &[u8] = b"VRF-Ephem-Challenge";
 pub const VRF_PREFIX_HASH_TO_POINT: &[u8] = b"VRF-Ephem-HashToPoint";
 pub const VRF_PREFIX_HASH_TO_SCALAR: &[u8] = b"VRF-Ephem-HashToScalar";
+pub const VRF_PREFIX_RANDOM_WORD: &[u8] = b"VRF-Ephem-RandomWord";
+pub const VRF_PREFIX_BEACON: &[u8] = b"VRF-Ephem-Beacon";
 
 pub const VRF_HIGH_PRIORITY_LAMPORTS_COST: u64 = 800000;
 pub const VRF_LAMPORTS_COST: u64 = 500000;
 
+/// Surcharge per callback account meta stored with a request.
+pub const VRF_LAMPORTS_COST_PER_ACCOUNT_META: u64 = 5000;
+
+/// Surcharge per callback argument or callback PDA seed byte stored with a request.
+pub const VRF_LAMPORTS_COST_PER_ARG_BYTE: u64 = 100;
+
+/// Maximum number of callback accounts of a request.
+pub const MAX_CALLBACK_ACCOUNTS: usize = 20;
+
+/// Maximum number of callback accounts of a request naming an address lookup table.
+pub const MAX_CALLBACK_ACCOUNTS_WITH_LOOKUP_TABLE: usize = 48;
+
+/// Delay between the claims of two oracles ranked next to each other for a request of a shared
+/// queue (see `Oracles::claim_rank`), about a slot.
+pub const SHARED_QUEUE_CLAIM_DELAY_MS: u64 = 400;
+
+/// Number of recent rounds kept by a randomness beacon.
+pub const BEACON_ROUNDS: usize = 16;
+
+/// Layout version of the items of queues, queues created before versioning read 0 and are
+/// converted by `MigrateQueue`.
+pub const QUEUE_VERSION: u8 = 1;
+
+/// Maximum number of random words delivered by a request.
+pub const MAX_RANDOM_WORDS: u8 = 32;
+
 // ~2 minutes on Solana (~500ms/slot) ≈ 240 slots. Round to 240.
 pub const QUEUE_TTL_SLOTS: u64 = 240;
 
+/// TTL of requests scheduled at a unix timestamp, counted from the timestamp.
+pub const QUEUE_TTL_SECONDS: i64 = 120;
+
+/// Share of the TTL of a request, in basis points, after which it can be reassigned to the
+/// queue of another oracle (see `ReassignRequest`), set at `Initialize` and changed with
+/// `SetReassignDelay`.
+pub const REASSIGN_AFTER_TTL_BPS: u64 = 5_000;
+
+/// Byte appended after the callback args of reassigned requests, whatever their `CallbackAbi`.
+pub const REASSIGNED_CALLBACK_FLAG: u8 = 1;
+
+/// Maximum delay of a scheduled request, about a week.
+pub const MAX_REQUEST_DELAY_SLOTS: u64 = 1_512_000;
+
+/// Maximum delay of a request scheduled at a unix timestamp, a week.
+pub const MAX_REQUEST_DELAY_SECONDS: i64 = 604_800;
+
 pub const RISTRETTO_BASEPOINT_POINT: PodRistrettoPoint = PodRistrettoPoint([
     226, 242, 174, 10, 106, 188, 78, 113, 168, 132, 169, 97, 197, 0, 81, 95, 88, 227, 11, 106, 165,
     130, 221, 141, 182, 166, 89, 69, 224, 141, 45, 118,
@@ -31,3 +113,8 @@ pub const RISTRETTO_BASEPOINT_POINT: PodRistrettoPoint = PodRistrettoPoint([
 pub const MAGIC_PROGRAM_ID: Pubkey = pubkey!("Magic11111111111111111111111111111111111111");
 pub const MAGIC_CONTEXT_ID: Pubkey = pubkey!("MagicContext1111111111111111111111111111111");
 pub const DEFAULT_EPHEMERAL_QUEUE: Pubkey = pubkey!("5hBR571xnXppuCPveTrctfTU7tJLSN94nq7kv7FRK5Tc");
+
+/// Unix timestamp (2027-01-01) until which requests of clients predating `fee_payment` are
+/// still accepted on the default ephemeral queue, free of charge as before the ephemeral
+/// balance. Later requests must pay with `FeePayment::EphemeralBalance`.
+pub const LEGACY_EPHEMERAL_REQUESTS_DEADLINE: i64 = 1_798_761_600;
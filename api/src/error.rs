@@ -31,6 +31,34 @@ pub enum EphemeralVrfError {
     InvalidOracleIdentity = 12,
     #[error("Oracle must fulfill in a different slot than the request slot")]
     OracleMustProvideInDifferentSlot = 13,
+    #[error("Queue does not accept token fee payments")]
+    TokenFeeNotConfigured = 14,
+    #[error("Mint is not supported for fee payments")]
+    UnsupportedMint = 15,
+    #[error("Fee payment method is not accepted by this queue")]
+    InvalidFeePayment = 16,
+    #[error("Callback program is not sponsored")]
+    ProgramNotSponsored = 17,
+    #[error("Sponsored request rate limit exceeded")]
+    SponsorRateLimitExceeded = 18,
+    #[error("Request is scheduled too far in the future")]
+    InvalidSchedule = 19,
+    #[error("Request is scheduled later")]
+    RequestNotDue = 20,
+    #[error("Beacon round is not due or its slot hash is unavailable")]
+    InvalidBeaconRound = 21,
+    #[error("Receipt is in use by a pending request")]
+    ReceiptInUse = 22,
+    #[error("Request can't be reassigned yet or to this queue")]
+    RequestNotReassignable = 23,
+    #[error("Queue is paused")]
+    QueuePaused = 24,
+    #[error("Queue holds requests in a previous layout, migrate it first")]
+    QueueMigrationRequired = 25,
+    #[error("Result PDA is in use by a pending request")]
+    ResultInUse = 26,
+    #[error("Request did not opt into the fallback")]
+    FallbackNotRequested = 27,
 }
 
 impl From<EphemeralVrfError> for ProgramError {
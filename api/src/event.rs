@@ -0,0 +1,236 @@
+use crate::prelude::{CallbackAbi, FeePayment};
+use crate::steel::Pubkey;
+#[cfg(feature = "client")]
+use base64::{engine::general_purpose::STANDARD, Engine};
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::log::sol_log_data;
+
+/// Prefix of the log data of events, distinguishing them from other `sol_log_data` entries.
+pub const EVENT_PREFIX: &[u8; 8] = b"vrfevent";
+
+/// Version of the event encoding, bumped on breaking changes. New variants and new events are
+/// only appended.
+pub const EVENT_VERSION: u8 = 1;
+
+/// Events emitted by the program with `sol_log_data`, logged as `Program data: <base64>`.
+///
+/// The data is `EVENT_PREFIX || EVENT_VERSION || borsh(event)`. Callbacks invoked by the
+/// program can log the same data, so events are decoded from the logs of a transaction with
+/// `from_logs`, which only keeps the entries logged by the program itself.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub enum EphemeralVrfEvent {
+    RequestCreated(RequestCreated),
+    RequestFulfilled(RequestFulfilled),
+    RequestPurged(RequestPurged),
+    QueueCreated(QueueCreated),
+    QueueClosed(QueueClosed),
+    OracleModified(OracleModified),
+    RequestReassigned(RequestReassigned),
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct RequestCreated {
+    pub queue: Pubkey,
+    /// Id of the request, also its VRF input.
+    pub request_id: [u8; 32],
+    pub callback_program_id: Pubkey,
+    pub caller_seed: [u8; 32],
+    pub slot: u64,
+    pub fee: u64,
+    pub tip: u64,
+    pub fee_payment: FeePayment,
+    pub high_priority: bool,
+    pub pull: bool,
+    pub num_words: u8,
+    pub callback_abi: CallbackAbi,
+    pub not_before_slot: u64,
+    pub not_before_timestamp: i64,
+    /// Subscription and sequence number of the round, for requests enqueued by a subscription.
+    pub subscription: Option<(Pubkey, u64)>,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct RequestFulfilled {
+    pub queue: Pubkey,
+    pub request_id: [u8; 32],
+    pub oracle: Pubkey,
+    pub callback_program_id: Pubkey,
+    pub slot: u64,
+    /// Hash of the VRF output, the randomness passed to the callback.
+    pub randomness: [u8; 32],
+    /// The randomness was recorded in the result PDA instead of invoking the callback.
+    pub fallback: bool,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct RequestPurged {
+    pub queue: Pubkey,
+    pub request_id: [u8; 32],
+    pub oracle: Pubkey,
+    pub slot: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct QueueCreated {
+    pub queue: Pubkey,
+    /// Oracle owning the queue, default for queues shared by the registered oracles.
+    pub oracle: Pubkey,
+    pub index: u8,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct QueueClosed {
+    pub queue: Pubkey,
+    pub oracle: Pubkey,
+    pub index: u8,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct OracleModified {
+    pub identity: Pubkey,
+    pub vrf_pubkey: [u8; 32],
+    /// True if the oracle was added, false if removed.
+    pub added: bool,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct RequestReassigned {
+    pub request_id: [u8; 32],
+    pub from_queue: Pubkey,
+    pub to_queue: Pubkey,
+    pub slot: u64,
+}
+
+impl EphemeralVrfEvent {
+    /// Log the event.
+    pub fn emit(&self) {
+        sol_log_data(&[&self.to_log_data()]);
+    }
+
+    pub fn to_log_data(&self) -> Vec<u8> {
+        let mut data = EVENT_PREFIX.to_vec();
+        data.push(EVENT_VERSION);
+        self.serialize(&mut data).unwrap();
+        data
+    }
+
+    /// Decodes the data of a `sol_log_data` entry, `None` if it isn't an event of this version.
+    pub fn from_log_data(data: &[u8]) -> Option<Self> {
+        let data = data.strip_prefix(EVENT_PREFIX)?;
+        let (&version, mut data) = data.split_first()?;
+        if version != EVENT_VERSION {
+            return None;
+        }
+        Self::deserialize(&mut data).ok()
+    }
+
+    /// Decodes the events of the logs of a transaction. Only `Program data: <base64>` lines
+    /// logged while the program is the innermost invoked program are decoded, following the
+    /// `Program <id> invoke [n]` and `Program <id> success` lines, so that programs invoked by
+    /// the program, such as callbacks, can't forge events. Logs are read up to a truncation.
+    #[cfg(feature = "client")]
+    pub fn from_logs<S: AsRef<str>>(logs: &[S]) -> Vec<Self> {
+        let program_id = crate::ID.to_string();
+        let mut invoked = Vec::new();
+        let mut events = Vec::new();
+        for log in logs.iter().map(AsRef::as_ref) {
+            if let Some(data) = log.strip_prefix("Program data: ") {
+                if invoked.last() == Some(&program_id.as_str()) {
+                    events.extend(
+                        STANDARD
+                            .decode(data)
+                            .ok()
+                            .and_then(|data| Self::from_log_data(&data)),
+                    );
+                }
+            } else if let Some(program) = log.strip_prefix("Program ") {
+                let mut words = program.split(' ');
+                match (words.next(), words.next()) {
+                    (Some(id), Some("invoke")) => invoked.push(id),
+                    (Some(id), Some("success" | "failed:")) if invoked.last() == Some(&id) => {
+                        invoked.pop();
+                    }
+                    (Some(_), Some("success" | "failed:")) => break,
+                    _ => {}
+                }
+            } else if log == "Log truncated" {
+                break;
+            }
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event() -> EphemeralVrfEvent {
+        EphemeralVrfEvent::RequestReassigned(RequestReassigned {
+            request_id: [1; 32],
+            from_queue: Pubkey::new_from_array([2; 32]),
+            to_queue: Pubkey::new_from_array([3; 32]),
+            slot: 4,
+        })
+    }
+
+    #[test]
+    fn log_data_round_trips() {
+        let data = event().to_log_data();
+        assert!(data.starts_with(EVENT_PREFIX));
+        assert_eq!(data[EVENT_PREFIX.len()], EVENT_VERSION);
+        assert_eq!(EphemeralVrfEvent::from_log_data(&data), Some(event()));
+    }
+
+    #[test]
+    fn rejects_other_log_data() {
+        let data = event().to_log_data();
+        let mut other_version = data.clone();
+        other_version[EVENT_PREFIX.len()] = EVENT_VERSION + 1;
+        assert_eq!(EphemeralVrfEvent::from_log_data(&other_version), None);
+        assert_eq!(
+            EphemeralVrfEvent::from_log_data(&data[EVENT_PREFIX.len()..]),
+            None
+        );
+        assert_eq!(
+            EphemeralVrfEvent::from_log_data(&data[..data.len() - 1]),
+            None
+        );
+    }
+
+    #[cfg(feature = "client")]
+    #[test]
+    fn decodes_only_the_events_logged_by_the_program() {
+        let program = crate::ID.to_string();
+        let callback = Pubkey::new_from_array([9; 32]).to_string();
+        let data = format!("Program data: {}", STANDARD.encode(event().to_log_data()));
+        let logs = [
+            format!("Program {callback} invoke [1]"),
+            data.clone(),
+            format!("Program {program} invoke [2]"),
+            format!("Program {callback} invoke [3]"),
+            data.clone(),
+            format!("Program {callback} consumed 100 of 200000 compute units"),
+            format!("Program {callback} success"),
+            data.clone(),
+            format!("Program {program} success"),
+            data.clone(),
+            format!("Program {callback} success"),
+        ];
+        assert_eq!(EphemeralVrfEvent::from_logs(&logs), vec![event()]);
+    }
+
+    #[cfg(feature = "client")]
+    #[test]
+    fn stops_decoding_at_a_truncation() {
+        let program = crate::ID.to_string();
+        let data = format!("Program data: {}", STANDARD.encode(event().to_log_data()));
+        let logs = [
+            format!("Program {program} invoke [1]"),
+            data.clone(),
+            "Log truncated".to_string(),
+            data,
+        ];
+        assert_eq!(EphemeralVrfEvent::from_logs(&logs), vec![event()]);
+    }
+}
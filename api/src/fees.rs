@@ -0,0 +1,87 @@
+use crate::consts::{
+    VRF_HIGH_PRIORITY_LAMPORTS_COST, VRF_LAMPORTS_COST, VRF_LAMPORTS_COST_PER_ACCOUNT_META,
+    VRF_LAMPORTS_COST_PER_ARG_BYTE,
+};
+use crate::state::PdaAccountMeta;
+
+/// Lamports charged for a randomness request, excluding the tip.
+///
+/// Requests pay a base fee plus a surcharge for every callback account meta and callback data
+/// byte (see `request_data_len`), which take queue space and make the fulfillment transaction
+/// larger.
+pub fn request_fee_lamports(high_priority: bool, metas_len: usize, args_len: usize) -> u64 {
+    let base = if high_priority {
+        VRF_HIGH_PRIORITY_LAMPORTS_COST
+    } else {
+        VRF_LAMPORTS_COST
+    };
+    base.saturating_add(VRF_LAMPORTS_COST_PER_ACCOUNT_META.saturating_mul(metas_len as u64))
+        .saturating_add(VRF_LAMPORTS_COST_PER_ARG_BYTE.saturating_mul(args_len as u64))
+}
+
+/// Tokens charged for a randomness request paid in the fee mint of a queue: its `price`, scaled
+/// by the metas and data bytes of the request like the lamports fee of a regular request.
+pub fn request_fee_tokens(price: u64, metas_len: usize, args_len: usize) -> u64 {
+    let fee = price as u128 * request_fee_lamports(false, metas_len, args_len) as u128
+        / VRF_LAMPORTS_COST as u128;
+    u64::try_from(fee).unwrap_or(u64::MAX)
+}
+
+/// Callback data bytes of a request charged per byte: its callback args and the seeds of its
+/// callback PDA metas.
+pub fn request_data_len(callback_args: &[u8], pda_metas: &[PdaAccountMeta]) -> usize {
+    pda_metas
+        .iter()
+        .flat_map(|meta| meta.seeds.iter())
+        .fold(callback_args.len(), |len, seed| {
+            len.saturating_add(seed.len())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base_fee_depends_on_priority() {
+        assert_eq!(
+            request_fee_lamports(true, 0, 0),
+            VRF_HIGH_PRIORITY_LAMPORTS_COST
+        );
+        assert_eq!(request_fee_lamports(false, 0, 0), VRF_LAMPORTS_COST);
+    }
+
+    #[test]
+    fn metas_and_args_are_charged() {
+        assert_eq!(
+            request_fee_lamports(false, 3, 10),
+            VRF_LAMPORTS_COST
+                + 3 * VRF_LAMPORTS_COST_PER_ACCOUNT_META
+                + 10 * VRF_LAMPORTS_COST_PER_ARG_BYTE
+        );
+    }
+
+    #[test]
+    fn token_price_scales_like_the_lamports_fee() {
+        assert_eq!(request_fee_tokens(1_000, 0, 0), 1_000);
+        // 20 metas and 512 bytes cost 1.2 and 0.1024 times the regular fee on top of it
+        assert_eq!(request_fee_tokens(1_000, 20, 512), 1_000 + 200 + 102);
+        assert_eq!(request_fee_tokens(u64::MAX, 1, 0), u64::MAX);
+    }
+
+    #[test]
+    fn pda_seeds_are_charged_as_data() {
+        let meta = PdaAccountMeta {
+            program_id: [1; 32],
+            seeds: vec![vec![0; 8], vec![0; 32]],
+            is_writable: false,
+        };
+        assert_eq!(request_data_len(&[0; 10], &[meta.clone(), meta]), 90);
+        assert_eq!(request_data_len(&[], &[]), 0);
+    }
+
+    #[test]
+    fn fee_saturates() {
+        assert_eq!(request_fee_lamports(true, usize::MAX, usize::MAX), u64::MAX);
+    }
+}
@@ -1,4 +1,4 @@
-use crate::prelude::SerializableAccountMeta;
+use crate::prelude::{PdaAccountMeta, SerializableAccountMeta};
 use crate::steel::*;
 use borsh::{BorshDeserialize, BorshSerialize};
 use solana_curve25519::ristretto::PodRistrettoPoint;
@@ -18,6 +18,32 @@ pub enum EphemeralVrfInstruction {
     CloseOracleQueue = 7,
     RequestRandomness = 8,
     PurgeExpiredRequests = 9,
+    ConfigureTokenFee = 10,
+    TopUpEphemeralBalance = 11,
+    DelegateEphemeralBalance = 12,
+    UndelegateEphemeralBalance = 13,
+    WithdrawEphemeralBalance = 14,
+    ClaimQueueFees = 15,
+    ConfigureSponsor = 16,
+    ModifySponsor = 17,
+    WithdrawSponsor = 18,
+    ProvideRandomnessFallback = 19,
+    ProvideRandomnessBatch = 20,
+    CreateSubscription = 21,
+    CancelSubscription = 22,
+    TriggerSubscription = 23,
+    ConfigureBeacon = 24,
+    UpdateBeacon = 25,
+    CloseReceipt = 26,
+    InitializeSharedQueue = 27,
+    ReassignRequest = 28,
+    ProposeAdmin = 29,
+    AcceptAdmin = 30,
+    SetAdminRole = 31,
+    SetQueuePaused = 32,
+    MigrateQueue = 33,
+    CloseRandomnessResult = 34,
+    SetReassignDelay = 35,
 }
 
 #[repr(C)]
@@ -50,6 +76,14 @@ impl InitializeOracleQueue {
     }
 }
 
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct InitializeSharedQueue {
+    pub target_size: u32,
+    pub index: u8,
+    pub _padding: [u8; 3],
+}
+
 #[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq, Default)]
 pub struct RequestRandomness {
     pub caller_seed: [u8; 32],
@@ -57,6 +91,175 @@ pub struct RequestRandomness {
     pub callback_discriminator: Vec<u8>,
     pub callback_accounts_metas: Vec<SerializableAccountMeta>,
     pub callback_args: Vec<u8>,
+    pub fee_payment: FeePayment,
+    /// Lamports paid on top of the fee to be served ahead of lower-tipped requests.
+    pub tip: u64,
+    /// Write the randomness to the requester's result PDA instead of invoking a callback.
+    pub pull: bool,
+    /// Callback accounts derived from seeds at fulfillment, passed after the fixed metas.
+    pub callback_pda_metas: Vec<PdaAccountMeta>,
+    /// Address lookup table of the callback accounts, raising the number of callback accounts
+    /// to `MAX_CALLBACK_ACCOUNTS_WITH_LOOKUP_TABLE`.
+    pub lookup_table: Option<Pubkey>,
+    /// Number of random words passed to the callback, derived from the same proof (see
+    /// `verify::random_words`), at most `MAX_RANDOM_WORDS`. 0 and 1 pass the randomness.
+    pub num_words: u8,
+    /// Layout of the callback data.
+    pub callback_abi: CallbackAbi,
+    /// Slot from which the request can be fulfilled, zero to fulfill it as soon as possible.
+    pub not_before_slot: u64,
+    /// Unix timestamp from which the request can be fulfilled, zero to fulfill it as soon as
+    /// possible.
+    pub not_before_timestamp: i64,
+    /// Create the receipt PDA of the request (see `receipt_pda`), tracking its outcome.
+    pub receipt: bool,
+    /// Let the oracle record the randomness in the result PDA, created by the request, in
+    /// place of a callback that keeps failing (see `ProvideRandomnessFallback`).
+    pub fallback: bool,
+}
+
+/// How the fee of a randomness request is paid.
+#[repr(u8)]
+#[derive(
+    BorshSerialize,
+    BorshDeserialize,
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    IntoPrimitive,
+    TryFromPrimitive,
+)]
+#[borsh(use_discriminant = true)]
+pub enum FeePayment {
+    /// Lamports transferred from the signer to the queue.
+    #[default]
+    Lamports = 0,
+    /// Tokens of the queue's fee mint transferred from the signer to the queue's escrow.
+    Token = 1,
+    /// Lamports debited from the signer's delegated ephemeral balance, required on the
+    /// default ephemeral queue.
+    EphemeralBalance = 2,
+    /// Lamports paid by a sponsor whitelisting the callback program.
+    Sponsor = 3,
+}
+
+/// Layout of the data passed to the callback of a request. The data of reassigned requests (see
+/// `ReassignRequest`) is followed by `REASSIGNED_CALLBACK_FLAG` in every layout.
+#[repr(u8)]
+#[derive(
+    BorshSerialize,
+    BorshDeserialize,
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    IntoPrimitive,
+    TryFromPrimitive,
+)]
+#[borsh(use_discriminant = true)]
+pub enum CallbackAbi {
+    /// `discriminator || random words || callback_args`.
+    #[default]
+    Randomness = 0,
+    /// `discriminator || FulfillmentHeader || random words || callback_args`.
+    Header = 1,
+    /// `discriminator || FulfillmentHeader || FulfillmentProof || random words || callback_args`.
+    Proof = 2,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct ReassignRequest {
+    pub request_id: [u8; 32],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct ProposeAdmin {
+    /// New authority, the default pubkey cancels a pending transfer.
+    pub authority: Pubkey,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct AcceptAdmin {}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct SetAdminRole {
+    /// New holder of the role, the default pubkey returns it to the authority.
+    pub member: Pubkey,
+    /// `AdminRole` to assign.
+    pub role: u8,
+    pub _padding: [u8; 7],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct SetQueuePaused {
+    pub paused: u8,
+    pub _padding: [u8; 7],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct MigrateQueue {}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct SetReassignDelay {
+    /// Share of the TTL of a request, in basis points, after which it can be reassigned.
+    pub after_ttl_bps: u64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct CloseRandomnessResult {}
+
+/// Header describing the fulfillment of a request, passed to callbacks opting into
+/// `CallbackAbi::Header`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+pub struct FulfillmentHeader {
+    pub request_id: [u8; 32],
+    /// Slot in which the request was made
+    pub request_slot: u64,
+    /// Slot in which the request was fulfilled
+    pub fulfillment_slot: u64,
+    pub oracle: Pubkey,
+    /// Queue the request was fulfilled from, differs from the queue returned by the request
+    /// once reassigned (see `ReassignRequest`)
+    pub queue: Pubkey,
+    /// VRF output, the randomness is its hash
+    pub output: [u8; 32],
+}
+
+impl FulfillmentHeader {
+    pub fn as_bytes(&self) -> &[u8] {
+        bytemuck::bytes_of(self)
+    }
+}
+
+/// Proof of the output of the header, passed to callbacks opting into `CallbackAbi::Proof` so
+/// that they can verify it themselves against the VRF public key of the oracle.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+pub struct FulfillmentProof {
+    pub commitment_base_compressed: PodRistrettoPoint,
+    pub commitment_hash_compressed: PodRistrettoPoint,
+    pub scalar: PodScalar,
+    pub vrf_pubkey: PodRistrettoPoint,
+}
+
+impl FulfillmentProof {
+    pub fn as_bytes(&self) -> &[u8] {
+        bytemuck::bytes_of(self)
+    }
 }
 
 pub struct PdaSeeds;
@@ -76,6 +279,49 @@ pub struct ProvideRandomness {
     pub scalar: PodScalar,
 }
 
+/// Same proof as `ProvideRandomness`, recorded in the requester's result PDA instead of
+/// invoking the callback.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct ProvideRandomnessFallback {
+    pub randomness: ProvideRandomness,
+}
+
+/// Proof of one request of a `ProvideRandomnessBatch`, with the number of callback accounts
+/// following its callback program in the instruction accounts.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct BatchedRandomness {
+    pub randomness: ProvideRandomness,
+    pub accounts_len: u8,
+}
+
+/// Fulfills several requests of the same queue, the data is a list of `BatchedRandomness`.
+pub struct ProvideRandomnessBatch;
+impl ProvideRandomnessBatch {
+    pub fn to_bytes(items: &[BatchedRandomness]) -> Vec<u8> {
+        let mut v = vec![
+            EphemeralVrfInstruction::ProvideRandomnessBatch as u8,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+        ];
+        v.extend_from_slice(bytemuck::cast_slice(items));
+        v
+    }
+
+    pub fn try_from_bytes(data: &[u8]) -> Result<&[BatchedRandomness], ProgramError> {
+        match bytemuck::try_cast_slice::<u8, BatchedRandomness>(data) {
+            Ok(items) if !items.is_empty() => Ok(items),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
+
 #[repr(C)]
 #[derive(Clone, Copy, Debug, Pod, Zeroable)]
 pub struct DelegateOracleQueue {
@@ -100,6 +346,124 @@ pub struct PurgeExpiredRequests {
     pub index: u8,
 }
 
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct ConfigureTokenFee {
+    pub price: u64,
+    pub index: u8,
+    pub _padding: [u8; 7],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct TopUpEphemeralBalance {
+    pub amount: u64,
+    pub index: u8,
+    pub _padding: [u8; 7],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct DelegateEphemeralBalance {
+    pub index: u8,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct UndelegateEphemeralBalance {
+    pub index: u8,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct WithdrawEphemeralBalance {
+    pub amount: u64,
+    pub index: u8,
+    pub _padding: [u8; 7],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct ClaimQueueFees {
+    pub index: u8,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct ConfigureSponsor {
+    pub window_slots: u64,
+    pub max_requests_per_user: u32,
+    pub index: u8,
+    pub _padding: [u8; 3],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct ModifySponsor {
+    pub program_id: Pubkey,
+    pub max_requests_per_window: u32,
+    pub index: u8,
+    /// 0 to add or update the program, 1 to remove it
+    pub operation: u8,
+    pub _padding: [u8; 2],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct WithdrawSponsor {
+    pub amount: u64,
+    pub index: u8,
+    pub _padding: [u8; 7],
+}
+
+/// Creation of a recurring request, each round is a regular request of the queue.
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq, Default)]
+pub struct CreateSubscription {
+    pub caller_seed: [u8; 32],
+    pub callback_program_id: Pubkey,
+    pub callback_discriminator: Vec<u8>,
+    pub callback_accounts_metas: Vec<SerializableAccountMeta>,
+    pub callback_args: Vec<u8>,
+    pub callback_pda_metas: Vec<PdaAccountMeta>,
+    pub num_words: u8,
+    pub callback_abi: CallbackAbi,
+    /// Slots between two rounds.
+    pub interval_slots: u64,
+    /// Slot of the first round, zero for the current slot.
+    pub first_round_slot: u64,
+    /// Lamports transferred from the payer to pay the fees of the rounds.
+    pub budget: u64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct CancelSubscription {}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct TriggerSubscription {}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct ConfigureBeacon {
+    pub interval_slots: u64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct UpdateBeacon {
+    /// Slot whose hash, still in the SlotHashes sysvar, is the input of the round.
+    pub slot: u64,
+    pub output: PodRistrettoPoint,
+    pub commitment_base_compressed: PodRistrettoPoint,
+    pub commitment_hash_compressed: PodRistrettoPoint,
+    pub scalar: PodScalar,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct CloseReceipt {}
+
 instruction8!(EphemeralVrfInstruction, Initialize);
 instruction8!(EphemeralVrfInstruction, ModifyOracle);
 instruction8!(EphemeralVrfInstruction, InitializeOracleQueue);
@@ -108,6 +472,30 @@ instruction8!(EphemeralVrfInstruction, DelegateOracleQueue);
 instruction8!(EphemeralVrfInstruction, UndelegateOracleQueue);
 instruction8!(EphemeralVrfInstruction, CloseOracleQueue);
 instruction8!(EphemeralVrfInstruction, PurgeExpiredRequests);
+instruction8!(EphemeralVrfInstruction, ConfigureTokenFee);
+instruction8!(EphemeralVrfInstruction, TopUpEphemeralBalance);
+instruction8!(EphemeralVrfInstruction, DelegateEphemeralBalance);
+instruction8!(EphemeralVrfInstruction, UndelegateEphemeralBalance);
+instruction8!(EphemeralVrfInstruction, WithdrawEphemeralBalance);
+instruction8!(EphemeralVrfInstruction, ClaimQueueFees);
+instruction8!(EphemeralVrfInstruction, ConfigureSponsor);
+instruction8!(EphemeralVrfInstruction, ModifySponsor);
+instruction8!(EphemeralVrfInstruction, WithdrawSponsor);
+instruction8!(EphemeralVrfInstruction, ProvideRandomnessFallback);
+instruction8!(EphemeralVrfInstruction, CancelSubscription);
+instruction8!(EphemeralVrfInstruction, TriggerSubscription);
+instruction8!(EphemeralVrfInstruction, ConfigureBeacon);
+instruction8!(EphemeralVrfInstruction, UpdateBeacon);
+instruction8!(EphemeralVrfInstruction, CloseReceipt);
+instruction8!(EphemeralVrfInstruction, InitializeSharedQueue);
+instruction8!(EphemeralVrfInstruction, ReassignRequest);
+instruction8!(EphemeralVrfInstruction, ProposeAdmin);
+instruction8!(EphemeralVrfInstruction, AcceptAdmin);
+instruction8!(EphemeralVrfInstruction, SetAdminRole);
+instruction8!(EphemeralVrfInstruction, SetQueuePaused);
+instruction8!(EphemeralVrfInstruction, MigrateQueue);
+instruction8!(EphemeralVrfInstruction, CloseRandomnessResult);
+instruction8!(EphemeralVrfInstruction, SetReassignDelay);
 
 impl RequestRandomness {
     pub fn to_bytes(&self) -> Vec<u8> {
@@ -125,7 +513,71 @@ impl RequestRandomness {
         bytes
     }
 
+    /// Whether the request was built by a client predating `fee_payment`, its data ending
+    /// after `callback_args`.
+    pub fn is_legacy(mut bytes: &[u8]) -> bool {
+        let bytes = &mut bytes;
+        <(
+            [u8; 32],
+            Pubkey,
+            Vec<u8>,
+            Vec<SerializableAccountMeta>,
+            Vec<u8>,
+        )>::deserialize(bytes)
+        .is_ok_and(|_| bytes.is_empty())
+    }
+
+    /// Deserializes the request, defaulting the fields appended after the initial release
+    /// so that instructions built by older clients keep working.
     pub fn try_from_bytes(mut bytes: &[u8]) -> Result<Self, std::io::Error> {
-        Self::deserialize(&mut bytes)
+        let bytes = &mut bytes;
+        Ok(Self {
+            caller_seed: BorshDeserialize::deserialize(bytes)?,
+            callback_program_id: BorshDeserialize::deserialize(bytes)?,
+            callback_discriminator: BorshDeserialize::deserialize(bytes)?,
+            callback_accounts_metas: BorshDeserialize::deserialize(bytes)?,
+            callback_args: BorshDeserialize::deserialize(bytes)?,
+            fee_payment: deserialize_or_default(bytes)?,
+            tip: deserialize_or_default(bytes)?,
+            pull: deserialize_or_default(bytes)?,
+            callback_pda_metas: deserialize_or_default(bytes)?,
+            lookup_table: deserialize_or_default(bytes)?,
+            num_words: deserialize_or_default(bytes)?,
+            callback_abi: deserialize_or_default(bytes)?,
+            not_before_slot: deserialize_or_default(bytes)?,
+            not_before_timestamp: deserialize_or_default(bytes)?,
+            receipt: deserialize_or_default(bytes)?,
+            fallback: deserialize_or_default(bytes)?,
+        })
+    }
+}
+
+impl CreateSubscription {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![
+            EphemeralVrfInstruction::CreateSubscription as u8,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+        ];
+        self.serialize(&mut bytes).unwrap();
+        bytes
+    }
+
+    pub fn try_from_bytes(bytes: &[u8]) -> Result<Self, std::io::Error> {
+        Self::try_from_slice(bytes)
+    }
+}
+
+fn deserialize_or_default<T: BorshDeserialize + Default>(
+    bytes: &mut &[u8],
+) -> Result<T, std::io::Error> {
+    if bytes.is_empty() {
+        return Ok(T::default());
     }
+    T::deserialize(bytes)
 }
@@ -1,12 +1,17 @@
 #[macro_use]
 pub mod macros;
 
+#[cfg(feature = "client")]
+pub mod client;
 pub mod consts;
 pub mod error;
+pub mod event;
+pub mod fees;
 pub mod instruction;
 pub mod loaders;
 pub mod pda;
 pub mod sdk;
+pub mod slot_hashes;
 pub mod state;
 pub mod steel;
 pub mod verify;
@@ -14,9 +19,12 @@ pub mod verify;
 pub mod prelude {
     pub use crate::consts::*;
     pub use crate::error::*;
+    pub use crate::event::*;
+    pub use crate::fees::*;
     pub use crate::instruction::*;
     pub use crate::pda::*;
     pub use crate::sdk::*;
+    pub use crate::slot_hashes::*;
     pub use crate::state::*;
     pub use crate::steel::*;
 }
@@ -4,7 +4,30 @@ use solana_program::program_error::ProgramError;
 use solana_program::pubkey::Pubkey;
 use solana_sdk_ids::bpf_loader_upgradeable;
 
-use crate::steel::{trace, AccountInfo};
+use crate::consts::ADMIN;
+use crate::error::EphemeralVrfError;
+use crate::state::{Admin, AdminRole};
+use crate::steel::{trace, AccountInfo, AccountInfoValidation, AsAccount};
+
+/// Check that the signer is the admin authority or holds `role` in the admin account
+pub fn check_admin_role(
+    admin_info: &AccountInfo,
+    signer_info: &AccountInfo,
+    role: AdminRole,
+) -> Result<(), ProgramError> {
+    signer_info.is_signer()?;
+    admin_info.has_seeds(&[ADMIN], &crate::ID)?;
+    let admin = admin_info.as_account::<Admin>(&crate::ID)?;
+    if !admin.is_authorized(role, signer_info.key) {
+        msg!(
+            "Signer {} is neither the admin authority nor the {:?}",
+            signer_info.key,
+            role
+        );
+        return Err(EphemeralVrfError::Unauthorized.into());
+    }
+    Ok(())
+}
 
 /// Get the program upgrade authority for a given program
 pub fn load_program_upgrade_authority(
@@ -10,13 +10,19 @@ use solana_curve25519::ristretto::PodRistrettoPoint;
 use solana_curve25519::scalar::PodScalar;
 use solana_sdk_ids::bpf_loader_upgradeable;
 
+/// Initializes the program, `signer` must be the program upgrade authority and becomes the admin
+/// authority.
 pub fn initialize(signer: Pubkey) -> Instruction {
+    let program_data_address =
+        Pubkey::find_program_address(&[crate::ID.as_ref()], &bpf_loader_upgradeable::id()).0;
     Instruction {
         program_id: ID,
         accounts: vec![
             AccountMeta::new(signer, true),
             AccountMeta::new(oracles_pda().0, false),
             AccountMeta::new_readonly(system_program::ID, false),
+            AccountMeta::new(admin_pda().0, false),
+            AccountMeta::new_readonly(program_data_address, false),
         ],
         data: Initialize {}.to_bytes(),
     }
@@ -24,15 +30,13 @@ pub fn initialize(signer: Pubkey) -> Instruction {
 
 pub fn add_oracle(signer: Pubkey, identity: Pubkey, oracle_pubkey: [u8; 32]) -> Instruction {
     let oracle_pubkey = PodRistrettoPoint(oracle_pubkey);
-    let program_data_address =
-        Pubkey::find_program_address(&[crate::ID.as_ref()], &bpf_loader_upgradeable::id()).0;
     Instruction {
         program_id: crate::ID,
         accounts: vec![
             AccountMeta::new(signer, true),
             AccountMeta::new(oracles_pda().0, false),
             AccountMeta::new(oracle_data_pda(&identity).0, false),
-            AccountMeta::new_readonly(program_data_address, false),
+            AccountMeta::new_readonly(admin_pda().0, false),
             AccountMeta::new_readonly(system_program::ID, false),
         ],
         data: ModifyOracle {
@@ -45,15 +49,13 @@ pub fn add_oracle(signer: Pubkey, identity: Pubkey, oracle_pubkey: [u8; 32]) ->
 }
 
 pub fn remove_oracle(signer: Pubkey, identity: Pubkey) -> Instruction {
-    let program_data_address =
-        Pubkey::find_program_address(&[crate::ID.as_ref()], &bpf_loader_upgradeable::id()).0;
     Instruction {
         program_id: crate::ID,
         accounts: vec![
             AccountMeta::new(signer, true),
             AccountMeta::new(oracles_pda().0, false),
             AccountMeta::new(oracle_data_pda(&identity).0, false),
-            AccountMeta::new_readonly(program_data_address, false),
+            AccountMeta::new_readonly(admin_pda().0, false),
             AccountMeta::new_readonly(system_program::ID, false),
         ],
         data: ModifyOracle {
@@ -97,6 +99,33 @@ pub fn initialize_oracle_queue(
     ixs
 }
 
+/// Creates a queue shared by the registered oracles, signed by the program upgrade authority.
+pub fn initialize_shared_queue(
+    admin: Pubkey,
+    index: u8,
+    bytes_to_allocate: Option<u32>,
+) -> Vec<Instruction> {
+    let target_size = bytes_to_allocate.unwrap_or(9500);
+    let inits = target_size.div_ceil(10240);
+    (0..inits)
+        .map(|_| Instruction {
+            program_id: ID,
+            accounts: vec![
+                AccountMeta::new(admin, true),
+                AccountMeta::new(shared_queue_pda(index).0, false),
+                AccountMeta::new_readonly(admin_pda().0, false),
+                AccountMeta::new_readonly(system_program::ID, false),
+            ],
+            data: InitializeSharedQueue {
+                target_size,
+                index,
+                _padding: [0; 3],
+            }
+            .to_bytes(),
+        })
+        .collect()
+}
+
 #[allow(clippy::too_many_arguments)]
 pub fn provide_randomness(
     oracle_identity: Pubkey,
@@ -128,6 +157,72 @@ pub fn provide_randomness(
     }
 }
 
+/// Fulfills a request without invoking its callback, recording the randomness in the result PDA
+/// of `requester` (the callback program) and the request's caller seed.
+#[allow(clippy::too_many_arguments)]
+pub fn provide_randomness_fallback(
+    oracle_identity: Pubkey,
+    oracle_queue: Pubkey,
+    requester: Pubkey,
+    caller_seed: [u8; 32],
+    rnd_seed: [u8; 32],
+    output: PodRistrettoPoint,
+    commitment_base_compressed: PodRistrettoPoint,
+    commitment_hash_compressed: PodRistrettoPoint,
+    s: PodScalar,
+) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(oracle_identity, true),
+            AccountMeta::new_readonly(oracle_data_pda(&oracle_identity).0, false),
+            AccountMeta::new(oracle_queue, false),
+            AccountMeta::new(randomness_result_pda(&requester, &caller_seed).0, false),
+        ],
+        data: ProvideRandomnessFallback {
+            randomness: ProvideRandomness {
+                input: rnd_seed,
+                output,
+                commitment_base_compressed,
+                commitment_hash_compressed,
+                scalar: s,
+            },
+        }
+        .to_bytes(),
+    }
+}
+
+/// Fulfills several requests of a queue, each given by its proof, its callback program and the
+/// accounts following the callback program in `provide_randomness` (callback accounts, then
+/// token fee accounts).
+pub fn provide_randomness_batch(
+    oracle_identity: Pubkey,
+    oracle_queue: Pubkey,
+    requests: &[(ProvideRandomness, Pubkey, Vec<AccountMeta>)],
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(oracle_identity, true),
+        AccountMeta::new_readonly(program_identity_pda().0, false),
+        AccountMeta::new_readonly(oracle_data_pda(&oracle_identity).0, false),
+        AccountMeta::new(oracle_queue, false),
+    ];
+    let mut items = Vec::with_capacity(requests.len());
+    for (randomness, callback_program_id, request_accounts) in requests {
+        accounts.push(AccountMeta::new_readonly(*callback_program_id, false));
+        accounts.extend_from_slice(request_accounts);
+        items.push(BatchedRandomness {
+            randomness: *randomness,
+            accounts_len: u8::try_from(request_accounts.len())
+                .expect("too many accounts for a batched request"),
+        });
+    }
+    Instruction {
+        program_id: crate::ID,
+        accounts,
+        data: ProvideRandomnessBatch::to_bytes(&items),
+    }
+}
+
 pub fn purge_expired_requests(identity: Pubkey, index: u8) -> Instruction {
     Instruction {
         program_id: crate::ID,
@@ -139,6 +234,20 @@ pub fn purge_expired_requests(identity: Pubkey, index: u8) -> Instruction {
     }
 }
 
+/// Purges the expired requests of a shared queue, paying their fees to the registered oracle
+/// `identity`.
+pub fn purge_expired_shared_requests(identity: Pubkey, index: u8) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(identity, true),
+            AccountMeta::new(shared_queue_pda(index).0, false),
+            AccountMeta::new_readonly(oracle_data_pda(&identity).0, false),
+        ],
+        data: PurgeExpiredRequests { index }.to_bytes(),
+    }
+}
+
 pub fn delegate_oracle_queue(signer: Pubkey, queue: Pubkey, index: u8) -> Instruction {
     let buffer = delegate_buffer_pda_from_delegated_account_and_owner_program(&queue, &crate::ID);
     let delegation_record = delegation_record_pda_from_delegated_account(&queue);
@@ -183,3 +292,490 @@ pub fn close_oracle_queue(identity: Pubkey, index: u8) -> Instruction {
         data: CloseOracleQueue { index }.to_bytes(),
     }
 }
+
+pub fn configure_token_fee(
+    identity: Pubkey,
+    index: u8,
+    mint: Pubkey,
+    token_program: Pubkey,
+    price: u64,
+) -> Instruction {
+    let queue = oracle_queue_pda(&identity, index).0;
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(identity, true),
+            AccountMeta::new_readonly(queue, false),
+            AccountMeta::new(token_fee_config_pda(&queue).0, false),
+            AccountMeta::new(token_fee_escrow_pda(&queue).0, false),
+            AccountMeta::new_readonly(mint, false),
+            AccountMeta::new_readonly(token_program, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+        data: ConfigureTokenFee {
+            price,
+            index,
+            _padding: [0; 7],
+        }
+        .to_bytes(),
+    }
+}
+
+/// Accounts to append to `provide_randomness` and `purge_expired_requests` when the
+/// queue holds requests paid in tokens. `oracle_token_account` receives the fees, except the
+/// fees of expired requests refunded to the token account of their receipt payer when the
+/// receipt and that account are passed to the purge.
+pub fn token_fee_accounts(
+    queue: &Pubkey,
+    oracle_token_account: Pubkey,
+    mint: Pubkey,
+    token_program: Pubkey,
+) -> Vec<AccountMeta> {
+    vec![
+        AccountMeta::new_readonly(token_fee_config_pda(queue).0, false),
+        AccountMeta::new(token_fee_escrow_pda(queue).0, false),
+        AccountMeta::new(oracle_token_account, false),
+        AccountMeta::new_readonly(mint, false),
+        AccountMeta::new_readonly(token_program, false),
+    ]
+}
+
+/// Configures the token fees of the shared queue `index`, signed by the admin fee manager.
+pub fn configure_shared_queue_token_fee(
+    fee_manager: Pubkey,
+    index: u8,
+    mint: Pubkey,
+    token_program: Pubkey,
+    price: u64,
+) -> Instruction {
+    let queue = shared_queue_pda(index).0;
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(fee_manager, true),
+            AccountMeta::new_readonly(queue, false),
+            AccountMeta::new(token_fee_config_pda(&queue).0, false),
+            AccountMeta::new(token_fee_escrow_pda(&queue).0, false),
+            AccountMeta::new_readonly(mint, false),
+            AccountMeta::new_readonly(token_program, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+            AccountMeta::new_readonly(admin_pda().0, false),
+        ],
+        data: ConfigureTokenFee {
+            price,
+            index,
+            _padding: [0; 7],
+        }
+        .to_bytes(),
+    }
+}
+
+pub fn top_up_ephemeral_balance(payer: Pubkey, index: u8, amount: u64) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(payer, true),
+            AccountMeta::new(ephemeral_balance_pda(&payer, index).0, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+        data: TopUpEphemeralBalance {
+            amount,
+            index,
+            _padding: [0; 7],
+        }
+        .to_bytes(),
+    }
+}
+
+pub fn delegate_ephemeral_balance(payer: Pubkey, index: u8) -> Instruction {
+    let balance = ephemeral_balance_pda(&payer, index).0;
+    let buffer = delegate_buffer_pda_from_delegated_account_and_owner_program(&balance, &crate::ID);
+    let delegation_record = delegation_record_pda_from_delegated_account(&balance);
+    let delegation_metadata = delegation_metadata_pda_from_delegated_account(&balance);
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(payer, true),
+            AccountMeta::new(balance, false),
+            AccountMeta::new(buffer, false),
+            AccountMeta::new(delegation_record, false),
+            AccountMeta::new(delegation_metadata, false),
+            AccountMeta::new_readonly(DELEGATION_PROGRAM_ID, false),
+            AccountMeta::new_readonly(crate::ID, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+        data: DelegateEphemeralBalance { index }.to_bytes(),
+    }
+}
+
+pub fn undelegate_ephemeral_balance(payer: Pubkey, index: u8) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(payer, true),
+            AccountMeta::new(ephemeral_balance_pda(&payer, index).0, false),
+            AccountMeta::new(MAGIC_CONTEXT_ID, false),
+            AccountMeta::new_readonly(MAGIC_PROGRAM_ID, false),
+        ],
+        data: UndelegateEphemeralBalance { index }.to_bytes(),
+    }
+}
+
+pub fn withdraw_ephemeral_balance(payer: Pubkey, index: u8, amount: u64) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(payer, true),
+            AccountMeta::new(ephemeral_balance_pda(&payer, index).0, false),
+        ],
+        data: WithdrawEphemeralBalance {
+            amount,
+            index,
+            _padding: [0; 7],
+        }
+        .to_bytes(),
+    }
+}
+
+pub fn claim_queue_fees(identity: Pubkey, index: u8) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(identity, true),
+            AccountMeta::new(oracle_queue_pda(&identity, index).0, false),
+        ],
+        data: ClaimQueueFees { index }.to_bytes(),
+    }
+}
+
+pub fn configure_sponsor(
+    authority: Pubkey,
+    index: u8,
+    window_slots: u64,
+    max_requests_per_user: u32,
+) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(authority, true),
+            AccountMeta::new(sponsor_pda(&authority, index).0, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+        data: ConfigureSponsor {
+            window_slots,
+            max_requests_per_user,
+            index,
+            _padding: [0; 3],
+        }
+        .to_bytes(),
+    }
+}
+
+pub fn add_sponsored_program(
+    authority: Pubkey,
+    index: u8,
+    program_id: Pubkey,
+    max_requests_per_window: u32,
+) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(authority, true),
+            AccountMeta::new(sponsor_pda(&authority, index).0, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+        data: ModifySponsor {
+            program_id,
+            max_requests_per_window,
+            index,
+            operation: 0,
+            _padding: [0; 2],
+        }
+        .to_bytes(),
+    }
+}
+
+pub fn remove_sponsored_program(authority: Pubkey, index: u8, program_id: Pubkey) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(authority, true),
+            AccountMeta::new(sponsor_pda(&authority, index).0, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+        data: ModifySponsor {
+            program_id,
+            max_requests_per_window: 0,
+            index,
+            operation: 1,
+            _padding: [0; 2],
+        }
+        .to_bytes(),
+    }
+}
+
+pub fn withdraw_sponsor(authority: Pubkey, index: u8, amount: u64) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(authority, true),
+            AccountMeta::new(sponsor_pda(&authority, index).0, false),
+        ],
+        data: WithdrawSponsor {
+            amount,
+            index,
+            _padding: [0; 7],
+        }
+        .to_bytes(),
+    }
+}
+
+pub fn create_subscription(payer: Pubkey, queue: Pubkey, args: CreateSubscription) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(payer, true),
+            AccountMeta::new_readonly(
+                Pubkey::find_program_address(&[IDENTITY], &args.callback_program_id).0,
+                true,
+            ),
+            AccountMeta::new(
+                subscription_pda(&args.callback_program_id, &args.caller_seed).0,
+                false,
+            ),
+            AccountMeta::new_readonly(queue, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+        data: args.to_bytes(),
+    }
+}
+
+pub fn cancel_subscription(authority: Pubkey, subscription: Pubkey) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(authority, true),
+            AccountMeta::new(subscription, false),
+        ],
+        data: CancelSubscription {}.to_bytes(),
+    }
+}
+
+pub fn trigger_subscription(
+    oracle_identity: Pubkey,
+    oracle_queue: Pubkey,
+    subscription: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(oracle_identity, true),
+            AccountMeta::new_readonly(oracle_data_pda(&oracle_identity).0, false),
+            AccountMeta::new(oracle_queue, false),
+            AccountMeta::new(subscription, false),
+            AccountMeta::new_readonly(sysvar::slot_hashes::ID, false),
+        ],
+        data: TriggerSubscription {}.to_bytes(),
+    }
+}
+
+pub fn configure_beacon(identity: Pubkey, interval_slots: u64) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(identity, true),
+            AccountMeta::new_readonly(oracle_data_pda(&identity).0, false),
+            AccountMeta::new(beacon_pda(&identity).0, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+        data: ConfigureBeacon { interval_slots }.to_bytes(),
+    }
+}
+
+pub fn close_receipt(payer: Pubkey, receipt: Pubkey, oracle_queue: Pubkey) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(payer, true),
+            AccountMeta::new(receipt, false),
+            AccountMeta::new_readonly(oracle_queue, false),
+        ],
+        data: CloseReceipt {}.to_bytes(),
+    }
+}
+
+pub fn close_randomness_result(
+    payer: Pubkey,
+    requester: Pubkey,
+    caller_seed: [u8; 32],
+) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(payer, true),
+            AccountMeta::new(randomness_result_pda(&requester, &caller_seed).0, false),
+        ],
+        data: CloseRandomnessResult {}.to_bytes(),
+    }
+}
+
+/// Moves a stale request of the queue `source_index` of `source_oracle` to the queue
+/// `target_index` of `target_oracle`, any other oracle registered in `Oracles`. Requests paid
+/// in tokens need `reassign_token_fee_accounts` appended, and requests created with a receipt
+/// their receipt PDA last.
+pub fn reassign_request(
+    source_oracle: Pubkey,
+    source_index: u8,
+    target_oracle: Pubkey,
+    target_index: u8,
+    request_id: [u8; 32],
+) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new_readonly(source_oracle, false),
+            AccountMeta::new(oracle_queue_pda(&source_oracle, source_index).0, false),
+            AccountMeta::new_readonly(target_oracle, false),
+            AccountMeta::new_readonly(oracle_data_pda(&target_oracle).0, false),
+            AccountMeta::new(oracle_queue_pda(&target_oracle, target_index).0, false),
+            AccountMeta::new_readonly(oracles_pda().0, false),
+            AccountMeta::new_readonly(admin_pda().0, false),
+        ],
+        data: ReassignRequest { request_id }.to_bytes(),
+    }
+}
+
+/// Token fee accounts moving the fee of a reassigned request between the escrows of two queues.
+pub fn reassign_token_fee_accounts(
+    source_queue: &Pubkey,
+    target_queue: &Pubkey,
+    mint: Pubkey,
+    token_program: Pubkey,
+) -> Vec<AccountMeta> {
+    let mut accounts = token_fee_accounts(
+        source_queue,
+        token_fee_escrow_pda(target_queue).0,
+        mint,
+        token_program,
+    );
+    accounts.push(AccountMeta::new_readonly(
+        token_fee_config_pda(target_queue).0,
+        false,
+    ));
+    accounts
+}
+
+pub fn update_beacon(
+    identity: Pubkey,
+    slot: u64,
+    output: PodRistrettoPoint,
+    commitment_base_compressed: PodRistrettoPoint,
+    commitment_hash_compressed: PodRistrettoPoint,
+    scalar: PodScalar,
+) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new_readonly(identity, true),
+            AccountMeta::new_readonly(oracle_data_pda(&identity).0, false),
+            AccountMeta::new(beacon_pda(&identity).0, false),
+            AccountMeta::new_readonly(sysvar::slot_hashes::ID, false),
+        ],
+        data: UpdateBeacon {
+            slot,
+            output,
+            commitment_base_compressed,
+            commitment_hash_compressed,
+            scalar,
+        }
+        .to_bytes(),
+    }
+}
+
+/// Proposes `new_authority` as admin authority, the default pubkey cancels a pending transfer.
+pub fn propose_admin(authority: Pubkey, new_authority: Pubkey) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new_readonly(authority, true),
+            AccountMeta::new(admin_pda().0, false),
+        ],
+        data: ProposeAdmin {
+            authority: new_authority,
+        }
+        .to_bytes(),
+    }
+}
+
+/// Accepts the admin authority proposed to `new_authority`.
+pub fn accept_admin(new_authority: Pubkey) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new_readonly(new_authority, true),
+            AccountMeta::new(admin_pda().0, false),
+        ],
+        data: AcceptAdmin {}.to_bytes(),
+    }
+}
+
+/// Assigns `role` to `member`, the default pubkey returns it to the authority.
+pub fn set_admin_role(authority: Pubkey, role: AdminRole, member: Pubkey) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new_readonly(authority, true),
+            AccountMeta::new(admin_pda().0, false),
+        ],
+        data: SetAdminRole {
+            member,
+            role: role.into(),
+            _padding: [0; 7],
+        }
+        .to_bytes(),
+    }
+}
+
+/// Pauses or resumes the intake of requests of `queue`, signed by the admin pauser.
+pub fn set_queue_paused(pauser: Pubkey, queue: Pubkey, paused: bool) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new_readonly(pauser, true),
+            AccountMeta::new_readonly(admin_pda().0, false),
+            AccountMeta::new(queue, false),
+        ],
+        data: SetQueuePaused {
+            paused: paused as u8,
+            _padding: [0; 7],
+        }
+        .to_bytes(),
+    }
+}
+
+/// Sets the share of the TTL of a request after which it can be reassigned, signed by the admin
+/// authority or oracle manager.
+pub fn set_reassign_delay(signer: Pubkey, after_ttl_bps: u64) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new_readonly(signer, true),
+            AccountMeta::new(admin_pda().0, false),
+        ],
+        data: SetReassignDelay { after_ttl_bps }.to_bytes(),
+    }
+}
+
+/// Converts the requests of a queue created before `QUEUE_VERSION` to the current layout,
+/// `payer` funds the growth of the account. Repeat until the queue is migrated.
+pub fn migrate_queue(payer: Pubkey, queue: Pubkey) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(payer, true),
+            AccountMeta::new(queue, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+        data: MigrateQueue {}.to_bytes(),
+    }
+}
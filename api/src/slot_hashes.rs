@@ -0,0 +1,106 @@
+//! Lookups in the data of the SlotHashes sysvar, a length followed by `(slot, hash)` entries
+//! from the most recent one.
+
+/// Hash of the slot of a scheduled round in the SlotHashes sysvar.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RoundHash {
+    /// No slot from the slot of the round has a hash yet.
+    Pending,
+    /// First slot from the slot of the round with a hash, and its hash.
+    Found(u64, [u8; 32]),
+    /// The hashes from the slot of the round left the sysvar, holding the slots from the one
+    /// given.
+    Expired(u64),
+}
+
+fn entries(data: &[u8]) -> impl Iterator<Item = (u64, [u8; 32])> + '_ {
+    let len = data
+        .get(..8)
+        .and_then(|len| len.try_into().ok())
+        .map_or(0, u64::from_le_bytes) as usize;
+    data.get(8..)
+        .unwrap_or_default()
+        .chunks_exact(40)
+        .take(len)
+        .map(|entry| {
+            let (slot, hash) = entry.split_at(8);
+            (
+                u64::from_le_bytes(slot.try_into().unwrap_or_default()),
+                hash.try_into().unwrap_or_default(),
+            )
+        })
+}
+
+/// Hash of `slot`, if still in the sysvar.
+pub fn find_slot_hash(data: &[u8], slot: u64) -> Option<[u8; 32]> {
+    entries(data)
+        .find(|(entry_slot, _)| *entry_slot == slot)
+        .map(|(_, hash)| hash)
+}
+
+/// First slot from `slot` with a hash. Only an older entry, or `slot` itself, proves that the
+/// slot found is the first one with a hash, so that it doesn't depend on when it is looked up.
+pub fn find_round_hash(data: &[u8], slot: u64) -> RoundHash {
+    let mut found = None;
+    for (entry_slot, entry_hash) in entries(data) {
+        if entry_slot < slot {
+            return match found {
+                Some((found_slot, found_hash)) => RoundHash::Found(found_slot, found_hash),
+                None => RoundHash::Pending,
+            };
+        }
+        if entry_slot == slot {
+            return RoundHash::Found(entry_slot, entry_hash);
+        }
+        found = Some((entry_slot, entry_hash));
+    }
+    match found {
+        Some((oldest_slot, _)) => RoundHash::Expired(oldest_slot),
+        None => RoundHash::Pending,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn slot_hashes(slots: &[u64]) -> Vec<u8> {
+        let mut data = (slots.len() as u64).to_le_bytes().to_vec();
+        for slot in slots {
+            data.extend_from_slice(&slot.to_le_bytes());
+            data.extend_from_slice(&[*slot as u8; 32]);
+        }
+        data
+    }
+
+    #[test]
+    fn finds_the_hash_of_the_slot() {
+        let data = slot_hashes(&[12, 10, 9]);
+        assert_eq!(find_round_hash(&data, 10), RoundHash::Found(10, [10; 32]));
+        assert_eq!(find_slot_hash(&data, 10), Some([10; 32]));
+        assert_eq!(find_slot_hash(&data, 11), None);
+    }
+
+    #[test]
+    fn finds_the_next_slot_when_skipped() {
+        let data = slot_hashes(&[12, 10, 9]);
+        assert_eq!(find_round_hash(&data, 11), RoundHash::Found(12, [12; 32]));
+    }
+
+    #[test]
+    fn future_slots_are_pending() {
+        assert_eq!(
+            find_round_hash(&slot_hashes(&[12, 10]), 13),
+            RoundHash::Pending
+        );
+        assert_eq!(find_round_hash(&slot_hashes(&[]), 13), RoundHash::Pending);
+    }
+
+    #[test]
+    fn slots_older_than_the_sysvar_are_expired() {
+        let data = slot_hashes(&[12, 10]);
+        // Slot 10 may have followed skipped slots whose hashes left the sysvar
+        assert_eq!(find_round_hash(&data, 9), RoundHash::Expired(10));
+        assert_eq!(find_round_hash(&data, 5), RoundHash::Expired(10));
+    }
+}
@@ -0,0 +1,63 @@
+use crate::state::AccountDiscriminator;
+use crate::steel::{Discriminator, IntoPrimitive, Pod, Pubkey, TryFromPrimitive, Zeroable};
+
+/// Roles the admin authority can delegate.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, IntoPrimitive, TryFromPrimitive)]
+pub enum AdminRole {
+    /// Adds and removes oracles, creates shared queues.
+    OracleManager = 0,
+    /// Configures the token fees of shared queues.
+    FeeManager = 1,
+    /// Pauses and resumes the intake of requests of queues.
+    Pauser = 2,
+}
+
+/// Admin of the program, set at `Initialize` and transferred in two steps (see
+/// `ProposeAdmin` and `AcceptAdmin`).
+///
+/// Keys are checked as signers only, a multisig PDA signing through CPI can hold the
+/// authority or any role. Roles left to the default pubkey are held by the authority.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+pub struct Admin {
+    pub authority: Pubkey,
+    /// Authority proposed by the current one, default if no transfer is pending.
+    pub pending_authority: Pubkey,
+    pub oracle_manager: Pubkey,
+    pub fee_manager: Pubkey,
+    pub pauser: Pubkey,
+    /// Share of the TTL of a request, in basis points, after which it can be reassigned.
+    pub reassign_after_ttl_bps: u64,
+}
+
+impl Admin {
+    pub fn to_bytes(&self) -> &[u8] {
+        bytemuck::bytes_of(self)
+    }
+
+    /// Returns true if `key` is the authority or holds `role`.
+    pub fn is_authorized(&self, role: AdminRole, key: &Pubkey) -> bool {
+        let member = match role {
+            AdminRole::OracleManager => &self.oracle_manager,
+            AdminRole::FeeManager => &self.fee_manager,
+            AdminRole::Pauser => &self.pauser,
+        };
+        self.authority.eq(key) || (member.ne(&Pubkey::default()) && member.eq(key))
+    }
+
+    /// Assign `role` to `member`, the default pubkey returns it to the authority.
+    pub fn set_role(&mut self, role: AdminRole, member: Pubkey) {
+        match role {
+            AdminRole::OracleManager => self.oracle_manager = member,
+            AdminRole::FeeManager => self.fee_manager = member,
+            AdminRole::Pauser => self.pauser = member,
+        }
+    }
+}
+
+impl Discriminator for Admin {
+    fn discriminator() -> u8 {
+        AccountDiscriminator::Admin.into()
+    }
+}
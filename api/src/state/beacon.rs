@@ -0,0 +1,169 @@
+use crate::consts::{BEACON_ROUNDS, VRF_PREFIX_BEACON};
+use crate::slot_hashes::{find_round_hash, RoundHash};
+use crate::state::AccountDiscriminator;
+use crate::steel::{Discriminator, Pod, Pubkey, Zeroable};
+use solana_curve25519::ristretto::PodRistrettoPoint;
+use solana_curve25519::scalar::PodScalar;
+use solana_program::hash::hashv;
+
+/// Public randomness published by an oracle every `interval_slots`, readable by any program.
+///
+/// Each round is the VRF output over the slot hash of a recent slot, verified when written.
+/// The latest `BEACON_ROUNDS` rounds are kept in a ring buffer.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+pub struct Beacon {
+    pub oracle: Pubkey,
+    pub interval_slots: u64,
+    /// Number of rounds published so far, the latest is at `(round_count - 1) % BEACON_ROUNDS`.
+    pub round_count: u64,
+    pub rounds: [BeaconRound; BEACON_ROUNDS],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+pub struct BeaconRound {
+    /// Slot whose hash is the input of the round.
+    pub slot: u64,
+    pub slothash: [u8; 32],
+    /// Hash of the VRF output.
+    pub randomness: [u8; 32],
+    pub output: PodRistrettoPoint,
+    pub commitment_base_compressed: PodRistrettoPoint,
+    pub commitment_hash_compressed: PodRistrettoPoint,
+    pub scalar: PodScalar,
+    /// VRF public key of the oracle when the round was published.
+    pub vrf_pubkey: PodRistrettoPoint,
+}
+
+impl Beacon {
+    pub fn to_bytes(&self) -> &[u8] {
+        bytemuck::bytes_of(self)
+    }
+
+    /// Latest round, if any.
+    pub fn latest_round(&self) -> Option<&BeaconRound> {
+        let index = self.round_count.checked_sub(1)? % BEACON_ROUNDS as u64;
+        self.rounds.get(index as usize)
+    }
+
+    /// Append a round, overwriting the oldest once the ring buffer is full.
+    pub fn push_round(&mut self, round: BeaconRound) {
+        let index = self.round_count % BEACON_ROUNDS as u64;
+        self.rounds[index as usize] = round;
+        self.round_count = self.round_count.saturating_add(1);
+    }
+
+    /// Hash of the slot of the round following the latest one, in the SlotHashes sysvar data:
+    /// the first slot with a hash from `interval_slots` after the latest round, skipping the
+    /// rounds whose slot hashes left the sysvar. `None` before the first round, which may be
+    /// published over any slot of the sysvar.
+    pub fn next_round_hash(&self, slot_hashes: &[u8]) -> Option<RoundHash> {
+        let latest = self.latest_round()?;
+        let interval_slots = self.interval_slots.max(1);
+        let mut round_slot = latest.slot.saturating_add(interval_slots);
+        if let RoundHash::Expired(oldest_slot) = find_round_hash(slot_hashes, round_slot) {
+            let skipped = (oldest_slot - round_slot).div_ceil(interval_slots);
+            round_slot = round_slot.saturating_add(skipped.saturating_mul(interval_slots));
+        }
+        Some(find_round_hash(slot_hashes, round_slot))
+    }
+
+    /// VRF input of the round of `oracle` over the hash of `slot`.
+    pub fn round_input(oracle: &Pubkey, slot: u64, slothash: &[u8; 32]) -> [u8; 32] {
+        hashv(&[
+            VRF_PREFIX_BEACON,
+            oracle.as_ref(),
+            &slot.to_le_bytes(),
+            slothash,
+        ])
+        .to_bytes()
+    }
+}
+
+impl Discriminator for Beacon {
+    fn discriminator() -> u8 {
+        AccountDiscriminator::Beacon.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn beacon() -> Beacon {
+        Beacon {
+            interval_slots: 10,
+            ..Beacon::zeroed()
+        }
+    }
+
+    fn round(slot: u64) -> BeaconRound {
+        BeaconRound {
+            slot,
+            ..BeaconRound::zeroed()
+        }
+    }
+
+    fn slot_hashes(slots: &[u64]) -> Vec<u8> {
+        let mut data = (slots.len() as u64).to_le_bytes().to_vec();
+        for slot in slots {
+            data.extend_from_slice(&slot.to_le_bytes());
+            data.extend_from_slice(&[*slot as u8; 32]);
+        }
+        data
+    }
+
+    #[test]
+    fn new_beacon_has_no_round() {
+        let beacon = beacon();
+        assert_eq!(beacon.latest_round(), None);
+        assert_eq!(beacon.next_round_hash(&slot_hashes(&[5])), None);
+    }
+
+    #[test]
+    fn push_round_overwrites_the_oldest_round() {
+        let mut beacon = beacon();
+        for slot in 0..BEACON_ROUNDS as u64 + 2 {
+            beacon.push_round(round(slot));
+            assert_eq!(beacon.latest_round(), Some(&round(slot)));
+        }
+        assert_eq!(beacon.round_count, BEACON_ROUNDS as u64 + 2);
+        assert_eq!(beacon.rounds[0], round(BEACON_ROUNDS as u64));
+        assert_eq!(beacon.rounds[1], round(BEACON_ROUNDS as u64 + 1));
+        assert_eq!(beacon.rounds[2], round(2));
+    }
+
+    #[test]
+    fn next_round_is_an_interval_after_the_latest() {
+        let mut beacon = beacon();
+        beacon.push_round(round(100));
+        assert_eq!(
+            beacon.next_round_hash(&slot_hashes(&[111, 110, 109])),
+            Some(RoundHash::Found(110, [110; 32]))
+        );
+        // Skipped slot
+        assert_eq!(
+            beacon.next_round_hash(&slot_hashes(&[112, 109])),
+            Some(RoundHash::Found(112, [112; 32]))
+        );
+        assert_eq!(
+            beacon.next_round_hash(&slot_hashes(&[109])),
+            Some(RoundHash::Pending)
+        );
+    }
+
+    #[test]
+    fn next_round_skips_the_rounds_left_the_sysvar() {
+        let mut beacon = beacon();
+        beacon.push_round(round(100));
+        assert_eq!(
+            beacon.next_round_hash(&slot_hashes(&[135, 131, 125])),
+            Some(RoundHash::Found(131, [131; 32]))
+        );
+        assert_eq!(
+            beacon.next_round_hash(&slot_hashes(&[131, 130])),
+            Some(RoundHash::Found(130, [130; 32]))
+        );
+    }
+}
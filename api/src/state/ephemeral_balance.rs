@@ -0,0 +1,26 @@
+use crate::state::AccountDiscriminator;
+use crate::steel::{Discriminator, Pod, Pubkey, Zeroable};
+
+/// Lamports escrowed by a payer to cover request fees on the default ephemeral queue.
+///
+/// The balance is topped up on the base layer and delegated to the ephemeral rollup, where
+/// requests debit it directly. Debited fees are settled to the base layer when it is committed.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+pub struct EphemeralBalance {
+    pub payer: Pubkey,
+    pub index: u8,
+    pub _padding: [u8; 7],
+}
+
+impl EphemeralBalance {
+    pub fn to_bytes(&self) -> &[u8] {
+        bytemuck::bytes_of(self)
+    }
+}
+
+impl Discriminator for EphemeralBalance {
+    fn discriminator() -> u8 {
+        AccountDiscriminator::EphemeralBalance.into()
+    }
+}
@@ -1,12 +1,28 @@
+mod admin;
+mod beacon;
+mod ephemeral_balance;
 mod macros;
 mod oracle;
 mod oracles;
 mod queue;
+mod randomness_result;
+mod receipt;
+mod sponsor;
+mod subscription;
+mod token_fee_config;
 
+pub use admin::*;
+pub use beacon::*;
+pub use ephemeral_balance::*;
 pub use oracle::*;
 pub use oracles::*;
 pub use queue::*;
+pub use randomness_result::*;
+pub use receipt::*;
 use solana_program::pubkey;
+pub use sponsor::*;
+pub use subscription::*;
+pub use token_fee_config::*;
 
 use crate::steel::*;
 
@@ -18,6 +34,15 @@ pub enum AccountDiscriminator {
     Oracles = 0,
     Oracle = 1,
     Queue = 3,
+    TokenFeeConfig = 4,
+    EphemeralBalance = 5,
+    Sponsor = 6,
+    SponsorUsage = 7,
+    RandomnessResult = 8,
+    Subscription = 9,
+    Beacon = 10,
+    Receipt = 11,
+    Admin = 12,
 }
 
 impl AccountDiscriminator {
@@ -35,6 +60,11 @@ pub trait AccountWithDiscriminator {
 pub fn oracles_pda() -> (Pubkey, u8) {
     Pubkey::find_program_address(&[ORACLES], &crate::id())
 }
+/// Fetch PDA of the admin account.
+pub fn admin_pda() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[ADMIN], &crate::id())
+}
+
 pub fn oracle_data_pda(identity: &Pubkey) -> (Pubkey, u8) {
     Pubkey::find_program_address(&[ORACLE_DATA, identity.to_bytes().as_slice()], &crate::id())
 }
@@ -51,3 +81,74 @@ pub fn oracle_queue_pda(identity: &Pubkey, index: u8) -> (Pubkey, u8) {
         &crate::id(),
     )
 }
+
+/// Fetch PDA of a queue shared by the registered oracles.
+pub fn shared_queue_pda(index: u8) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[SHARED_QUEUE, &[index]], &crate::id())
+}
+
+/// Fetch PDA of the token fee configuration of a queue.
+pub fn token_fee_config_pda(queue: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[TOKEN_FEE_CONFIG, queue.as_ref()], &crate::id())
+}
+
+/// Fetch PDA of the token account escrowing token fees of a queue.
+pub fn token_fee_escrow_pda(queue: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[TOKEN_FEE_ESCROW, queue.as_ref()], &crate::id())
+}
+
+/// Fetch PDA of the ephemeral balance of a payer.
+pub fn ephemeral_balance_pda(payer: &Pubkey, index: u8) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[EPHEMERAL_BALANCE, payer.as_ref(), &[index]], &crate::id())
+}
+
+/// Fetch PDA of a sponsor.
+pub fn sponsor_pda(authority: &Pubkey, index: u8) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[SPONSOR, authority.as_ref(), &[index]], &crate::id())
+}
+
+/// Fetch PDA of the request counter of a user of a sponsor.
+pub fn sponsor_usage_pda(sponsor: &Pubkey, user: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[SPONSOR_USAGE, sponsor.as_ref(), user.as_ref()],
+        &crate::id(),
+    )
+}
+
+/// Fetch PDA of the pull-mode result of a requester.
+pub fn randomness_result_pda(requester: &Pubkey, caller_seed: &[u8; 32]) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[RANDOMNESS_RESULT, requester.as_ref(), caller_seed],
+        &crate::id(),
+    )
+}
+
+/// Fetch PDA of the receipt of the requests of a callback program with a caller seed, one
+/// request at a time.
+pub fn receipt_pda(callback_program_id: &Pubkey, caller_seed: &[u8; 32]) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[RECEIPT, callback_program_id.as_ref(), caller_seed],
+        &crate::id(),
+    )
+}
+
+/// Fetch PDA of a recurring request of a callback program.
+pub fn subscription_pda(callback_program_id: &Pubkey, caller_seed: &[u8; 32]) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[SUBSCRIPTION, callback_program_id.as_ref(), caller_seed],
+        &crate::id(),
+    )
+}
+
+/// Fetch PDA of the randomness beacon of an oracle.
+pub fn beacon_pda(identity: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[BEACON, identity.as_ref()], &crate::id())
+}
+
+/// Fetch PDA signed for by the VRF program when invoking the callback of a request.
+pub fn callback_signer_pda(callback_program_id: &Pubkey, caller_seed: &[u8; 32]) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[CALLBACK_SIGNER, callback_program_id.as_ref(), caller_seed],
+        &crate::id(),
+    )
+}
@@ -2,6 +2,7 @@ use crate::prelude::{AccountDiscriminator, AccountWithDiscriminator};
 use crate::steel::*;
 use crate::{impl_to_bytes_with_discriminator_borsh, impl_try_from_bytes_with_discriminator_borsh};
 use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::hash::hashv;
 
 #[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq, Default)]
 pub struct Oracles {
@@ -19,6 +20,20 @@ impl Oracles {
         let item_size = 32;
         8 + 4 + (item_size * self.oracles.len())
     }
+
+    /// Rank of `oracle` among the oracles claiming a request of a shared queue, `None` if not
+    /// registered. Oracles are ordered by the hash of the request id and their identity, so
+    /// that each request has a different first claimant.
+    pub fn claim_rank(&self, oracle: &Pubkey, request_id: &[u8; 32]) -> Option<usize> {
+        let priority = |identity: &Pubkey| hashv(&[request_id, identity.as_ref()]).to_bytes();
+        let own = self.oracles.contains(oracle).then(|| priority(oracle))?;
+        Some(
+            self.oracles
+                .iter()
+                .filter(|identity| priority(identity) < own)
+                .count(),
+        )
+    }
 }
 
 impl_to_bytes_with_discriminator_borsh!(Oracles);
@@ -1,8 +1,13 @@
-use crate::prelude::{AccountDiscriminator, EphemeralVrfError};
+use crate::prelude::{
+    AccountDiscriminator, EphemeralVrfError, FeePayment, MAX_CALLBACK_ACCOUNTS,
+    MAX_CALLBACK_ACCOUNTS_WITH_LOOKUP_TABLE, QUEUE_TTL_SECONDS, QUEUE_TTL_SLOTS, QUEUE_VERSION,
+    VRF_HIGH_PRIORITY_LAMPORTS_COST, VRF_LAMPORTS_COST,
+};
 use crate::steel::{AccountMeta, Pod, ProgramError, Pubkey, Zeroable};
 use borsh::{BorshDeserialize, BorshSerialize};
 use core::mem::{size_of, size_of_val};
 use core::ptr;
+use solana_program::pubkey::{MAX_SEEDS, MAX_SEED_LEN};
 
 /// Header of the queue account (fixed size, lives at the start of the account
 /// after the 8-byte discriminator).
@@ -16,7 +21,12 @@ pub struct Queue {
     pub cursor: u32,
     /// Logical index or shard id of the queue.
     pub index: u8,
-    pub _padding: [u8; 3],
+    /// Flag: 1 = any registered oracle can fulfill the requests (see `shared_queue_pda`).
+    pub shared: u8,
+    /// Flag: 1 = new requests are rejected, set by the admin pauser (see `SetQueuePaused`).
+    pub paused: u8,
+    /// Layout version of the items (see `QUEUE_VERSION`), 0 for queues created before it.
+    pub version: u8,
 }
 
 /// Single queue entry. This is written into the variable region and
@@ -26,7 +36,19 @@ pub struct Queue {
 pub struct QueueItem {
     pub slot: u64,
     pub id: [u8; 32],
+    /// Seed chosen by the requester, used to derive its result PDA.
+    pub caller_seed: [u8; 32],
     pub callback_program_id: [u8; 32],
+    /// Address lookup table used by oracles to fit the callback accounts, zero if none.
+    pub lookup_table: [u8; 32],
+    /// Fee escrowed for this request, in lamports or in base units of the queue's fee mint.
+    pub fee: u64,
+    /// Lamports tipped on top of the fee, oracles serve higher tips first.
+    pub tip: u64,
+    /// Slot from which the request can be fulfilled, zero if not scheduled.
+    pub not_before_slot: u64,
+    /// Unix timestamp from which the request can be fulfilled, zero if not scheduled.
+    pub not_before_timestamp: i64,
     pub callback_discriminator_offset: u32,
     pub metas_offset: u32,
     pub args_offset: u32,
@@ -34,11 +56,99 @@ pub struct QueueItem {
     pub metas_len: u16, // number of SerializableAccountMeta
     pub args_len: u16,  // number of bytes
     pub priority_request: u8,
-    pub used: u8, // Flag: 1 = used, 0 = free (logically removed)
-    pub _padding: [u8; 4],
+    pub used: u8,           // Flag: 1 = used, 0 = free (logically removed)
+    pub fee_payment: u8,    // FeePayment the fee was escrowed with
+    pub pull: u8, // Flag: 1 = write to the result PDA in the metas, 0 = invoke the callback
+    pub pda_metas_len: u16, // number of bytes of the borsh-encoded PdaAccountMeta list
+    pub pda_metas_offset: u32,
+    pub num_words: u8, // number of random words passed to the callback, 0 for a single one
+    pub callback_abi: u8, // CallbackAbi of the callback data
+    pub receipt: u8,   // Flag: 1 = the receipt PDA of the request follows its accounts
+    pub fallback: u8, // Flag: 1 = record the randomness in the result PDA if the callback keeps failing
+    /// Slot of the latest reassignment of the request, zero if never reassigned.
+    pub reassigned_slot: u64,
+    /// Zeroed bytes for fields added without changing the layout of the queue.
+    pub _reserved: [u8; 16],
 }
 
 impl QueueItem {
+    /// Returns true if the fee of this request was escrowed in the queue's fee mint.
+    pub fn is_token_fee(&self) -> bool {
+        self.fee_payment == FeePayment::Token as u8
+    }
+
+    /// Lamports escrowed in the queue for this request: the tip, plus the fee unless paid in tokens.
+    pub fn lamports_due(&self) -> u64 {
+        if self.is_token_fee() {
+            self.tip
+        } else {
+            self.fee.saturating_add(self.tip)
+        }
+    }
+
+    /// Address lookup table named by the request, if any.
+    pub fn lookup_table(&self) -> Option<Pubkey> {
+        (self.lookup_table != [0; 32]).then(|| Pubkey::new_from_array(self.lookup_table))
+    }
+
+    /// Returns true if the schedule of the request allows fulfilling it at `slot` and `timestamp`.
+    pub fn is_due(&self, slot: u64, timestamp: i64) -> bool {
+        slot >= self.not_before_slot && timestamp >= self.not_before_timestamp
+    }
+
+    /// Returns true if the request outlived its TTL, counted from its schedule if any.
+    pub fn is_expired(&self, slot: u64, timestamp: i64) -> bool {
+        is_request_expired(
+            self.slot,
+            self.not_before_slot,
+            self.not_before_timestamp,
+            slot,
+            timestamp,
+        )
+    }
+
+    /// Slot from which the TTL of the request counts, its schedule if any.
+    pub fn ttl_start_slot(&self) -> u64 {
+        self.slot.max(self.not_before_slot)
+    }
+
+    /// Slot from which the request waits to be reassigned, its TTL start or its latest
+    /// reassignment.
+    pub fn waiting_since_slot(&self) -> u64 {
+        self.ttl_start_slot().max(self.reassigned_slot)
+    }
+
+    /// Returns true if the request waited for `after_ttl_bps` of its TTL since it was due, and
+    /// as long since its latest reassignment, without expiring, so that it can be moved to the
+    /// queue of another oracle.
+    pub fn is_reassignable(&self, slot: u64, timestamp: i64, after_ttl_bps: u64) -> bool {
+        let waiting_since = self.waiting_since_slot();
+        let delay_slots = QUEUE_TTL_SLOTS * after_ttl_bps / 10_000;
+        let delay_seconds = QUEUE_TTL_SECONDS * after_ttl_bps as i64 / 10_000;
+        self.is_due(slot, timestamp)
+            && !self.is_expired(slot, timestamp)
+            && slot.saturating_sub(waiting_since) >= delay_slots
+            && (self.not_before_timestamp == 0
+                || timestamp.saturating_sub(self.not_before_timestamp) >= delay_seconds)
+    }
+
+    /// Maximum number of callback accounts, raised for requests naming a lookup table.
+    pub fn max_callback_accounts(has_lookup_table: bool) -> usize {
+        if has_lookup_table {
+            MAX_CALLBACK_ACCOUNTS_WITH_LOOKUP_TABLE
+        } else {
+            MAX_CALLBACK_ACCOUNTS
+        }
+    }
+
+    /// Length of the variable data of this item: discriminator, metas, args and PDA metas.
+    pub fn data_len(&self) -> usize {
+        (self.callback_discriminator_len as usize)
+            + (self.metas_len as usize) * size_of::<CompactAccountMeta>()
+            + (self.args_len as usize)
+            + (self.pda_metas_len as usize)
+    }
+
     pub fn callback_discriminator<'a>(&self, acc: &'a [u8]) -> &'a [u8] {
         let start = self.callback_discriminator_offset as usize;
         let end = start + self.callback_discriminator_len as usize;
@@ -71,6 +181,66 @@ impl QueueItem {
         }
         &acc[start..end]
     }
+
+    /// Callback accounts resolved from PDA seeds at fulfillment, passed after the fixed metas.
+    pub fn pda_account_metas(&self, acc: &[u8]) -> Result<Vec<PdaAccountMeta>, ProgramError> {
+        if self.pda_metas_len == 0 {
+            return Ok(Vec::new());
+        }
+        let start = self.pda_metas_offset as usize;
+        let end = start + self.pda_metas_len as usize;
+        if end > acc.len() {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Vec::<PdaAccountMeta>::try_from_slice(&acc[start..end])
+            .map_err(|_| ProgramError::InvalidAccountData)
+    }
+}
+
+/// Returns true if a request made at `request_slot` outlived its TTL, counted from its
+/// schedule if any.
+pub fn is_request_expired(
+    request_slot: u64,
+    not_before_slot: u64,
+    not_before_timestamp: i64,
+    slot: u64,
+    timestamp: i64,
+) -> bool {
+    let ttl_start_slot = request_slot.max(not_before_slot);
+    slot.saturating_sub(ttl_start_slot) > QUEUE_TTL_SLOTS
+        && (not_before_timestamp == 0
+            || timestamp.saturating_sub(not_before_timestamp) > QUEUE_TTL_SECONDS)
+}
+
+/// Callback account written as the PDA of `program_id` with `seeds`, derived with the
+/// canonical bump when the request is fulfilled. Allows callbacks to receive accounts that
+/// only exist after the request was made.
+#[derive(Clone, Debug, Default, PartialEq, BorshDeserialize, BorshSerialize)]
+pub struct PdaAccountMeta {
+    pub program_id: [u8; 32],
+    pub seeds: Vec<Vec<u8>>,
+    pub is_writable: bool,
+}
+
+impl PdaAccountMeta {
+    /// Returns true if the seeds can be used to derive a program address.
+    pub fn is_valid(&self) -> bool {
+        self.seeds.len() < MAX_SEEDS && self.seeds.iter().all(|seed| seed.len() <= MAX_SEED_LEN)
+    }
+
+    /// Derive the account address.
+    pub fn pubkey(&self) -> Pubkey {
+        let seeds = self.seeds.iter().map(Vec::as_slice).collect::<Vec<_>>();
+        Pubkey::find_program_address(&seeds, &Pubkey::new_from_array(self.program_id)).0
+    }
+
+    pub fn to_account_meta(&self) -> AccountMeta {
+        AccountMeta {
+            pubkey: self.pubkey(),
+            is_signer: false,
+            is_writable: self.is_writable,
+        }
+    }
 }
 
 /// Serializable meta, Borsh compatible and Pod/Zeroable for zero copy.
@@ -79,6 +249,8 @@ impl QueueItem {
 pub struct CompactAccountMeta {
     pub pubkey: [u8; 32],
     pub is_writable: u8,
+    /// Flag: 1 = signed by the VRF program when invoking the callback (see `callback_signer_pda`)
+    pub is_signer: u8,
 }
 
 #[derive(Clone, Copy, Debug, Default, PartialEq, BorshDeserialize, BorshSerialize)]
@@ -93,11 +265,14 @@ impl From<SerializableAccountMeta> for CompactAccountMeta {
         CompactAccountMeta {
             pubkey: val.pubkey,
             is_writable: val.is_writable as u8,
+            is_signer: val.is_signer as u8,
         }
     }
 }
 
 impl CompactAccountMeta {
+    /// Meta of the account in the transaction providing the randomness. Signers are PDAs signed
+    /// for by the VRF program during the callback, so they are never transaction signers.
     pub fn to_account_meta(&self) -> AccountMeta {
         let pubkey = Pubkey::new_from_array(self.pubkey);
         let is_signer = false;
@@ -109,6 +284,112 @@ impl CompactAccountMeta {
             is_writable,
         }
     }
+
+    /// Meta of the account in the callback instruction, preserving the signer flag.
+    pub fn to_callback_account_meta(&self) -> AccountMeta {
+        AccountMeta {
+            is_signer: self.is_signer != 0,
+            ..self.to_account_meta()
+        }
+    }
+}
+
+/// Queue entry in the layout used before `QUEUE_VERSION`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Zeroable, Pod)]
+struct LegacyQueueItem {
+    slot: u64,
+    id: [u8; 32],
+    callback_program_id: [u8; 32],
+    callback_discriminator_offset: u32,
+    metas_offset: u32,
+    args_offset: u32,
+    callback_discriminator_len: u16,
+    metas_len: u16,
+    args_len: u16,
+    priority_request: u8,
+    used: u8,
+    _padding: [u8; 4],
+}
+
+/// Account meta in the layout used before `QUEUE_VERSION`, without signer flag.
+const LEGACY_ACCOUNT_META_LEN: usize = 33;
+
+/// Iterator over the used items of a legacy queue with their discriminator, metas and args.
+struct LegacyItems<'a> {
+    acc: &'a [u8],
+    cursor: usize,
+    end: usize,
+}
+
+impl<'a> LegacyItems<'a> {
+    fn read(acc: &'a [u8]) -> Self {
+        let end =
+            bytemuck::try_from_bytes::<Queue>(acc.get(..size_of::<Queue>()).unwrap_or_default())
+                .map_or(0, |header| header.cursor as usize)
+                .min(acc.len());
+        Self {
+            acc,
+            cursor: QueueAccount::items_start(),
+            end,
+        }
+    }
+
+    fn slice(&self, offset: u32, len: usize) -> &'a [u8] {
+        let start = offset as usize;
+        self.acc
+            .get(start..start.saturating_add(len))
+            .unwrap_or_default()
+    }
+}
+
+impl<'a> Iterator for LegacyItems<'a> {
+    type Item = (LegacyQueueItem, &'a [u8], Vec<CompactAccountMeta>, &'a [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item_size = size_of::<LegacyQueueItem>();
+        while self.cursor + item_size <= self.end {
+            let item = bytemuck::pod_read_unaligned::<LegacyQueueItem>(
+                &self.acc[self.cursor..self.cursor + item_size],
+            );
+            let data_len = item.callback_discriminator_len as usize
+                + item.metas_len as usize * LEGACY_ACCOUNT_META_LEN
+                + item.args_len as usize;
+            let next = QueueAccount::align_up(
+                self.cursor + item_size + data_len,
+                core::mem::align_of::<LegacyQueueItem>(),
+            );
+            if next <= self.cursor {
+                break;
+            }
+            self.cursor = next;
+            if item.used != 1 {
+                continue;
+            }
+            let metas = self
+                .slice(
+                    item.metas_offset,
+                    item.metas_len as usize * LEGACY_ACCOUNT_META_LEN,
+                )
+                .chunks_exact(LEGACY_ACCOUNT_META_LEN)
+                .map(|meta| CompactAccountMeta {
+                    pubkey: meta[..32].try_into().expect("slice of 32 bytes"),
+                    is_writable: meta[32],
+                    is_signer: 0,
+                })
+                .collect();
+            return Some((
+                item,
+                self.slice(
+                    item.callback_discriminator_offset,
+                    item.callback_discriminator_len as usize,
+                ),
+                metas,
+                self.slice(item.args_offset, item.args_len as usize),
+            ));
+        }
+        None
+    }
 }
 
 /// View over a queue account: header + variable region in the same account data.
@@ -149,11 +430,87 @@ impl<'a> QueueAccount<'a> {
         // If this is a freshly created account, cursor 0 means "no data yet":
         if header.cursor == 0 {
             header.cursor = Self::items_start() as u32;
+            header.version = QUEUE_VERSION;
+        }
+
+        // Items of previous layouts would be misread, only empty queues are upgraded in place
+        if header.version != QUEUE_VERSION {
+            if header.item_count != 0 {
+                return Err(EphemeralVrfError::QueueMigrationRequired.into());
+            }
+            header.cursor = Self::items_start() as u32;
+            header.version = QUEUE_VERSION;
         }
 
         Ok(Self { header, acc })
     }
 
+    /// Returns true if the queue data (without discriminator) holds items in the layout used
+    /// before `QUEUE_VERSION`, to be converted with `migrate_legacy`.
+    pub fn is_legacy(acc: &[u8]) -> bool {
+        bytemuck::try_from_bytes::<Queue>(acc.get(..size_of::<Queue>()).unwrap_or_default())
+            .is_ok_and(|header| header.cursor != 0 && header.version == 0 && header.item_count != 0)
+    }
+
+    /// Size of the queue data (without discriminator) once the items of a legacy queue are
+    /// converted to the current layout.
+    pub fn migrated_len(acc: &[u8]) -> usize {
+        let align = core::mem::align_of::<QueueItem>();
+        LegacyItems::read(acc).fold(
+            Self::items_start(),
+            |len, (_, discriminator, metas, args)| {
+                Self::align_up(len, align)
+                    + size_of::<QueueItem>()
+                    + discriminator.len()
+                    + metas.len() * size_of::<CompactAccountMeta>()
+                    + args.len()
+            },
+        )
+    }
+
+    /// Convert the items of a legacy queue to the current layout, in place.
+    ///
+    /// Legacy requests were paid in lamports at the flat priority cost, which becomes their
+    /// escrowed fee. The data must be at least `migrated_len` long.
+    pub fn migrate_legacy(acc: &'a mut [u8]) -> Result<Self, ProgramError> {
+        if !Self::is_legacy(acc) {
+            return Self::load(acc);
+        }
+        if acc.len() < Self::migrated_len(acc) {
+            return Err(ProgramError::AccountDataTooSmall);
+        }
+
+        let items = LegacyItems::read(acc)
+            .map(|(item, discriminator, metas, args)| {
+                (item, discriminator.to_vec(), metas, args.to_vec())
+            })
+            .collect::<Vec<_>>();
+        let legacy_header = bytemuck::pod_read_unaligned::<Queue>(&acc[..size_of::<Queue>()]);
+        acc.fill(0);
+
+        let mut queue_acc = Self::load(acc)?;
+        queue_acc.header.index = legacy_header.index;
+        queue_acc.header.shared = legacy_header.shared;
+        queue_acc.header.paused = legacy_header.paused;
+        for (legacy, discriminator, metas, args) in items {
+            let item = QueueItem {
+                slot: legacy.slot,
+                id: legacy.id,
+                callback_program_id: legacy.callback_program_id,
+                fee: if legacy.priority_request == 1 {
+                    VRF_HIGH_PRIORITY_LAMPORTS_COST
+                } else {
+                    VRF_LAMPORTS_COST
+                },
+                priority_request: legacy.priority_request,
+                fee_payment: FeePayment::Lamports.into(),
+                ..QueueItem::default()
+            };
+            queue_acc.add_item(&item, &discriminator, &metas, &args, &[])?;
+        }
+        Ok(queue_acc)
+    }
+
     /// Internal helper to write bytes into the variable region at current cursor and advance.
     fn write_bytes(&mut self, bytes: &[u8]) -> Result<u32, ProgramError> {
         let start = self.header.cursor as usize;
@@ -199,12 +556,7 @@ impl<'a> QueueAccount<'a> {
             let bytes = &self.acc[cursor..cursor + size_of::<QueueItem>()];
             let item = Self::read_item_unaligned(bytes);
 
-            let metas_bytes = (item.metas_len as usize) * size_of::<CompactAccountMeta>();
-            let item_end = cursor
-                + size_of::<QueueItem>()
-                + (item.callback_discriminator_len as usize)
-                + metas_bytes
-                + (item.args_len as usize);
+            let item_end = cursor + size_of::<QueueItem>() + item.data_len();
             let next = Self::align_up(item_end, align);
 
             if item.used == 1 {
@@ -232,9 +584,18 @@ impl<'a> QueueAccount<'a> {
         discriminator: &[u8],
         metas: &[CompactAccountMeta],
         args: &[u8],
+        pda_metas: &[PdaAccountMeta],
     ) -> Result<usize, ProgramError> {
+        // PDA metas are stored borsh-encoded, an empty list takes no space
+        let pda_metas = if pda_metas.is_empty() {
+            Vec::new()
+        } else {
+            borsh::to_vec(pda_metas).map_err(|_| ProgramError::InvalidInstructionData)?
+        };
+
         // Enforce upper bounds on metas and args lengths to prevent oversized QueueItems
-        if metas.len() > 20 || args.len() > 512 {
+        let max_metas = QueueItem::max_callback_accounts(base_item.lookup_table().is_some());
+        if metas.len() > max_metas || args.len() > 512 || pda_metas.len() > 1024 {
             return Err(ProgramError::from(EphemeralVrfError::ArgumentSizeTooLarge));
         }
 
@@ -247,12 +608,14 @@ impl<'a> QueueAccount<'a> {
         let disc_len_usize = discriminator.len();
         let metas_bytes_len = size_of_val(metas);
         let args_len_usize = args.len();
+        let pda_metas_len_usize = pda_metas.len();
 
         // Total bytes needed for this append (no trailing alignment; we align at the start)
         let total_needed = item_size
             .saturating_add(disc_len_usize)
             .saturating_add(metas_bytes_len)
-            .saturating_add(args_len_usize);
+            .saturating_add(args_len_usize)
+            .saturating_add(pda_metas_len_usize);
 
         // Ensure we have enough room in the account before mutating any state
         if aligned.saturating_add(total_needed) > self.acc.len() {
@@ -286,6 +649,9 @@ impl<'a> QueueAccount<'a> {
         let args_off = self.write_bytes(args)?;
         let args_len = args_len_usize as u16;
 
+        let pda_metas_off = self.write_bytes(&pda_metas)?;
+        let pda_metas_len = pda_metas_len_usize as u16;
+
         // Build final item with filled offsets
         let mut item = *base_item;
         item.callback_discriminator_offset = disc_off;
@@ -294,6 +660,8 @@ impl<'a> QueueAccount<'a> {
         item.metas_len = metas_len;
         item.args_offset = args_off;
         item.args_len = args_len;
+        item.pda_metas_offset = pda_metas_off;
+        item.pda_metas_len = pda_metas_len;
         item.used = 1;
 
         // Write the item back into the reserved slot using unaligned store
@@ -322,15 +690,7 @@ impl<'a> QueueAccount<'a> {
                 out.push(item);
             }
 
-            let metas_bytes = (item.metas_len as usize) * size_of::<CompactAccountMeta>();
-            let next = Self::align_up(
-                cursor
-                    + size_of::<QueueItem>()
-                    + (item.callback_discriminator_len as usize)
-                    + metas_bytes
-                    + (item.args_len as usize),
-                align,
-            );
+            let next = Self::align_up(cursor + size_of::<QueueItem>() + item.data_len(), align);
 
             // Prevent infinite loop in case of corrupted lengths
             if next <= cursor {
@@ -361,15 +721,7 @@ impl<'a> QueueAccount<'a> {
                 current += 1;
             }
 
-            let metas_bytes = (item.metas_len as usize) * size_of::<CompactAccountMeta>();
-            let next = Self::align_up(
-                cursor
-                    + size_of::<QueueItem>()
-                    + (item.callback_discriminator_len as usize)
-                    + metas_bytes
-                    + (item.args_len as usize),
-                align,
-            );
+            let next = Self::align_up(cursor + size_of::<QueueItem>() + item.data_len(), align);
             if next <= cursor {
                 break;
             }
@@ -394,12 +746,7 @@ impl<'a> QueueAccount<'a> {
             if item.used == 1 {
                 if current == index {
                     // Compute if this item was at the physical tail
-                    let metas_bytes = (item.metas_len as usize) * size_of::<CompactAccountMeta>();
-                    let item_end = cursor
-                        + size_of::<QueueItem>()
-                        + (item.callback_discriminator_len as usize)
-                        + metas_bytes
-                        + (item.args_len as usize);
+                    let item_end = cursor + size_of::<QueueItem>() + item.data_len();
                     let next = Self::align_up(item_end, align);
                     let was_tail = next == self.header.cursor as usize;
 
@@ -419,15 +766,7 @@ impl<'a> QueueAccount<'a> {
                 current += 1;
             }
 
-            let metas_bytes = (item.metas_len as usize) * size_of::<CompactAccountMeta>();
-            let next = Self::align_up(
-                cursor
-                    + size_of::<QueueItem>()
-                    + (item.callback_discriminator_len as usize)
-                    + metas_bytes
-                    + (item.args_len as usize),
-                align,
-            );
+            let next = Self::align_up(cursor + size_of::<QueueItem>() + item.data_len(), align);
             if next <= cursor {
                 break;
             }
@@ -456,15 +795,7 @@ impl<'a> QueueAccount<'a> {
                 current += 1;
             }
 
-            let metas_bytes = (item.metas_len as usize) * size_of::<CompactAccountMeta>();
-            let next = Self::align_up(
-                cursor
-                    + size_of::<QueueItem>()
-                    + (item.callback_discriminator_len as usize)
-                    + metas_bytes
-                    + (item.args_len as usize),
-                align,
-            );
+            let next = Self::align_up(cursor + size_of::<QueueItem>() + item.data_len(), align);
             if next <= cursor {
                 break;
             }
@@ -532,3 +863,288 @@ impl Queue {
             .map_err(|_| ProgramError::InvalidAccountData)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const QUEUE_LEN: usize = 4096;
+
+    /// Legacy item: id byte, used flag, discriminator, metas and args.
+    type LegacyItem<'a> = (u8, u8, &'a [u8], &'a [[u8; 33]], &'a [u8]);
+
+    fn legacy_queue(items: &[LegacyItem]) -> Vec<u8> {
+        let mut acc = vec![0u8; QUEUE_LEN];
+        let align = core::mem::align_of::<LegacyQueueItem>();
+        let mut cursor = QueueAccount::items_start();
+        let mut item_count = 0;
+        for (id, used, discriminator, metas, args) in items {
+            let item_pos = cursor;
+            let mut offset = item_pos + size_of::<LegacyQueueItem>();
+            let mut write = |bytes: &[u8]| {
+                acc[offset..offset + bytes.len()].copy_from_slice(bytes);
+                offset += bytes.len();
+                (offset - bytes.len()) as u32
+            };
+            let callback_discriminator_offset = write(discriminator);
+            let metas_offset = write(&metas.concat());
+            let args_offset = write(args);
+            let item = LegacyQueueItem {
+                slot: 10,
+                id: [*id; 32],
+                callback_program_id: [7; 32],
+                callback_discriminator_offset,
+                metas_offset,
+                args_offset,
+                callback_discriminator_len: discriminator.len() as u16,
+                metas_len: metas.len() as u16,
+                args_len: args.len() as u16,
+                priority_request: *id % 2,
+                used: *used,
+                _padding: [0; 4],
+            };
+            acc[item_pos..item_pos + size_of::<LegacyQueueItem>()]
+                .copy_from_slice(bytemuck::bytes_of(&item));
+            cursor = QueueAccount::align_up(offset, align);
+            item_count += *used as u32;
+        }
+        let header = Queue {
+            item_count,
+            cursor: cursor as u32,
+            index: 3,
+            ..Queue::default()
+        };
+        acc[..size_of::<Queue>()].copy_from_slice(bytemuck::bytes_of(&header));
+        acc
+    }
+
+    fn legacy_meta(key: u8, is_writable: u8) -> [u8; 33] {
+        let mut meta = [key; 33];
+        meta[32] = is_writable;
+        meta
+    }
+
+    #[test]
+    fn load_initializes_fresh_queues_at_current_version() {
+        let mut acc = vec![0u8; QUEUE_LEN];
+        let queue_acc = QueueAccount::load(&mut acc).unwrap();
+        assert_eq!(queue_acc.header.version, QUEUE_VERSION);
+        assert_eq!(
+            queue_acc.header.cursor as usize,
+            QueueAccount::items_start()
+        );
+    }
+
+    #[test]
+    fn load_rejects_legacy_queues_with_requests() {
+        let mut acc = legacy_queue(&[(1, 1, &[1, 2], &[], &[])]);
+        assert!(QueueAccount::is_legacy(&acc));
+        assert_eq!(
+            QueueAccount::load(&mut acc).err(),
+            Some(EphemeralVrfError::QueueMigrationRequired.into())
+        );
+    }
+
+    #[test]
+    fn load_upgrades_empty_legacy_queues() {
+        let mut acc = legacy_queue(&[(1, 0, &[1, 2], &[legacy_meta(4, 1)], &[5])]);
+        assert!(!QueueAccount::is_legacy(&acc));
+        let queue_acc = QueueAccount::load(&mut acc).unwrap();
+        assert_eq!(queue_acc.header.version, QUEUE_VERSION);
+        assert_eq!(queue_acc.header.index, 3);
+        assert_eq!(queue_acc.iter_items().count(), 0);
+    }
+
+    #[test]
+    fn migrate_legacy_converts_pending_requests() {
+        let mut acc = legacy_queue(&[
+            (
+                1,
+                1,
+                &[1, 2, 3],
+                &[legacy_meta(4, 1), legacy_meta(5, 0)],
+                &[9, 9],
+            ),
+            (2, 0, &[1], &[], &[]),
+            (4, 1, &[6], &[], &[8; 40]),
+        ]);
+        assert!(QueueAccount::migrated_len(&acc) <= QUEUE_LEN);
+
+        let queue_acc = QueueAccount::migrate_legacy(&mut acc).unwrap();
+        assert_eq!(queue_acc.header.version, QUEUE_VERSION);
+        assert_eq!(queue_acc.header.index, 3);
+        let items = queue_acc.iter_items().collect::<Vec<_>>();
+        assert_eq!(items.len(), 2);
+
+        assert_eq!(items[0].id, [1; 32]);
+        assert_eq!(items[0].slot, 10);
+        assert_eq!(items[0].callback_program_id, [7; 32]);
+        assert_eq!(items[0].fee, VRF_HIGH_PRIORITY_LAMPORTS_COST);
+        assert_eq!(items[0].callback_discriminator(queue_acc.acc), &[1, 2, 3]);
+        let metas = items[0].account_metas(queue_acc.acc);
+        assert_eq!(metas.len(), 2);
+        assert_eq!(metas[0].pubkey, [4; 32]);
+        assert_eq!((metas[0].is_writable, metas[0].is_signer), (1, 0));
+        assert_eq!(metas[1].pubkey, [5; 32]);
+        assert_eq!(metas[1].is_writable, 0);
+        assert_eq!(items[0].callback_args(queue_acc.acc), &[9, 9]);
+
+        assert_eq!(items[1].id, [4; 32]);
+        assert_eq!(items[1].fee, VRF_LAMPORTS_COST);
+        assert_eq!(items[1].lamports_due(), VRF_LAMPORTS_COST);
+        assert_eq!(items[1].callback_args(queue_acc.acc), &[8; 40]);
+    }
+
+    #[test]
+    fn migrate_legacy_needs_room_for_the_converted_requests() {
+        let mut acc = legacy_queue(&[(1, 1, &[1], &[legacy_meta(4, 1); 20], &[0; 512])]);
+        let migrated_len = QueueAccount::migrated_len(&acc);
+        acc.truncate(migrated_len - 1);
+        assert_eq!(
+            QueueAccount::migrate_legacy(&mut acc).err(),
+            Some(ProgramError::AccountDataTooSmall)
+        );
+        acc.resize(migrated_len, 0);
+        assert_eq!(QueueAccount::migrate_legacy(&mut acc).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn token_fees_are_not_escrowed_in_lamports() {
+        let item = QueueItem {
+            fee: 1_000,
+            tip: 50,
+            ..QueueItem::default()
+        };
+        assert!(!item.is_token_fee());
+        assert_eq!(item.lamports_due(), 1_050);
+
+        let item = QueueItem {
+            fee_payment: FeePayment::Token.into(),
+            ..item
+        };
+        assert!(item.is_token_fee());
+        assert_eq!(item.lamports_due(), 50);
+    }
+
+    #[test]
+    fn pda_metas_need_derivable_seeds() {
+        let meta = |seeds: Vec<Vec<u8>>| PdaAccountMeta {
+            program_id: [7; 32],
+            seeds,
+            is_writable: true,
+        };
+        assert!(meta(vec![]).is_valid());
+        assert!(meta(vec![vec![1; MAX_SEED_LEN]; MAX_SEEDS - 1]).is_valid());
+        // The bump seed is appended when deriving the address
+        assert!(!meta(vec![vec![1]; MAX_SEEDS]).is_valid());
+        assert!(!meta(vec![vec![1; MAX_SEED_LEN + 1]]).is_valid());
+    }
+
+    #[test]
+    fn pda_metas_derive_their_address() {
+        let meta = PdaAccountMeta {
+            program_id: [7; 32],
+            seeds: vec![b"vault".to_vec(), vec![3; 32]],
+            is_writable: true,
+        };
+        let (pubkey, _) =
+            Pubkey::find_program_address(&[b"vault", &[3; 32]], &Pubkey::new_from_array([7; 32]));
+        assert_eq!(meta.pubkey(), pubkey);
+        let account_meta = meta.to_account_meta();
+        assert_eq!(account_meta.pubkey, pubkey);
+        assert!(account_meta.is_writable);
+        assert!(!account_meta.is_signer);
+    }
+
+    #[test]
+    fn unscheduled_requests_are_due_and_expire_from_their_slot() {
+        let item = QueueItem {
+            slot: 100,
+            ..QueueItem::default()
+        };
+        assert!(item.is_due(100, 0));
+        assert!(!item.is_expired(100 + QUEUE_TTL_SLOTS, 0));
+        assert!(item.is_expired(101 + QUEUE_TTL_SLOTS, 0));
+    }
+
+    #[test]
+    fn requests_scheduled_at_a_slot_wait_for_it() {
+        let item = QueueItem {
+            slot: 100,
+            not_before_slot: 1_000,
+            ..QueueItem::default()
+        };
+        assert!(!item.is_due(999, 0));
+        assert!(item.is_due(1_000, 0));
+        // The TTL counts from the target slot
+        assert!(!item.is_expired(999, 0));
+        assert!(!item.is_expired(1_000 + QUEUE_TTL_SLOTS, 0));
+        assert!(item.is_expired(1_001 + QUEUE_TTL_SLOTS, 0));
+    }
+
+    #[test]
+    fn requests_scheduled_at_a_timestamp_expire_after_both_ttls() {
+        let item = QueueItem {
+            slot: 100,
+            not_before_timestamp: 5_000,
+            ..QueueItem::default()
+        };
+        assert!(!item.is_due(1_000, 4_999));
+        assert!(item.is_due(1_000, 5_000));
+        // Slots elapsed alone do not expire a request waiting for its timestamp
+        assert!(!item.is_expired(1_000, 5_000 + QUEUE_TTL_SECONDS));
+        assert!(!item.is_expired(100, 6_000));
+        assert!(item.is_expired(1_000, 5_001 + QUEUE_TTL_SECONDS));
+    }
+
+    #[test]
+    fn requests_are_reassignable_after_their_share_of_the_ttl() {
+        let item = QueueItem {
+            slot: 100,
+            ..QueueItem::default()
+        };
+        let delay = QUEUE_TTL_SLOTS / 2;
+        assert!(!item.is_reassignable(99 + delay, 0, 5_000));
+        assert!(item.is_reassignable(100 + delay, 0, 5_000));
+        assert!(!item.is_reassignable(100 + delay, 0, 7_500));
+        assert!(item.is_reassignable(100 + QUEUE_TTL_SLOTS, 0, 7_500));
+        // Expired requests are purged instead
+        assert!(!item.is_reassignable(101 + QUEUE_TTL_SLOTS, 0, 5_000));
+    }
+
+    #[test]
+    fn scheduled_requests_are_reassignable_after_their_schedule() {
+        let item = QueueItem {
+            slot: 100,
+            not_before_slot: 1_000,
+            ..QueueItem::default()
+        };
+        assert_eq!(item.ttl_start_slot(), 1_000);
+        assert!(!item.is_reassignable(999 + QUEUE_TTL_SLOTS / 2, 0, 5_000));
+        assert!(item.is_reassignable(1_000 + QUEUE_TTL_SLOTS / 2, 0, 5_000));
+
+        let item = QueueItem {
+            slot: 100,
+            not_before_timestamp: 5_000,
+            ..QueueItem::default()
+        };
+        let delay_seconds = QUEUE_TTL_SECONDS / 2;
+        assert!(!item.is_reassignable(1_000, 4_999 + delay_seconds, 5_000));
+        assert!(item.is_reassignable(1_000, 5_000 + delay_seconds, 5_000));
+    }
+
+    #[test]
+    fn reassigned_requests_wait_the_delay_again() {
+        let item = QueueItem {
+            slot: 100,
+            reassigned_slot: 150,
+            ..QueueItem::default()
+        };
+        let delay = QUEUE_TTL_SLOTS / 4;
+        assert!(!item.is_reassignable(100 + delay, 0, 2_500));
+        assert!(!item.is_reassignable(149 + delay, 0, 2_500));
+        assert!(item.is_reassignable(150 + delay, 0, 2_500));
+        // The TTL still counts from the request
+        assert!(!item.is_reassignable(150 + QUEUE_TTL_SLOTS, 0, 2_500));
+    }
+}
@@ -0,0 +1,64 @@
+use crate::state::{is_request_expired, AccountDiscriminator};
+use crate::steel::{Discriminator, Pod, Pubkey, Zeroable};
+use solana_curve25519::ristretto::PodRistrettoPoint;
+use solana_curve25519::scalar::PodScalar;
+
+/// Randomness of a pull-mode request, written by the oracle instead of invoking a callback,
+/// or of a request opting into the fallback whose callback kept failing.
+///
+/// The account is created when the request is made and reset by every new request using the
+/// same seeds, so it can be read by the requester in a later transaction. A new request is
+/// rejected while the latest one is pending, neither fulfilled nor expired. Its payer can then
+/// close it (see `CloseRandomnessResult`).
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+pub struct RandomnessResult {
+    pub requester: Pubkey,
+    pub caller_seed: [u8; 32],
+    /// Id of the queue item of the latest request.
+    pub request_id: [u8; 32],
+    /// Hash of the VRF output, as passed to callbacks.
+    pub randomness: [u8; 32],
+    pub output: PodRistrettoPoint,
+    pub commitment_base_compressed: PodRistrettoPoint,
+    pub commitment_hash_compressed: PodRistrettoPoint,
+    pub scalar: PodScalar,
+    /// Oracle which fulfilled the request.
+    pub oracle: Pubkey,
+    /// Slot in which the request was fulfilled.
+    pub slot: u64,
+    pub fulfilled: u8,
+    pub _padding: [u8; 7],
+    /// Slot in which the latest request was made.
+    pub request_slot: u64,
+    /// Schedule of the latest request, counting its TTL (see `QueueItem::is_expired`).
+    pub not_before_slot: u64,
+    pub not_before_timestamp: i64,
+    /// Signer which created the account, refunded when it is closed.
+    pub payer: Pubkey,
+}
+
+impl RandomnessResult {
+    pub fn to_bytes(&self) -> &[u8] {
+        bytemuck::bytes_of(self)
+    }
+
+    /// Returns true while the latest request can still be fulfilled.
+    pub fn is_pending(&self, slot: u64, timestamp: i64) -> bool {
+        self.fulfilled == 0
+            && self.request_id != [0; 32]
+            && !is_request_expired(
+                self.request_slot,
+                self.not_before_slot,
+                self.not_before_timestamp,
+                slot,
+                timestamp,
+            )
+    }
+}
+
+impl Discriminator for RandomnessResult {
+    fn discriminator() -> u8 {
+        AccountDiscriminator::RandomnessResult.into()
+    }
+}
@@ -0,0 +1,65 @@
+use crate::state::AccountDiscriminator;
+use crate::steel::{Discriminator, IntoPrimitive, Pod, Pubkey, TryFromPrimitive, Zeroable};
+
+/// Status of a request tracked by a receipt.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, IntoPrimitive, TryFromPrimitive)]
+pub enum ReceiptStatus {
+    /// The request is waiting in its queue.
+    Pending = 0,
+    /// The randomness was delivered, to the callback or to the result PDA.
+    Fulfilled = 1,
+    /// The request outlived its TTL and was purged from its queue.
+    Expired = 2,
+}
+
+/// Outcome of a single request, created with the request when asked for and updated when it
+/// leaves its queue.
+///
+/// The account is paid by the payer of the request, who can close it once the request is no
+/// longer pending (see `CloseReceipt`). Its PDA is derived from the callback program and the
+/// caller seed, not from the request: there is one receipt per caller seed at a time, closed
+/// before requesting another receipt with the same seed.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+pub struct Receipt {
+    /// Payer of the rent of the receipt, allowed to close it.
+    pub payer: Pubkey,
+    pub queue: Pubkey,
+    pub request_id: [u8; 32],
+    pub callback_program_id: Pubkey,
+    pub caller_seed: [u8; 32],
+    /// Hash of the VRF output, zero unless fulfilled.
+    pub randomness: [u8; 32],
+    /// Oracle which fulfilled or purged the request.
+    pub oracle: Pubkey,
+    /// Slot in which the request was made.
+    pub request_slot: u64,
+    /// Slot in which the request was fulfilled or purged.
+    pub completion_slot: u64,
+    pub status: u8,
+    pub _padding: [u8; 7],
+}
+
+impl Receipt {
+    pub fn to_bytes(&self) -> &[u8] {
+        bytemuck::bytes_of(self)
+    }
+
+    pub fn status(&self) -> Option<ReceiptStatus> {
+        ReceiptStatus::try_from(self.status).ok()
+    }
+
+    /// Record the completion of the request.
+    pub fn complete(&mut self, status: ReceiptStatus, oracle: &Pubkey, slot: u64) {
+        self.status = status.into();
+        self.oracle = *oracle;
+        self.completion_slot = slot;
+    }
+}
+
+impl Discriminator for Receipt {
+    fn discriminator() -> u8 {
+        AccountDiscriminator::Receipt.into()
+    }
+}
@@ -0,0 +1,84 @@
+use crate::prelude::{AccountDiscriminator, AccountWithDiscriminator};
+use crate::steel::*;
+use crate::{impl_to_bytes_with_discriminator_borsh, impl_try_from_bytes_with_discriminator_borsh};
+use borsh::{BorshDeserialize, BorshSerialize};
+
+/// Vault funded by a dApp to pay the fees of requests from whitelisted callback programs.
+///
+/// Requests reference the sponsor instead of having the signer pay, limits are counted over
+/// windows of `window_slots` slots, per program and per user.
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq, Default)]
+pub struct Sponsor {
+    pub authority: Pubkey,
+    pub index: u8,
+    pub window_slots: u64,
+    /// Max sponsored requests per user and window, 0 for no limit.
+    pub max_requests_per_user: u32,
+    pub programs: Vec<SponsoredProgram>,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq, Default, Clone, Copy)]
+pub struct SponsoredProgram {
+    pub program_id: Pubkey,
+    /// Max sponsored requests per window, 0 for no limit.
+    pub max_requests_per_window: u32,
+    pub window_start_slot: u64,
+    pub requests_in_window: u32,
+}
+
+/// Per-user request counter of a sponsor.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+pub struct SponsorUsage {
+    pub window_start_slot: u64,
+    pub requests_in_window: u32,
+    pub _padding: [u8; 4],
+}
+
+impl AccountWithDiscriminator for Sponsor {
+    fn discriminator() -> AccountDiscriminator {
+        AccountDiscriminator::Sponsor
+    }
+}
+
+impl Discriminator for SponsorUsage {
+    fn discriminator() -> u8 {
+        AccountDiscriminator::SponsorUsage.into()
+    }
+}
+
+impl Sponsor {
+    pub fn size_with_discriminator(&self) -> usize {
+        let item_size = 32 + 4 + 8 + 4;
+        8 + 32 + 1 + 8 + 4 + 4 + (item_size * self.programs.len())
+    }
+}
+
+impl SponsorUsage {
+    pub fn to_bytes(&self) -> &[u8] {
+        bytemuck::bytes_of(self)
+    }
+}
+
+/// Count one request in a rate limit window, starting a new window once `window_slots` elapsed.
+/// Returns false, without counting, if `limit` requests were already made in the window.
+pub fn consume_rate_limit(
+    window_start_slot: &mut u64,
+    requests_in_window: &mut u32,
+    limit: u32,
+    window_slots: u64,
+    slot: u64,
+) -> bool {
+    if slot.saturating_sub(*window_start_slot) >= window_slots {
+        *window_start_slot = slot;
+        *requests_in_window = 0;
+    }
+    if limit != 0 && *requests_in_window >= limit {
+        return false;
+    }
+    *requests_in_window = requests_in_window.saturating_add(1);
+    true
+}
+
+impl_to_bytes_with_discriminator_borsh!(Sponsor);
+impl_try_from_bytes_with_discriminator_borsh!(Sponsor);
@@ -0,0 +1,118 @@
+use crate::prelude::{
+    AccountDiscriminator, AccountWithDiscriminator, CallbackAbi, PdaAccountMeta,
+    SerializableAccountMeta,
+};
+use crate::steel::*;
+use crate::{impl_to_bytes_with_discriminator_borsh, impl_try_from_bytes_with_discriminator_borsh};
+use borsh::{BorshDeserialize, BorshSerialize};
+
+/// Recurring randomness request, enqueued by the oracle of its queue every `interval_slots`.
+///
+/// Each round is a regular request of the queue, its fee is debited from the lamports of the
+/// subscription above rent. The subscription is funded at creation and with plain lamport
+/// transfers to its PDA.
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq, Default)]
+pub struct Subscription {
+    /// Receives the remaining lamports when the subscription is cancelled.
+    pub authority: Pubkey,
+    pub queue: Pubkey,
+    pub callback_program_id: Pubkey,
+    pub caller_seed: [u8; 32],
+    pub interval_slots: u64,
+    /// Slot from which the next round can be enqueued.
+    pub next_round_slot: u64,
+    /// Number of rounds enqueued so far.
+    pub sequence: u64,
+    pub num_words: u8,
+    pub callback_abi: CallbackAbi,
+    pub callback_discriminator: Vec<u8>,
+    pub callback_accounts_metas: Vec<SerializableAccountMeta>,
+    pub callback_args: Vec<u8>,
+    pub callback_pda_metas: Vec<PdaAccountMeta>,
+}
+
+impl AccountWithDiscriminator for Subscription {
+    fn discriminator() -> AccountDiscriminator {
+        AccountDiscriminator::Subscription
+    }
+}
+
+impl Subscription {
+    pub fn size_with_discriminator(&self) -> usize {
+        8 + borsh::object_length(self).unwrap_or_default()
+    }
+
+    /// Id of the request of a round, also its VRF input. The hash of the slot of the round
+    /// keeps its input unknown until the slot is past, then fixes it whenever it is enqueued.
+    pub fn round_id(
+        subscription: &Pubkey,
+        sequence: u64,
+        slot: u64,
+        slothash: &[u8; 32],
+    ) -> [u8; 32] {
+        solana_program::hash::hashv(&[
+            subscription.as_ref(),
+            &sequence.to_le_bytes(),
+            &slot.to_le_bytes(),
+            slothash,
+        ])
+        .to_bytes()
+    }
+
+    /// Schedule the round following the one enqueued at `slot`, skipping the missed ones.
+    pub fn advance(&mut self, slot: u64) {
+        let missed = slot.saturating_sub(self.next_round_slot) / self.interval_slots;
+        self.next_round_slot = self
+            .next_round_slot
+            .saturating_add(self.interval_slots.saturating_mul(missed.saturating_add(1)));
+        self.sequence = self.sequence.saturating_add(1);
+    }
+}
+
+impl_to_bytes_with_discriminator_borsh!(Subscription);
+impl_try_from_bytes_with_discriminator_borsh!(Subscription);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn subscription(next_round_slot: u64) -> Subscription {
+        Subscription {
+            interval_slots: 10,
+            next_round_slot,
+            ..Subscription::default()
+        }
+    }
+
+    #[test]
+    fn advance_schedules_the_next_interval() {
+        let mut subscription = subscription(100);
+        subscription.advance(100);
+        assert_eq!(subscription.next_round_slot, 110);
+        assert_eq!(subscription.sequence, 1);
+        subscription.advance(115);
+        assert_eq!(subscription.next_round_slot, 120);
+        assert_eq!(subscription.sequence, 2);
+    }
+
+    #[test]
+    fn advance_skips_the_missed_rounds() {
+        let mut subscription = subscription(100);
+        subscription.advance(135);
+        assert_eq!(subscription.next_round_slot, 140);
+        assert_eq!(subscription.sequence, 1);
+    }
+
+    #[test]
+    fn round_id_depends_on_the_sequence_slot_and_hash() {
+        let key = Pubkey::new_unique();
+        let id = Subscription::round_id(&key, 0, 100, &[1; 32]);
+        assert_ne!(id, Subscription::round_id(&key, 1, 100, &[1; 32]));
+        assert_ne!(id, Subscription::round_id(&key, 0, 101, &[1; 32]));
+        assert_ne!(id, Subscription::round_id(&key, 0, 100, &[2; 32]));
+        assert_ne!(
+            id,
+            Subscription::round_id(&Pubkey::new_unique(), 0, 100, &[1; 32])
+        );
+    }
+}
@@ -0,0 +1,31 @@
+use crate::state::AccountDiscriminator;
+use crate::steel::{Discriminator, Pod, Pubkey, Zeroable};
+
+/// Per-queue configuration for paying request fees with an SPL or Token-2022 mint.
+///
+/// Token fees are escrowed in a token account owned by this PDA and paid out to the
+/// fulfilling oracle in the same mint.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+pub struct TokenFeeConfig {
+    pub mint: Pubkey,
+    pub token_program: Pubkey,
+    pub escrow: Pubkey,
+    /// Price of a request without callback accounts or data, in base units of the mint, scaled
+    /// by the size of the request (see `request_fee_tokens`). Zero disables token payments.
+    pub price: u64,
+    pub decimals: u8,
+    pub _padding: [u8; 7],
+}
+
+impl TokenFeeConfig {
+    pub fn to_bytes(&self) -> &[u8] {
+        bytemuck::bytes_of(self)
+    }
+}
+
+impl Discriminator for TokenFeeConfig {
+    fn discriminator() -> u8 {
+        AccountDiscriminator::TokenFeeConfig.into()
+    }
+}
@@ -1,10 +1,12 @@
-use crate::consts::VRF_PREFIX_HASH_TO_SCALAR;
+use crate::consts::{VRF_PREFIX_HASH_TO_SCALAR, VRF_PREFIX_RANDOM_WORD};
 use crate::prelude::*;
 use curve25519_dalek::Scalar;
 use solana_curve25519::edwards::{validate_edwards, PodEdwardsPoint};
-use solana_curve25519::ristretto::{add_ristretto, multiply_ristretto, PodRistrettoPoint};
+use solana_curve25519::ristretto::{
+    add_ristretto, multiply_ristretto, multiscalar_multiply_ristretto, PodRistrettoPoint,
+};
 use solana_curve25519::scalar::PodScalar;
-use solana_program::hash::hash;
+use solana_program::hash::{hash, hashv};
 use solana_program::pubkey::Pubkey;
 
 /// Verify a VRF proof
@@ -31,18 +33,13 @@ pub fn verify_vrf(
     let h = hash_to_point(input);
 
     // Recompute challenge
-    let challenge_input = [
-        VRF_PREFIX_CHALLENGE.to_vec(),
-        output_compressed.0.to_vec(),
-        commitment_base_compressed.0.to_vec(),
-        commitment_hash_compressed.0.to_vec(),
-        pk.0.to_vec(),
-        input.to_vec(),
-    ]
-    .concat();
-
-    let challenge_hash = hash(challenge_input.as_slice());
-    let c = hash_to_scalar(&challenge_hash.to_bytes());
+    let c = challenge(
+        pk,
+        input,
+        output_compressed,
+        commitment_base_compressed,
+        commitment_hash_compressed,
+    );
 
     // ---------------------------
     // 1) Schnorr check for G:
@@ -85,6 +82,114 @@ pub fn verify_vrf(
     lhs_base == rhs_base && lhs_hash == rhs_hash
 }
 
+/// Verify several VRF proofs of the same public key at once
+///
+/// Accounts: None
+///
+/// Requirements:
+///
+/// - Every proof must be valid for the given public key, input, and output
+///
+/// 1. Recompute the hash point and the challenge of each proof
+/// 2. Derive a random weight per proof from all the proofs
+/// 3. Check that the weighted sum of both Schnorr equations of all proofs is the identity,
+///    with a single multiscalar multiplication
+pub fn verify_vrf_batch(pk: &PodRistrettoPoint, proofs: &[ProvideRandomness]) -> bool {
+    // Weights are bound to every proof so that invalid proofs can't cancel each other out
+    let seed = hashv(
+        &proofs
+            .iter()
+            .map(bytemuck::bytes_of)
+            .chain([pk.0.as_slice()])
+            .collect::<Vec<_>>(),
+    );
+    // Separates the base point equations from the hash point equations
+    let w = to_scalar(&hash_to_scalar(&hashv(&[seed.as_ref(), b"w"]).to_bytes()));
+
+    let mut scalars = Vec::with_capacity(2 + 4 * proofs.len());
+    let mut points = Vec::with_capacity(2 + 4 * proofs.len());
+    let mut base_scalar = Scalar::ZERO;
+    let mut pk_scalar = Scalar::ZERO;
+
+    for (i, proof) in proofs.iter().enumerate() {
+        let Some(s) = Option::<Scalar>::from(Scalar::from_canonical_bytes(proof.scalar.0)) else {
+            return false;
+        };
+        let c = to_scalar(&challenge(
+            pk,
+            &proof.input,
+            &proof.output,
+            &proof.commitment_base_compressed,
+            &proof.commitment_hash_compressed,
+        ));
+        let z = to_scalar(&hash_to_scalar(
+            &hashv(&[seed.as_ref(), &(i as u32).to_le_bytes()]).to_bytes(),
+        ));
+
+        // z·(s·G - commitment_base - c·pk)
+        base_scalar += z * s;
+        pk_scalar -= z * c;
+        scalars.push(PodScalar::from(-z));
+        points.push(proof.commitment_base_compressed);
+
+        // w·z·(s·h - commitment_hash - c·output)
+        scalars.push(PodScalar::from(w * z * s));
+        points.push(hash_to_point(&proof.input));
+        scalars.push(PodScalar::from(-(w * z)));
+        points.push(proof.commitment_hash_compressed);
+        scalars.push(PodScalar::from(-(w * z * c)));
+        points.push(proof.output);
+    }
+    scalars.push(PodScalar::from(base_scalar));
+    points.push(RISTRETTO_BASEPOINT_POINT);
+    scalars.push(PodScalar::from(pk_scalar));
+    points.push(*pk);
+
+    matches!(
+        multiscalar_multiply_ristretto(&scalars, &points),
+        Some(sum) if sum == PodRistrettoPoint([0; 32])
+    )
+}
+
+/// Derive the random words of a request from its randomness, the hash of the VRF output.
+///
+/// A single word is the randomness itself, more words are each hashed from the randomness and
+/// their index.
+pub fn random_words(randomness: &[u8; 32], num_words: u8) -> Vec<[u8; 32]> {
+    if num_words <= 1 {
+        return vec![*randomness];
+    }
+    (0..num_words)
+        .map(|index| hashv(&[VRF_PREFIX_RANDOM_WORD, randomness, &[index]]).to_bytes())
+        .collect()
+}
+
+/// Compute the Fiat-Shamir challenge of a proof
+fn challenge(
+    pk: &PodRistrettoPoint,
+    input: &[u8; 32],
+    output_compressed: &PodRistrettoPoint,
+    commitment_base_compressed: &PodRistrettoPoint,
+    commitment_hash_compressed: &PodRistrettoPoint,
+) -> PodScalar {
+    let challenge_input = [
+        VRF_PREFIX_CHALLENGE.to_vec(),
+        output_compressed.0.to_vec(),
+        commitment_base_compressed.0.to_vec(),
+        commitment_hash_compressed.0.to_vec(),
+        pk.0.to_vec(),
+        input.to_vec(),
+    ]
+    .concat();
+
+    let challenge_hash = hash(challenge_input.as_slice());
+    hash_to_scalar(&challenge_hash.to_bytes())
+}
+
+fn to_scalar(scalar: &PodScalar) -> Scalar {
+    Scalar::from_bytes_mod_order(scalar.0)
+}
+
 /// Hash the input with a prefix, convert the result to a scalar, and multiply it with the base point
 ///
 /// Accounts: None
@@ -127,3 +232,96 @@ fn hash_to_scalar(input: &[u8; 32]) -> PodScalar {
 pub fn is_on_curve(key: &Pubkey) -> bool {
     validate_edwards(&PodEdwardsPoint(key.to_bytes()))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use curve25519_dalek::constants::RISTRETTO_BASEPOINT_TABLE;
+    use ephemeral_vrf::vrf::compute_vrf;
+
+    fn keypair() -> (Scalar, PodRistrettoPoint) {
+        let sk = Scalar::from(42u64);
+        let pk = (&sk * RISTRETTO_BASEPOINT_TABLE).compress();
+        (sk, PodRistrettoPoint(pk.to_bytes()))
+    }
+
+    fn proof(sk: Scalar, input: [u8; 32]) -> ProvideRandomness {
+        let (output, (commitment_base, commitment_hash, s)) = compute_vrf(sk, &input);
+        ProvideRandomness {
+            input,
+            output: PodRistrettoPoint(output.to_bytes()),
+            commitment_base_compressed: PodRistrettoPoint(commitment_base.to_bytes()),
+            commitment_hash_compressed: PodRistrettoPoint(commitment_hash.to_bytes()),
+            scalar: PodScalar(s.to_bytes()),
+        }
+    }
+
+    fn proofs(sk: Scalar) -> Vec<ProvideRandomness> {
+        (1..=3).map(|i| proof(sk, [i; 32])).collect()
+    }
+
+    #[test]
+    fn batch_accepts_valid_proofs() {
+        let (sk, pk) = keypair();
+        assert!(verify_vrf_batch(&pk, &proofs(sk)));
+        assert!(verify_vrf_batch(&pk, &proofs(sk)[..1]));
+    }
+
+    #[test]
+    fn batch_rejects_one_bad_proof_among_good_ones() {
+        let (sk, pk) = keypair();
+        let mut batch = proofs(sk);
+        batch[1].output = proof(sk, [9; 32]).output;
+        assert!(!verify_vrf_batch(&pk, &batch));
+
+        let mut batch = proofs(sk);
+        batch[2].scalar.0[0] ^= 1;
+        assert!(!verify_vrf_batch(&pk, &batch));
+    }
+
+    #[test]
+    fn batch_rejects_swapped_outputs() {
+        let (sk, pk) = keypair();
+        let mut batch = proofs(sk);
+        let output = batch[0].output;
+        batch[0].output = batch[1].output;
+        batch[1].output = output;
+        assert!(!verify_vrf_batch(&pk, &batch));
+    }
+
+    #[test]
+    fn batch_rejects_another_key() {
+        let (_, pk) = keypair();
+        assert!(!verify_vrf_batch(&pk, &proofs(Scalar::from(7u64))));
+    }
+
+    #[test]
+    fn batch_rejects_non_canonical_scalars() {
+        let (sk, pk) = keypair();
+        let mut batch = proofs(sk);
+        batch[0].scalar = PodScalar([0xff; 32]);
+        assert!(!verify_vrf_batch(&pk, &batch));
+    }
+
+    #[test]
+    fn single_word_is_the_randomness() {
+        assert_eq!(random_words(&[5; 32], 0), vec![[5; 32]]);
+        assert_eq!(random_words(&[5; 32], 1), vec![[5; 32]]);
+    }
+
+    #[test]
+    fn words_are_hashed_from_the_randomness_and_their_index() {
+        let words = random_words(&[5; 32], MAX_RANDOM_WORDS);
+        assert_eq!(words.len(), MAX_RANDOM_WORDS as usize);
+        assert_eq!(
+            words[3],
+            hashv(&[VRF_PREFIX_RANDOM_WORD, &[5; 32], &[3]]).to_bytes()
+        );
+        // Fewer words are a prefix of more words
+        assert_eq!(random_words(&[5; 32], 2), words[..2]);
+        let mut unique = words.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(unique.len(), words.len());
+    }
+}
@@ -0,0 +1,39 @@
+use ephemeral_vrf_api::prelude::EphemeralVrfError::Unauthorized;
+use ephemeral_vrf_api::prelude::*;
+
+/// Process the acceptance of the admin authority proposed with `ProposeAdmin`
+///
+/// Accounts:
+///
+/// 0. `[signer]` new_authority_info - The proposed authority
+/// 1. `[writable]` admin_info - The admin account ([ADMIN])
+///
+/// Requirements:
+///
+/// - The signer must be the pending authority, signing through CPI if it is a multisig PDA
+///
+/// 1. Transfer the admin authority to the signer and clear the pending authority
+pub fn process_accept_admin(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    // Parse args
+    AcceptAdmin::try_from_bytes(data)?;
+
+    // Load accounts
+    let [new_authority_info, admin_info] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    new_authority_info.is_signer()?;
+    admin_info
+        .is_writable()?
+        .has_seeds(&[ADMIN], &ephemeral_vrf_api::ID)?;
+
+    let mut admin = admin_info.as_account_mut::<Admin>(&ephemeral_vrf_api::ID)?;
+    if admin.pending_authority == Pubkey::default()
+        || admin.pending_authority.ne(new_authority_info.key)
+    {
+        return Err(Unauthorized.into());
+    }
+    admin.authority = admin.pending_authority;
+    admin.pending_authority = Pubkey::default();
+
+    Ok(())
+}
@@ -0,0 +1,34 @@
+use ephemeral_vrf_api::prelude::*;
+
+/// Process the cancellation of a recurring randomness request
+///
+/// Accounts:
+///
+/// 0. `[signer, writable]` authority_info - The subscription authority, receives the lamports
+/// 1. `[writable]` subscription_info - The subscription PDA
+///
+/// Requirements:
+///
+/// - The authority must be a signer and the authority of the subscription
+///
+/// 1. Close the subscription, rounds already enqueued are still fulfilled
+pub fn process_cancel_subscription(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    // Parse args
+    CancelSubscription::try_from_bytes(data)?;
+
+    // Load accounts
+    let [authority_info, subscription_info] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    authority_info.is_signer()?;
+    subscription_info
+        .is_writable()?
+        .has_owner(&ephemeral_vrf_api::ID)?;
+    let subscription =
+        Subscription::try_from_bytes_with_discriminator(&subscription_info.try_borrow_data()?)?;
+    if subscription.authority.ne(authority_info.key) {
+        return Err(EphemeralVrfError::Unauthorized.into());
+    }
+
+    close_account(subscription_info, authority_info)
+}
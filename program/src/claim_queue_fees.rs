@@ -0,0 +1,57 @@
+use ephemeral_vrf_api::prelude::*;
+use solana_program::msg;
+use solana_program::rent::Rent;
+
+/// Process the claim of the fees accrued in an oracle queue
+///
+/// Fees paid on the default ephemeral queue are not transferred to the oracle inside the
+/// ephemeral rollup, they accrue in the queue and are settled to the base layer when the queue
+/// is committed. Once the queue is undelegated, the oracle claims them with this instruction.
+///
+/// Accounts:
+///
+/// 0. `[signer, writable]` oracle_info - The oracle owning the queue, receives the fees
+/// 1. `[writable]` oracle_queue_info - The oracle queue account ([QUEUE, oracle, index])
+///
+/// Requirements:
+///
+/// - The oracle must be a signer
+/// - The queue must be owned by the ephemeral VRF program
+/// - Lamports escrowed for pending requests and the rent-exempt minimum stay in the queue
+pub fn process_claim_queue_fees(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    // Parse args
+    let args = ClaimQueueFees::try_from_bytes(data)?;
+
+    // Load accounts
+    let [oracle_info, oracle_queue_info] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    oracle_info.is_signer()?;
+    oracle_queue_info
+        .is_writable()?
+        .has_owner(&ephemeral_vrf_api::ID)?
+        .has_seeds(
+            &[QUEUE, oracle_info.key.as_ref(), &[args.index]],
+            &ephemeral_vrf_api::ID,
+        )?;
+
+    // Lamports still owed to pending requests
+    let pending = {
+        let mut data = oracle_queue_info.try_borrow_mut_data()?;
+        let queue_acc = QueueAccount::load(&mut data[8..])?;
+        queue_acc
+            .iter_items()
+            .fold(0u64, |acc, item| acc.saturating_add(item.lamports_due()))
+    };
+
+    let claimable = oracle_queue_info
+        .lamports()
+        .saturating_sub(Rent::get()?.minimum_balance(oracle_queue_info.data_len()))
+        .saturating_sub(pending);
+    msg!("Claiming {} lamports of queue fees", claimable);
+    if claimable > 0 {
+        crate::fees::transfer_fee(oracle_queue_info, oracle_info, claimable)?;
+    }
+
+    Ok(())
+}
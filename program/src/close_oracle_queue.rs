@@ -72,5 +72,12 @@ pub fn process_close_oracle_queue(accounts: &[AccountInfo], data: &[u8]) -> Prog
 
     close_account(oracle_queue_info, oracle_info)?;
 
+    EphemeralVrfEvent::QueueClosed(QueueClosed {
+        queue: *oracle_queue_info.key,
+        oracle: *oracle_info.key,
+        index: args.index,
+    })
+    .emit();
+
     Ok(())
 }
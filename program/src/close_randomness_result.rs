@@ -0,0 +1,39 @@
+use ephemeral_vrf_api::prelude::*;
+
+/// Process the closing of a result PDA
+///
+/// Accounts:
+///
+/// 0. `[signer, writable]` payer_info - The payer of the result PDA, receives the lamports
+/// 1. `[writable]` randomness_result_info - The result PDA (see `randomness_result_pda`)
+///
+/// Requirements:
+///
+/// - The payer must be a signer and the payer recorded in the result PDA
+/// - The latest request using the result PDA must be fulfilled or expired
+///
+/// 1. Close the result PDA, transferring its lamports to the payer
+pub fn process_close_randomness_result(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    // Parse args
+    CloseRandomnessResult::try_from_bytes(data)?;
+
+    // Load accounts
+    let [payer_info, randomness_result_info] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    payer_info.is_signer()?;
+
+    {
+        let result =
+            randomness_result_info.as_account_mut::<RandomnessResult>(&ephemeral_vrf_api::ID)?;
+        if result.payer.ne(payer_info.key) {
+            return Err(EphemeralVrfError::Unauthorized.into());
+        }
+        let clock = Clock::get()?;
+        if result.is_pending(clock.slot, clock.unix_timestamp) {
+            return Err(EphemeralVrfError::ResultInUse.into());
+        }
+    }
+
+    close_account(randomness_result_info, payer_info)
+}
@@ -0,0 +1,56 @@
+use ephemeral_vrf_api::prelude::*;
+
+/// Process the closing of the receipt of a request
+///
+/// Accounts:
+///
+/// 0. `[signer, writable]` payer_info - The payer of the receipt, receives the lamports
+/// 1. `[writable]` receipt_info - The receipt PDA
+/// 2. `[]` oracle_queue_info - The queue of the request
+///
+/// Requirements:
+///
+/// - The payer must be a signer and the payer of the receipt
+/// - The request must no longer be in its queue. Receipts of requests purged without their
+///   receipt are still pending and can be closed once the request left the queue, which must
+///   be readable: pending receipts of delegated queues are kept until the queue is undelegated
+///   or closed.
+///
+/// 1. Close the receipt, transferring its lamports to the payer
+pub fn process_close_receipt(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    // Parse args
+    CloseReceipt::try_from_bytes(data)?;
+
+    // Load accounts
+    let [payer_info, receipt_info, oracle_queue_info] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    payer_info.is_signer()?;
+
+    let (request_id, pending) = {
+        let receipt = receipt_info.as_account_mut::<Receipt>(&ephemeral_vrf_api::ID)?;
+        if receipt.payer.ne(payer_info.key) {
+            return Err(EphemeralVrfError::Unauthorized.into());
+        }
+        oracle_queue_info.has_address(&receipt.queue)?;
+        (
+            receipt.request_id,
+            receipt.status() == Some(ReceiptStatus::Pending),
+        )
+    };
+
+    // Closed queues hold no requests, the requests of delegated queues can't be read
+    if pending && !oracle_queue_info.data_is_empty() {
+        if oracle_queue_info.owner != &ephemeral_vrf_api::ID {
+            return Err(EphemeralVrfError::ReceiptInUse.into());
+        }
+        let mut data = oracle_queue_info.try_borrow_mut_data()?;
+        let queue_acc =
+            QueueAccount::load(data.get_mut(8..).ok_or(ProgramError::InvalidAccountData)?)?;
+        if queue_acc.find_item_by_id(&request_id).is_some() {
+            return Err(EphemeralVrfError::ReceiptInUse.into());
+        }
+    }
+
+    close_account(receipt_info, payer_info)
+}
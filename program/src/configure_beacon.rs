@@ -0,0 +1,57 @@
+use ephemeral_vrf_api::prelude::*;
+
+/// Process the creation or update of the randomness beacon of an oracle
+///
+/// Accounts:
+///
+/// 0. `[signer, writable]` oracle_info - The oracle, pays for the account creation
+/// 1. `[]` oracle_data_info - The oracle data PDA
+/// 2. `[writable]` beacon_info - The beacon PDA ([BEACON, oracle])
+/// 3. `[]` system_program - The system program
+///
+/// Requirements:
+///
+/// - The oracle must be a signer and registered
+/// - The interval must be at least one slot
+///
+/// 1. Create the beacon on first use
+/// 2. Update the interval between rounds
+pub fn process_configure_beacon(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    // Parse args
+    let args = ConfigureBeacon::try_from_bytes(data)?;
+    if args.interval_slots == 0 {
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    // Load accounts
+    let [oracle_info, oracle_data_info, beacon_info, system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    oracle_info.is_signer()?;
+    oracle_data_info
+        .has_seeds(
+            &[ORACLE_DATA, oracle_info.key.to_bytes().as_ref()],
+            &ephemeral_vrf_api::ID,
+        )?
+        .as_account::<Oracle>(&ephemeral_vrf_api::ID)?;
+
+    let seeds: &[&[u8]] = &[BEACON, oracle_info.key.as_ref()];
+    beacon_info
+        .is_writable()?
+        .has_seeds(seeds, &ephemeral_vrf_api::ID)?;
+    if beacon_info.owner != &ephemeral_vrf_api::ID {
+        create_program_account::<Beacon>(
+            beacon_info,
+            system_program,
+            oracle_info,
+            &ephemeral_vrf_api::ID,
+            seeds,
+        )?;
+    }
+
+    let mut beacon = beacon_info.as_account_mut::<Beacon>(&ephemeral_vrf_api::ID)?;
+    beacon.oracle = *oracle_info.key;
+    beacon.interval_slots = args.interval_slots;
+
+    Ok(())
+}
@@ -0,0 +1,71 @@
+use ephemeral_vrf_api::loaders::is_empty_or_zeroed;
+use ephemeral_vrf_api::prelude::*;
+
+/// Process the creation or update of a sponsor
+///
+/// A sponsor pays the fees of requests from whitelisted callback programs. It is funded with
+/// plain lamport transfers to its PDA.
+///
+/// Accounts:
+///
+/// 0. `[signer, writable]` authority_info - The sponsor authority, pays for the account creation
+/// 1. `[writable]` sponsor_info - The sponsor PDA ([SPONSOR, authority, index])
+/// 2. `[]` system_program - The system program
+///
+/// Requirements:
+///
+/// - The authority must be a signer
+/// - The rate limit window must be at least one slot
+///
+/// 1. Create the sponsor with an empty whitelist on first use
+/// 2. Update the rate limit window and the per-user limit
+pub fn process_configure_sponsor(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    // Parse args
+    let args = ConfigureSponsor::try_from_bytes(data)?;
+    if args.window_slots == 0 {
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    // Load accounts
+    let [authority_info, sponsor_info, system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    authority_info.is_signer()?;
+
+    let seeds: &[&[u8]] = &[SPONSOR, authority_info.key.as_ref(), &[args.index]];
+    sponsor_info
+        .is_writable()?
+        .has_seeds(seeds, &ephemeral_vrf_api::ID)?;
+
+    let mut sponsor = if sponsor_info.owner != &ephemeral_vrf_api::ID {
+        is_empty_or_zeroed(sponsor_info)?;
+        let sponsor = Sponsor {
+            authority: *authority_info.key,
+            index: args.index,
+            ..Default::default()
+        };
+        let bump = sponsor_pda(authority_info.key, args.index).1;
+        create_pda(
+            sponsor_info,
+            &ephemeral_vrf_api::ID,
+            sponsor.size_with_discriminator(),
+            seeds,
+            bump,
+            system_program,
+            authority_info,
+        )?;
+        sponsor
+    } else {
+        Sponsor::try_from_bytes_with_discriminator(&sponsor_info.try_borrow_data()?)?
+    };
+
+    sponsor.window_slots = args.window_slots;
+    sponsor.max_requests_per_user = args.max_requests_per_user;
+
+    let sponsor_bytes = sponsor.to_bytes_with_discriminator()?;
+    sponsor_info
+        .try_borrow_mut_data()?
+        .copy_from_slice(&sponsor_bytes);
+
+    Ok(())
+}
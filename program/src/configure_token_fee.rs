@@ -0,0 +1,160 @@
+use ephemeral_vrf_api::loaders::check_admin_role;
+use ephemeral_vrf_api::prelude::EphemeralVrfError::UnsupportedMint;
+use ephemeral_vrf_api::prelude::*;
+use solana_program::program::invoke;
+use spl_token_2022_interface::extension::{
+    BaseStateWithExtensions, ExtensionType, StateWithExtensions,
+};
+use spl_token_2022_interface::instruction::initialize_account3;
+use spl_token_2022_interface::state::{Account, Mint};
+
+/// Mint extensions that would make the escrowed amount differ from the amount paid out,
+/// or let a third party move or freeze the escrowed tokens.
+const UNSUPPORTED_MINT_EXTENSIONS: [ExtensionType; 5] = [
+    ExtensionType::TransferFeeConfig,
+    ExtensionType::TransferHook,
+    ExtensionType::NonTransferable,
+    ExtensionType::DefaultAccountState,
+    ExtensionType::PermanentDelegate,
+];
+
+/// Process the configuration of token fee payments for an oracle queue
+///
+/// Accounts:
+///
+/// 0. `[signer]` oracle_info - The oracle owning the queue, or the admin fee manager for shared
+///    queues, pays for the accounts creation
+/// 1. `[]` oracle_queue_info - The oracle or shared queue accepting token payments
+/// 2. `[writable]` token_fee_config_info - The token fee config PDA ([TOKEN_FEE_CONFIG, queue])
+/// 3. `[writable]` token_fee_escrow_info - The token account escrowing fees ([TOKEN_FEE_ESCROW, queue])
+/// 4. `[]` mint_info - The fee mint
+/// 5. `[]` token_program_info - The SPL Token or Token-2022 program owning the mint
+/// 6. `[]` system_program - The system program
+/// 7. `[]` admin_info - The admin account ([ADMIN]), for shared queues only
+///
+/// Requirements:
+///
+/// - The oracle must be a signer and the queue must be derived from it, shared queues are
+///   configured by the admin authority or fee manager
+/// - The mint must be owned by the token program and must not use unsupported extensions
+/// - The fee mint cannot be changed once configured, a price of zero disables token payments
+///
+/// 1. Validate the accounts and the mint
+/// 2. Create the config and the escrow token account on first use
+/// 3. Update the price
+pub fn process_configure_token_fee(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    // Parse args
+    let args = ConfigureTokenFee::try_from_bytes(data)?;
+
+    // Load accounts
+    let (
+        [oracle_info, oracle_queue_info, token_fee_config_info, token_fee_escrow_info, mint_info, token_program_info, system_program],
+        admin_accounts,
+    ) = accounts.split_at(7)
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    oracle_info.is_signer()?;
+
+    oracle_queue_info.has_owner(&ephemeral_vrf_api::ID)?;
+    let shared = Queue::try_from_bytes(&oracle_queue_info.try_borrow_data()?)?.shared == 1;
+    if shared {
+        let [admin_info] = admin_accounts else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+        check_admin_role(admin_info, oracle_info, AdminRole::FeeManager)?;
+        oracle_queue_info.has_seeds(&[SHARED_QUEUE, &[args.index]], &ephemeral_vrf_api::ID)?;
+    } else {
+        oracle_queue_info.has_seeds(
+            &[QUEUE, oracle_info.key.as_ref(), &[args.index]],
+            &ephemeral_vrf_api::ID,
+        )?;
+    }
+    let queue_key = oracle_queue_info.key;
+    token_fee_config_info.is_writable()?.has_seeds(
+        &[TOKEN_FEE_CONFIG, queue_key.as_ref()],
+        &ephemeral_vrf_api::ID,
+    )?;
+    token_fee_escrow_info.is_writable()?.has_seeds(
+        &[TOKEN_FEE_ESCROW, queue_key.as_ref()],
+        &ephemeral_vrf_api::ID,
+    )?;
+    spl_token_2022_interface::check_spl_token_program_account(token_program_info.key)?;
+    mint_info.has_owner(token_program_info.key)?;
+
+    // Validate the mint
+    let (decimals, mint_extensions) = {
+        let mint_data = mint_info.try_borrow_data()?;
+        let mint = StateWithExtensions::<Mint>::unpack(&mint_data)?;
+        (mint.base.decimals, mint.get_extension_types()?)
+    };
+    if mint_extensions
+        .iter()
+        .any(|extension| UNSUPPORTED_MINT_EXTENSIONS.contains(extension))
+    {
+        log(format!(
+            "Mint {} uses unsupported extensions",
+            mint_info.key
+        ));
+        return Err(UnsupportedMint.into());
+    }
+
+    // Create the config and the escrow on first use
+    if token_fee_config_info.owner != &ephemeral_vrf_api::ID {
+        create_program_account::<TokenFeeConfig>(
+            token_fee_config_info,
+            system_program,
+            oracle_info,
+            &ephemeral_vrf_api::ID,
+            &[TOKEN_FEE_CONFIG, queue_key.as_ref()],
+        )?;
+
+        let escrow_seeds: &[&[u8]] = &[TOKEN_FEE_ESCROW, queue_key.as_ref()];
+        let escrow_bump = token_fee_escrow_pda(queue_key).1;
+        let escrow_space = ExtensionType::try_calculate_account_len::<Account>(
+            &ExtensionType::get_required_init_account_extensions(&mint_extensions),
+        )?;
+        create_pda(
+            token_fee_escrow_info,
+            token_program_info.key,
+            escrow_space,
+            escrow_seeds,
+            escrow_bump,
+            system_program,
+            oracle_info,
+        )?;
+        invoke(
+            &initialize_account3(
+                token_program_info.key,
+                token_fee_escrow_info.key,
+                mint_info.key,
+                token_fee_config_info.key,
+            )?,
+            &[
+                token_fee_escrow_info.clone(),
+                mint_info.clone(),
+                token_program_info.clone(),
+            ],
+        )?;
+
+        let mut config =
+            token_fee_config_info.as_account_mut::<TokenFeeConfig>(&ephemeral_vrf_api::ID)?;
+        config.mint = *mint_info.key;
+        config.token_program = *token_program_info.key;
+        config.escrow = *token_fee_escrow_info.key;
+        config.decimals = decimals;
+    }
+
+    let mut config =
+        token_fee_config_info.as_account_mut::<TokenFeeConfig>(&ephemeral_vrf_api::ID)?;
+    if config.mint.ne(mint_info.key) {
+        log(format!(
+            "Fee mint cannot be changed, expected: {}, got: {}",
+            config.mint, mint_info.key
+        ));
+        return Err(UnsupportedMint.into());
+    }
+    config.price = args.price;
+
+    Ok(())
+}
@@ -0,0 +1,118 @@
+use crate::request_randomness::validate_callback;
+use ephemeral_vrf_api::loaders::is_empty_or_zeroed;
+use ephemeral_vrf_api::prelude::*;
+
+/// Process the creation of a recurring randomness request
+///
+/// Accounts:
+///
+/// 0. `[signer, writable]` payer_info - Pays for the account and the budget, can cancel the
+///    subscription
+/// 1. `[signer]` program_identity_info - The identity PDA of the callback program
+/// 2. `[writable]` subscription_info - The subscription PDA ([SUBSCRIPTION, callback_program_id,
+///    caller_seed])
+/// 3. `[]` oracle_queue_info - The oracle queue serving the rounds
+/// 4. `[]` system_program - The system program
+///
+/// Requirements:
+///
+/// - The payer must be a signer
+/// - The program identity must be a valid signer and derived from the callback program ID
+/// - The queue must be a queue of the program, other than the default ephemeral queue whose
+///   requests are paid from an ephemeral balance
+/// - The interval must be at least one slot
+/// - The callback must meet the requirements of a request callback
+///
+/// 1. Create the subscription, its first round is due at `first_round_slot`
+/// 2. Transfer the budget paying the fees of the rounds to the subscription
+pub fn process_create_subscription(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    // Parse args
+    let args = CreateSubscription::try_from_bytes(data)?;
+    if args.interval_slots == 0 {
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    // Load accounts
+    let [payer_info, program_identity_info, subscription_info, oracle_queue_info, system_program] =
+        accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    payer_info.is_signer()?;
+    program_identity_info
+        .has_seeds(&[IDENTITY], &args.callback_program_id)?
+        .is_signer()?;
+    oracle_queue_info.has_owner(&ephemeral_vrf_api::ID)?;
+    Queue::try_from_bytes(&oracle_queue_info.try_borrow_data()?)?;
+    if oracle_queue_info.key.eq(&DEFAULT_EPHEMERAL_QUEUE) {
+        return Err(EphemeralVrfError::InvalidFeePayment.into());
+    }
+
+    let seeds: &[&[u8]] = &[
+        SUBSCRIPTION,
+        args.callback_program_id.as_ref(),
+        &args.caller_seed,
+    ];
+    subscription_info
+        .is_writable()?
+        .has_seeds(seeds, &ephemeral_vrf_api::ID)?;
+    is_empty_or_zeroed(subscription_info)?;
+
+    validate_callback(
+        &args.callback_program_id,
+        &args.caller_seed,
+        &args.callback_discriminator,
+        &args.callback_accounts_metas,
+        &args.callback_pda_metas,
+        false,
+        args.num_words,
+    )?;
+
+    let subscription = Subscription {
+        authority: *payer_info.key,
+        queue: *oracle_queue_info.key,
+        callback_program_id: args.callback_program_id,
+        caller_seed: args.caller_seed,
+        interval_slots: args.interval_slots,
+        next_round_slot: args.first_round_slot.max(Clock::get()?.slot),
+        sequence: 0,
+        num_words: args.num_words,
+        callback_abi: args.callback_abi,
+        callback_discriminator: args.callback_discriminator,
+        callback_accounts_metas: args.callback_accounts_metas,
+        callback_args: args.callback_args,
+        callback_pda_metas: args.callback_pda_metas,
+    };
+    let bump = subscription_pda(&subscription.callback_program_id, &subscription.caller_seed).1;
+    create_pda(
+        subscription_info,
+        &ephemeral_vrf_api::ID,
+        subscription.size_with_discriminator(),
+        seeds,
+        bump,
+        system_program,
+        payer_info,
+    )?;
+    let subscription_bytes = subscription.to_bytes_with_discriminator()?;
+    subscription_info
+        .try_borrow_mut_data()?
+        .copy_from_slice(&subscription_bytes);
+
+    // Fund the rounds
+    if args.budget > 0 {
+        solana_program::program::invoke(
+            &solana_system_interface::instruction::transfer(
+                payer_info.key,
+                subscription_info.key,
+                args.budget,
+            ),
+            &[
+                payer_info.clone(),
+                subscription_info.clone(),
+                system_program.clone(),
+            ],
+        )?;
+    }
+
+    Ok(())
+}
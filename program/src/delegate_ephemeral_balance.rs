@@ -0,0 +1,65 @@
+use ephemeral_rollups_sdk::cpi::{delegate_account, DelegateAccounts, DelegateConfig};
+use ephemeral_vrf_api::prelude::*;
+
+/// Process the delegation of a payer's ephemeral balance to the delegation program
+///
+/// Once delegated, requests on the default ephemeral queue debit their fees from the balance
+/// inside the ephemeral rollup.
+///
+/// Accounts:
+///
+/// 0. `[signer]` The payer owning the ephemeral balance
+/// 1. `[writable]` The ephemeral balance account to be delegated
+/// 2. `[writable]` The delegation buffer account
+/// 3. `[writable]` The delegation record account
+/// 4. `[writable]` The delegation metadata account
+/// 5. `[]` The delegation program
+/// 6. `[]` The owner program (must be the ephemeral VRF program)
+/// 7. `[]` The system program
+///
+/// Requirements:
+///
+/// - The payer (account 0) must be a signer.
+/// - The ephemeral balance (account 1) must be a valid PDA with seeds [EPHEMERAL_BALANCE, payer.key, index].
+/// - The owner program (account 6) must be the ephemeral VRF program.
+pub fn process_delegate_ephemeral_balance(
+    accounts: &[AccountInfo<'_>],
+    data: &[u8],
+) -> ProgramResult {
+    let args = DelegateEphemeralBalance::try_from_bytes(data)?;
+
+    // Load accounts.
+    let [payer_info, ephemeral_balance_info, buffer, delegation_record, delegation_metadata, delegation_program, owner_program, system_program] =
+        accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Checks
+    payer_info.is_signer()?;
+    owner_program.has_address(&ephemeral_vrf_api::ID)?;
+    let pda_seeds: &[&[u8]] = &[EPHEMERAL_BALANCE, &payer_info.key.to_bytes(), &[args.index]];
+    ephemeral_balance_info
+        .is_writable()?
+        .has_owner(&ephemeral_vrf_api::ID)?
+        .has_seeds(pda_seeds, &ephemeral_vrf_api::ID)?;
+
+    // Delegate
+    let delegate_accounts = DelegateAccounts {
+        payer: payer_info,
+        pda: ephemeral_balance_info,
+        owner_program,
+        buffer,
+        delegation_record,
+        delegation_metadata,
+        delegation_program,
+        system_program,
+    };
+    let delegate_config = DelegateConfig {
+        commit_frequency_ms: 0,
+        validator: Some(system_program::id()),
+    };
+    delegate_account(delegate_accounts, pda_seeds, delegate_config)?;
+
+    Ok(())
+}
@@ -1,5 +1,10 @@
-use solana_program::account_info::AccountInfo;
-use solana_program::program_error::ProgramError;
+use ephemeral_vrf_api::prelude::*;
+use solana_program::program::{invoke, invoke_signed};
+use solana_program::rent::Rent;
+use solana_system_interface::instruction as system_instruction;
+use spl_token_2022_interface::extension::StateWithExtensions;
+use spl_token_2022_interface::instruction::transfer_checked;
+use spl_token_2022_interface::state::Account;
 
 // Transfer a specific amount of lamports from the oracle queue account to the oracle account.
 // Assumes caller already validated seeds/ownership/writability and any signer requirements.
@@ -22,3 +27,264 @@ pub fn transfer_fee(
 
     Ok(())
 }
+
+// Debit a request cost from the payer's ephemeral balance into the oracle queue account,
+// keeping the balance rent-exempt.
+pub fn debit_ephemeral_balance(
+    ephemeral_balance_info: &AccountInfo<'_>,
+    payer: &Pubkey,
+    oracle_queue_info: &AccountInfo<'_>,
+    amount: u64,
+) -> Result<(), ProgramError> {
+    let index = {
+        let balance =
+            ephemeral_balance_info.as_account::<EphemeralBalance>(&ephemeral_vrf_api::ID)?;
+        if balance.payer.ne(payer) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        balance.index
+    };
+    ephemeral_balance_info.is_writable()?.has_seeds(
+        &[EPHEMERAL_BALANCE, payer.as_ref(), &[index]],
+        &ephemeral_vrf_api::ID,
+    )?;
+
+    transfer_above_rent(ephemeral_balance_info, oracle_queue_info, amount)
+}
+
+// Debit a request cost from a sponsor into the oracle queue account, after checking that the
+// callback program is whitelisted and counting the request against the sponsor's rate limits.
+// The per-user counter is created on first use, with its rent paid by the sponsor.
+pub fn debit_sponsor<'info>(
+    sponsor_info: &AccountInfo<'info>,
+    sponsor_usage_info: &AccountInfo<'info>,
+    user: &Pubkey,
+    callback_program_id: &Pubkey,
+    oracle_queue_info: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    amount: u64,
+) -> Result<(), ProgramError> {
+    sponsor_info
+        .is_writable()?
+        .has_owner(&ephemeral_vrf_api::ID)?;
+    let mut sponsor = Sponsor::try_from_bytes_with_discriminator(&sponsor_info.try_borrow_data()?)?;
+    sponsor_info.has_seeds(
+        &[SPONSOR, sponsor.authority.as_ref(), &[sponsor.index]],
+        &ephemeral_vrf_api::ID,
+    )?;
+
+    // Program whitelist and per-program limit
+    let slot = Clock::get()?.slot;
+    let window_slots = sponsor.window_slots;
+    let program = sponsor
+        .programs
+        .iter_mut()
+        .find(|program| program.program_id.eq(callback_program_id))
+        .ok_or(EphemeralVrfError::ProgramNotSponsored)?;
+    if !consume_rate_limit(
+        &mut program.window_start_slot,
+        &mut program.requests_in_window,
+        program.max_requests_per_window,
+        window_slots,
+        slot,
+    ) {
+        return Err(EphemeralVrfError::SponsorRateLimitExceeded.into());
+    }
+
+    // Per-user limit
+    if sponsor.max_requests_per_user != 0 {
+        let seeds: &[&[u8]] = &[SPONSOR_USAGE, sponsor_info.key.as_ref(), user.as_ref()];
+        sponsor_usage_info
+            .is_writable()?
+            .has_seeds(seeds, &ephemeral_vrf_api::ID)?;
+        if sponsor_usage_info.owner != &ephemeral_vrf_api::ID {
+            // The sponsor holds data and cannot fund a system transfer: allocate and assign the
+            // counter first, then move its rent, so that no balance changes across the CPIs
+            let space = 8 + core::mem::size_of::<SponsorUsage>();
+            let bump = [sponsor_usage_pda(sponsor_info.key, user).1];
+            let signer_seeds = [seeds, &[&bump[..]]].concat();
+            for ix in [
+                system_instruction::allocate(sponsor_usage_info.key, space as u64),
+                system_instruction::assign(sponsor_usage_info.key, &ephemeral_vrf_api::ID),
+            ] {
+                invoke_signed(
+                    &ix,
+                    &[sponsor_usage_info.clone(), system_program.clone()],
+                    &[&signer_seeds],
+                )?;
+            }
+            let rent = Rent::get()?
+                .minimum_balance(space)
+                .saturating_sub(sponsor_usage_info.lamports());
+            transfer_above_rent(sponsor_info, sponsor_usage_info, rent)?;
+            sponsor_usage_info.try_borrow_mut_data()?[0] = SponsorUsage::discriminator();
+        }
+        let mut usage =
+            sponsor_usage_info.as_account_mut::<SponsorUsage>(&ephemeral_vrf_api::ID)?;
+        let usage = &mut *usage;
+        if !consume_rate_limit(
+            &mut usage.window_start_slot,
+            &mut usage.requests_in_window,
+            sponsor.max_requests_per_user,
+            window_slots,
+            slot,
+        ) {
+            return Err(EphemeralVrfError::SponsorRateLimitExceeded.into());
+        }
+    }
+
+    // Counters are fixed size, the serialized sponsor keeps its length
+    let sponsor_bytes = sponsor.to_bytes_with_discriminator()?;
+    sponsor_info
+        .try_borrow_mut_data()?
+        .copy_from_slice(&sponsor_bytes);
+
+    transfer_above_rent(sponsor_info, oracle_queue_info, amount)
+}
+
+// Transfer lamports between program-owned accounts, keeping the source rent-exempt.
+pub fn transfer_above_rent(
+    from_info: &AccountInfo<'_>,
+    to_info: &AccountInfo<'_>,
+    amount: u64,
+) -> Result<(), ProgramError> {
+    let available = from_info
+        .lamports()
+        .saturating_sub(Rent::get()?.minimum_balance(from_info.data_len()));
+    if amount > available {
+        return Err(ProgramError::InsufficientFunds);
+    }
+    transfer_fee(from_info, to_info, amount)
+}
+
+/// Accounts used to move token fees in and out of a queue's fee escrow, in the order they
+/// are appended to request, provide and purge instructions.
+pub struct TokenFeeAccounts<'a, 'info> {
+    pub config: &'a AccountInfo<'info>,
+    pub escrow: &'a AccountInfo<'info>,
+    pub token_account: &'a AccountInfo<'info>,
+    pub mint: &'a AccountInfo<'info>,
+    pub token_program: &'a AccountInfo<'info>,
+}
+
+impl<'a, 'info> TokenFeeAccounts<'a, 'info> {
+    pub const LEN: usize = 5;
+
+    // Load the token fee accounts and check them against the queue's fee configuration.
+    pub fn load(
+        accounts: &'a [AccountInfo<'info>],
+        oracle_queue: &Pubkey,
+    ) -> Result<(Self, TokenFeeConfig), ProgramError> {
+        let [config, escrow, token_account, mint, token_program] = accounts else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+        config.has_seeds(
+            &[TOKEN_FEE_CONFIG, oracle_queue.as_ref()],
+            &ephemeral_vrf_api::ID,
+        )?;
+        let fee_config = *config.as_account::<TokenFeeConfig>(&ephemeral_vrf_api::ID)?;
+        escrow.is_writable()?.has_address(&fee_config.escrow)?;
+        token_account.is_writable()?;
+        mint.has_address(&fee_config.mint)?;
+        token_program.has_address(&fee_config.token_program)?;
+        Ok((
+            Self {
+                config,
+                escrow,
+                token_account,
+                mint,
+                token_program,
+            },
+            fee_config,
+        ))
+    }
+
+    // Transfer the fee from the payer's token account into the escrow.
+    pub fn collect(
+        &self,
+        payer_info: &AccountInfo<'info>,
+        fee_config: &TokenFeeConfig,
+        amount: u64,
+    ) -> Result<(), ProgramError> {
+        let ix = transfer_checked(
+            self.token_program.key,
+            self.token_account.key,
+            self.mint.key,
+            self.escrow.key,
+            payer_info.key,
+            &[],
+            amount,
+            fee_config.decimals,
+        )?;
+        invoke(
+            &ix,
+            &[
+                self.token_account.clone(),
+                self.mint.clone(),
+                self.escrow.clone(),
+                payer_info.clone(),
+                self.token_program.clone(),
+            ],
+        )
+    }
+
+    // Transfer the fee from the escrow to the token account, which must be owned by the oracle.
+    pub fn pay(
+        &self,
+        oracle_queue: &Pubkey,
+        oracle: &Pubkey,
+        fee_config: &TokenFeeConfig,
+        amount: u64,
+    ) -> Result<(), ProgramError> {
+        self.pay_to(self.token_account, oracle_queue, oracle, fee_config, amount)
+    }
+
+    // Transfer the fee from the escrow to `token_account`, which must be owned by `owner`.
+    pub fn pay_to(
+        &self,
+        token_account: &AccountInfo<'info>,
+        oracle_queue: &Pubkey,
+        owner: &Pubkey,
+        fee_config: &TokenFeeConfig,
+        amount: u64,
+    ) -> Result<(), ProgramError> {
+        if !self.is_token_account_of(token_account, owner) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let ix = transfer_checked(
+            self.token_program.key,
+            self.escrow.key,
+            self.mint.key,
+            token_account.key,
+            self.config.key,
+            &[],
+            amount,
+            fee_config.decimals,
+        )?;
+        let bump = token_fee_config_pda(oracle_queue).1;
+        invoke_signed(
+            &ix,
+            &[
+                self.escrow.clone(),
+                self.mint.clone(),
+                token_account.clone(),
+                self.config.clone(),
+                self.token_program.clone(),
+            ],
+            &[&[TOKEN_FEE_CONFIG, oracle_queue.as_ref(), &[bump]]],
+        )
+    }
+
+    // Whether `token_account` is a token account of the fee mint owned by `owner`.
+    pub fn is_token_account_of(&self, token_account: &AccountInfo<'info>, owner: &Pubkey) -> bool {
+        if token_account.owner.ne(self.token_program.key) {
+            return false;
+        }
+        let Ok(data) = token_account.try_borrow_data() else {
+            return false;
+        };
+        StateWithExtensions::<Account>::unpack(&data).is_ok_and(|token_account| {
+            token_account.base.owner.eq(owner) && token_account.base.mint.eq(self.mint.key)
+        })
+    }
+}
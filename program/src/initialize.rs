@@ -1,3 +1,5 @@
+use ephemeral_vrf_api::loaders::load_program_upgrade_authority;
+use ephemeral_vrf_api::prelude::EphemeralVrfError::Unauthorized;
 use ephemeral_vrf_api::prelude::*;
 
 /// Process the initialization of the EphemeralVrf program
@@ -7,41 +9,73 @@ use ephemeral_vrf_api::prelude::*;
 /// 0; `[signer]` The authority that initializes the program
 /// 1; `[]`       The oracles account (PDA to be created)
 /// 2; `[]`       The system program
+/// 3; `[]`       The admin account (PDA to be created)
+/// 4; `[]`       The program data account, used to read the program's upgrade authority
 ///
 /// Requirements:
 ///
-/// - The authority (account 0) must be a signer.
-/// - The oracles account (account 1) must be empty and use the correct seeds ([ORACLES]).
+/// - The authority (account 0) must be a signer and the program upgrade authority.
+/// - The oracles account (account 1) must use the correct seeds ([ORACLES]), it is kept if
+///   already initialized.
+/// - The admin account (account 3) must be empty and use the correct seeds ([ADMIN]).
 ///
 /// 1. Parse the instruction data and extract arguments (Initialize).
-/// 2. Create the oracles PDA.
-/// 3. Write the default Oracles data to the new PDA.
+/// 2. Create the oracles PDA and write the default Oracles data, unless it exists.
+/// 3. Create the admin PDA, with the authority as admin and `REASSIGN_AFTER_TTL_BPS` as
+///    reassign delay.
 pub fn process_initialize(accounts: &[AccountInfo<'_>], _data: &[u8]) -> ProgramResult {
     // Load accounts.
-    let [signer_info, oracles_info, system_program] = accounts else {
+    let [signer_info, oracles_info, system_program, admin_info, program_data_info] = accounts
+    else {
         return Err(ProgramError::NotEnoughAccountKeys);
     };
     signer_info.is_signer()?;
+
+    // The admin is first set to the program upgrade authority, then managed independently
+    let upgrade_authority =
+        load_program_upgrade_authority(&ephemeral_vrf_api::ID, program_data_info)?
+            .ok_or(Unauthorized)?;
+    if !signer_info.key.eq(&upgrade_authority) {
+        return Err(Unauthorized.into());
+    }
+
     oracles_info
-        .is_empty()?
         .is_writable()?
         .has_seeds(&[ORACLES], &ephemeral_vrf_api::ID)?;
+    admin_info
+        .is_empty()?
+        .is_writable()?
+        .has_seeds(&[ADMIN], &ephemeral_vrf_api::ID)?;
 
-    let oracles = Oracles::default();
-    let oracles_bytes = oracles.to_bytes_with_discriminator()?;
+    // Programs initialized before the admin account only create it
+    if oracles_info.owner != &ephemeral_vrf_api::ID {
+        let oracles = Oracles::default();
+        let oracles_bytes = oracles.to_bytes_with_discriminator()?;
 
-    create_pda(
-        oracles_info,
-        &ephemeral_vrf_api::ID,
-        oracles_bytes.len(),
-        &[ORACLES],
-        oracles_pda().1,
+        create_pda(
+            oracles_info,
+            &ephemeral_vrf_api::ID,
+            oracles_bytes.len(),
+            &[ORACLES],
+            oracles_pda().1,
+            system_program,
+            signer_info,
+        )?;
+
+        let mut oracles_data = oracles_info.try_borrow_mut_data()?;
+        oracles_data.copy_from_slice(&oracles_bytes);
+    }
+
+    create_program_account::<Admin>(
+        admin_info,
         system_program,
         signer_info,
+        &ephemeral_vrf_api::ID,
+        &[ADMIN],
     )?;
-
-    let mut oracles_data = oracles_info.try_borrow_mut_data()?;
-    oracles_data.copy_from_slice(&oracles_bytes);
+    let mut admin = admin_info.as_account_mut::<Admin>(&ephemeral_vrf_api::ID)?;
+    admin.authority = *signer_info.key;
+    admin.reassign_after_ttl_bps = REASSIGN_AFTER_TTL_BPS;
 
     Ok(())
 }
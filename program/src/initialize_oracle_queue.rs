@@ -2,7 +2,7 @@ use ephemeral_vrf_api::loaders::is_empty_or_zeroed;
 use ephemeral_vrf_api::prelude::EphemeralVrfError::Unauthorized;
 use ephemeral_vrf_api::prelude::*;
 use solana_program::msg;
-const MAX_EXTRA_BYTES: usize = 10_240;
+pub(crate) const MAX_EXTRA_BYTES: usize = 10_240;
 
 /// Process the initialization of the Oracle queue
 ///
@@ -79,18 +79,60 @@ pub fn process_initialize_oracle_queue(accounts: &[AccountInfo<'_>], data: &[u8]
 
     // PDA creation or reallocation
     let seeds: &[&[u8]] = &[QUEUE, oracle_key_ref, &[args.index]];
-    let bump = Pubkey::find_program_address(seeds, &ephemeral_vrf_api::ID).1;
+    if !allocate_queue(
+        signer_info,
+        oracle_queue_info,
+        system_program,
+        seeds,
+        args.target_size as usize,
+    )? {
+        return Ok(());
+    }
+
+    // Set discriminator and initialize queue header using zero-copy view
+    {
+        let mut data = oracle_queue_info.data.borrow_mut();
+        let disc = AccountDiscriminator::Queue.to_bytes();
+        data[..8].copy_from_slice(&disc);
+        let acc_without_disc = &mut data[8..];
+        let qacc = QueueAccount::load(acc_without_disc)?;
+        qacc.header.index = args.index;
+    }
+
+    // Increment oracle's open queue count
+    let mut oracle_data_mut = oracle_data_info.as_account_mut::<Oracle>(&ephemeral_vrf_api::ID)?;
+    oracle_data_mut.open_queue = oracle_data_mut.open_queue.saturating_add(1);
+
+    EphemeralVrfEvent::QueueCreated(QueueCreated {
+        queue: *oracle_queue_info.key,
+        oracle: *oracle_info.key,
+        index: args.index,
+    })
+    .emit();
 
-    let target_size = args.target_size as usize;
-    let current_size = oracle_queue_info.data_len();
+    Ok(())
+}
+
+/// Create or grow a queue PDA towards `target_size`, returning whether it reached it.
+///
+/// Queues growing by more than `MAX_EXTRA_BYTES` need the instruction to be repeated.
+pub(crate) fn allocate_queue<'info>(
+    signer_info: &AccountInfo<'info>,
+    queue_info: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    seeds: &[&[u8]],
+    target_size: usize,
+) -> Result<bool, ProgramError> {
+    let bump = Pubkey::find_program_address(seeds, &ephemeral_vrf_api::ID).1;
+    let current_size = queue_info.data_len();
 
     let extra_bytes = target_size.saturating_sub(current_size);
 
     if extra_bytes > MAX_EXTRA_BYTES {
         let realloc_size = current_size + MAX_EXTRA_BYTES;
-        if oracle_queue_info.owner != &ephemeral_vrf_api::ID {
+        if queue_info.owner != &ephemeral_vrf_api::ID {
             create_pda(
-                oracle_queue_info,
+                queue_info,
                 &ephemeral_vrf_api::ID,
                 MAX_EXTRA_BYTES,
                 seeds,
@@ -99,20 +141,20 @@ pub fn process_initialize_oracle_queue(accounts: &[AccountInfo<'_>], data: &[u8]
                 signer_info,
             )?;
         } else {
-            resize_pda(signer_info, oracle_queue_info, system_program, realloc_size)?;
+            resize_pda(signer_info, queue_info, system_program, realloc_size)?;
         }
         msg!(
-            "Reallocating oracle queue account by 10_240 bytes, execute one more time. Current size: {}, target size: {}",
+            "Reallocating queue account by 10_240 bytes, execute one more time. Current size: {}, target size: {}",
             current_size,
             target_size
         );
-        return Ok(());
+        return Ok(false);
     }
 
     // Finalize PDA size if needed
-    if oracle_queue_info.owner != &ephemeral_vrf_api::ID {
+    if queue_info.owner != &ephemeral_vrf_api::ID {
         create_pda(
-            oracle_queue_info,
+            queue_info,
             &ephemeral_vrf_api::ID,
             target_size,
             seeds,
@@ -121,22 +163,8 @@ pub fn process_initialize_oracle_queue(accounts: &[AccountInfo<'_>], data: &[u8]
             signer_info,
         )?;
     } else {
-        resize_pda(signer_info, oracle_queue_info, system_program, target_size)?;
+        resize_pda(signer_info, queue_info, system_program, target_size)?;
     }
 
-    // Set discriminator and initialize queue header using zero-copy view
-    {
-        let mut data = oracle_queue_info.data.borrow_mut();
-        let disc = AccountDiscriminator::Queue.to_bytes();
-        data[..8].copy_from_slice(&disc);
-        let acc_without_disc = &mut data[8..];
-        let qacc = QueueAccount::load(acc_without_disc)?;
-        qacc.header.index = args.index;
-    }
-
-    // Increment oracle's open queue count
-    let mut oracle_data_mut = oracle_data_info.as_account_mut::<Oracle>(&ephemeral_vrf_api::ID)?;
-    oracle_data_mut.open_queue = oracle_data_mut.open_queue.saturating_add(1);
-
-    Ok(())
+    Ok(true)
 }
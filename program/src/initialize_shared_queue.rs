@@ -0,0 +1,68 @@
+use crate::initialize_oracle_queue::allocate_queue;
+use ephemeral_vrf_api::loaders::{check_admin_role, is_empty_or_zeroed};
+use ephemeral_vrf_api::prelude::*;
+
+/// Process the initialization of a queue shared by the registered oracles
+///
+/// Like `InitializeOracleQueue`, the instruction is repeated until the queue reaches its target
+/// size. Requests of a shared queue can be fulfilled by any oracle of the `Oracles` account, the
+/// first valid proof is paid.
+///
+/// Accounts:
+///
+/// 0. `[signer]` signer - Must be the admin authority or oracle manager, pays for the queue
+/// 1. `[writable]` shared_queue_info - The shared queue account (PDA to be created)
+/// 2. `[]` admin_info - The admin account ([ADMIN])
+/// 3. `[]` system_program - System program for account creation
+///
+/// Requirements:
+///
+/// - Signer must be the admin authority or the oracle manager
+/// - The queue must be empty and use the correct seeds ([SHARED_QUEUE, index])
+///
+/// 1. Verify the signer is the admin
+/// 2. Create or grow the queue PDA
+/// 3. Initialize the queue header, flagged as shared
+pub fn process_initialize_shared_queue(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    // Parse args
+    let args = InitializeSharedQueue::try_from_bytes(data)?;
+
+    // Load accounts
+    let [signer_info, shared_queue_info, admin_info, system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    check_admin_role(admin_info, signer_info, AdminRole::OracleManager)?;
+
+    let seeds: &[&[u8]] = &[SHARED_QUEUE, &[args.index]];
+    shared_queue_info
+        .is_writable()?
+        .has_seeds(seeds, &ephemeral_vrf_api::ID)?;
+    is_empty_or_zeroed(shared_queue_info)?;
+
+    if !allocate_queue(
+        signer_info,
+        shared_queue_info,
+        system_program,
+        seeds,
+        args.target_size as usize,
+    )? {
+        return Ok(());
+    }
+
+    {
+        let mut data = shared_queue_info.data.borrow_mut();
+        data[..8].copy_from_slice(&AccountDiscriminator::Queue.to_bytes());
+        let qacc = QueueAccount::load(&mut data[8..])?;
+        qacc.header.index = args.index;
+        qacc.header.shared = 1;
+    }
+
+    EphemeralVrfEvent::QueueCreated(QueueCreated {
+        queue: *shared_queue_info.key,
+        oracle: Pubkey::default(),
+        index: args.index,
+    })
+    .emit();
+
+    Ok(())
+}
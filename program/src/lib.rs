@@ -1,26 +1,78 @@
 #![allow(unexpected_cfgs)]
+mod accept_admin;
+mod cancel_subscription;
+mod claim_queue_fees;
 mod close_oracle_queue;
+mod close_randomness_result;
+mod close_receipt;
+mod configure_beacon;
+mod configure_sponsor;
+mod configure_token_fee;
+mod create_subscription;
+mod delegate_ephemeral_balance;
 mod delegate_oracle_queue;
 mod fees;
 mod initialize;
 mod initialize_oracle_queue;
+mod initialize_shared_queue;
+mod migrate_queue;
 mod modify_oracles;
+mod modify_sponsor;
 mod process_undelegation;
+mod propose_admin;
 mod provide_randomness;
+mod provide_randomness_batch;
+mod provide_randomness_fallback;
 mod purge_expired_requests;
+mod reassign_request;
 mod request_randomness;
+mod set_admin_role;
+mod set_queue_paused;
+mod set_reassign_delay;
+mod top_up_ephemeral_balance;
+mod trigger_subscription;
+mod undelegate_ephemeral_balance;
 mod undelegate_oracle_queue;
+mod update_beacon;
+mod withdraw_ephemeral_balance;
+mod withdraw_sponsor;
 
+use accept_admin::*;
+use cancel_subscription::*;
+use claim_queue_fees::*;
 use close_oracle_queue::*;
+use close_randomness_result::*;
+use close_receipt::*;
+use configure_beacon::*;
+use configure_sponsor::*;
+use configure_token_fee::*;
+use create_subscription::*;
+use delegate_ephemeral_balance::*;
 use delegate_oracle_queue::*;
 use initialize::*;
 use initialize_oracle_queue::*;
+use initialize_shared_queue::*;
+use migrate_queue::*;
 use modify_oracles::*;
+use modify_sponsor::*;
 use process_undelegation::*;
+use propose_admin::*;
 use provide_randomness::*;
+use provide_randomness_batch::*;
+use provide_randomness_fallback::*;
 use purge_expired_requests::*;
+use reassign_request::*;
 use request_randomness::*;
+use set_admin_role::*;
+use set_queue_paused::*;
+use set_reassign_delay::*;
+use top_up_ephemeral_balance::*;
+use trigger_subscription::*;
+use undelegate_ephemeral_balance::*;
 use undelegate_oracle_queue::*;
+use update_beacon::*;
+use withdraw_ephemeral_balance::*;
+use withdraw_sponsor::*;
 
 use ephemeral_vrf_api::prelude::*;
 
@@ -59,6 +111,12 @@ pub fn process_instruction(
             process_request_randomness(accounts, data, false)?
         }
         EphemeralVrfInstruction::ProvideRandomness => process_provide_randomness(accounts, data)?,
+        EphemeralVrfInstruction::ProvideRandomnessFallback => {
+            process_provide_randomness_fallback(accounts, data)?
+        }
+        EphemeralVrfInstruction::ProvideRandomnessBatch => {
+            process_provide_randomness_batch(accounts, data)?
+        }
         EphemeralVrfInstruction::DelegateOracleQueue => {
             process_delegate_oracle_queue(accounts, data)?
         }
@@ -70,6 +128,44 @@ pub fn process_instruction(
         EphemeralVrfInstruction::PurgeExpiredRequests => {
             process_purge_expired_requests(accounts, data)?
         }
+        EphemeralVrfInstruction::ConfigureTokenFee => process_configure_token_fee(accounts, data)?,
+        EphemeralVrfInstruction::TopUpEphemeralBalance => {
+            process_top_up_ephemeral_balance(accounts, data)?
+        }
+        EphemeralVrfInstruction::DelegateEphemeralBalance => {
+            process_delegate_ephemeral_balance(accounts, data)?
+        }
+        EphemeralVrfInstruction::UndelegateEphemeralBalance => {
+            process_undelegate_ephemeral_balance(accounts, data)?
+        }
+        EphemeralVrfInstruction::WithdrawEphemeralBalance => {
+            process_withdraw_ephemeral_balance(accounts, data)?
+        }
+        EphemeralVrfInstruction::ClaimQueueFees => process_claim_queue_fees(accounts, data)?,
+        EphemeralVrfInstruction::ConfigureSponsor => process_configure_sponsor(accounts, data)?,
+        EphemeralVrfInstruction::ModifySponsor => process_modify_sponsor(accounts, data)?,
+        EphemeralVrfInstruction::WithdrawSponsor => process_withdraw_sponsor(accounts, data)?,
+        EphemeralVrfInstruction::CreateSubscription => process_create_subscription(accounts, data)?,
+        EphemeralVrfInstruction::CancelSubscription => process_cancel_subscription(accounts, data)?,
+        EphemeralVrfInstruction::TriggerSubscription => {
+            process_trigger_subscription(accounts, data)?
+        }
+        EphemeralVrfInstruction::ConfigureBeacon => process_configure_beacon(accounts, data)?,
+        EphemeralVrfInstruction::UpdateBeacon => process_update_beacon(accounts, data)?,
+        EphemeralVrfInstruction::CloseReceipt => process_close_receipt(accounts, data)?,
+        EphemeralVrfInstruction::InitializeSharedQueue => {
+            process_initialize_shared_queue(accounts, data)?
+        }
+        EphemeralVrfInstruction::ReassignRequest => process_reassign_request(accounts, data)?,
+        EphemeralVrfInstruction::ProposeAdmin => process_propose_admin(accounts, data)?,
+        EphemeralVrfInstruction::AcceptAdmin => process_accept_admin(accounts, data)?,
+        EphemeralVrfInstruction::SetAdminRole => process_set_admin_role(accounts, data)?,
+        EphemeralVrfInstruction::SetQueuePaused => process_set_queue_paused(accounts, data)?,
+        EphemeralVrfInstruction::SetReassignDelay => process_set_reassign_delay(accounts, data)?,
+        EphemeralVrfInstruction::MigrateQueue => process_migrate_queue(accounts, data)?,
+        EphemeralVrfInstruction::CloseRandomnessResult => {
+            process_close_randomness_result(accounts, data)?
+        }
     }
 
     Ok(())
@@ -0,0 +1,64 @@
+use crate::initialize_oracle_queue::MAX_EXTRA_BYTES;
+use ephemeral_vrf_api::prelude::*;
+use solana_program::msg;
+
+/// Process the migration of a queue created before `QUEUE_VERSION`
+///
+/// Permissionless, converts the pending requests of the queue to the current layout so that
+/// they can be fulfilled. Empty queues are upgraded on their next use and need no migration.
+///
+/// This instruction is designed to be repeated until the queue is migrated, the account grows
+/// by at most 10_240 bytes per instruction when the converted requests don't fit.
+///
+/// Accounts:
+///
+/// 0. `[signer, writable]` payer_info - Pays for the growth of the queue
+/// 1. `[writable]` queue_info - The oracle or shared queue
+/// 2. `[]` system_program - The system program
+///
+/// Requirements:
+///
+/// - The queue must be owned by the program, delegated queues are migrated where they are
+///   delegated
+///
+/// 1. Grow the queue towards the size of the converted requests
+/// 2. Rewrite the requests in the current layout
+pub fn process_migrate_queue(accounts: &[AccountInfo<'_>], _data: &[u8]) -> ProgramResult {
+    // Load accounts
+    let [payer_info, queue_info, system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    payer_info.is_signer()?;
+    queue_info
+        .is_writable()?
+        .has_owner(&ephemeral_vrf_api::ID)?;
+
+    let target_size = {
+        let data = queue_info.try_borrow_data()?;
+        Queue::try_from_bytes(&data)?;
+        if !QueueAccount::is_legacy(&data[8..]) {
+            return Ok(());
+        }
+        8 + QueueAccount::migrated_len(&data[8..])
+    };
+
+    // Grow the queue until the converted requests fit
+    let current_size = queue_info.data_len();
+    if target_size > current_size {
+        let new_size = target_size.min(current_size + MAX_EXTRA_BYTES);
+        resize_pda(payer_info, queue_info, system_program, new_size)?;
+        if new_size < target_size {
+            msg!(
+                "Reallocating queue account by 10_240 bytes, execute one more time. Current size: {}, target size: {}",
+                new_size,
+                target_size
+            );
+            return Ok(());
+        }
+    }
+
+    let mut data = queue_info.try_borrow_mut_data()?;
+    QueueAccount::migrate_legacy(&mut data[8..])?;
+
+    Ok(())
+}
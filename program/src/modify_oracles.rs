@@ -1,7 +1,5 @@
-use ephemeral_vrf_api::loaders::load_program_upgrade_authority;
-use ephemeral_vrf_api::prelude::EphemeralVrfError::{
-    InvalidOracleIdentity, QueueNotEmpty, Unauthorized,
-};
+use ephemeral_vrf_api::loaders::check_admin_role;
+use ephemeral_vrf_api::prelude::EphemeralVrfError::{InvalidOracleIdentity, QueueNotEmpty};
 use ephemeral_vrf_api::prelude::*;
 use ephemeral_vrf_api::verify::is_on_curve;
 use solana_program::msg;
@@ -10,15 +8,15 @@ use solana_program::msg;
 ///
 /// Accounts:
 ///
-/// 0. `[signer]` signer - Must be the admin
+/// 0. `[signer]` signer - Must be the admin authority or oracle manager
 /// 1. `[writable]` oracles_info - PDA that stores the list of oracle identities
 /// 2. `[writable]` oracle_data_info - PDA that stores the oracle data
-/// 2. `[]` program data account - Used to read the program's upgrade authority
-/// 3. `[]` system_program - System program for account creation/closing
+/// 3. `[]` admin_info - The admin account ([ADMIN])
+/// 4. `[]` system_program - System program for account creation/closing
 ///
 /// Requirements:
 ///
-/// - Signer must be the admin (ADMIN_PUBKEY)
+/// - Signer must be the admin authority or the oracle manager
 /// - For adding an oracle (operation = 0):
 ///   - Oracle data account is created
 ///   - Oracle identity is added to the oracles list
@@ -44,24 +42,12 @@ pub fn process_modify_oracles(accounts: &[AccountInfo<'_>], data: &[u8]) -> Prog
     }
 
     // Load accounts.
-    let [signer_info, oracles_info, oracle_data_info, program_data_info, system_program] = accounts
-    else {
+    let [signer_info, oracles_info, oracle_data_info, admin_info, system_program] = accounts else {
         return Err(ProgramError::NotEnoughAccountKeys);
     };
-    signer_info.is_signer()?;
 
-    // Check that the signer is the admin.
-    // The admin is the program upgrade authority, which should be a multi-sig.
-    let admin_pubkey = load_program_upgrade_authority(&ephemeral_vrf_api::ID, program_data_info)?
-        .ok_or(Unauthorized)?;
-
-    if !signer_info.key.eq(&admin_pubkey) {
-        log(format!(
-            "Signer not authorized, expected: {}, got: {}",
-            admin_pubkey, signer_info.key
-        ));
-        return Err(Unauthorized.into());
-    }
+    // Check that the signer is the admin or its oracle manager.
+    check_admin_role(admin_info, signer_info, AdminRole::OracleManager)?;
 
     oracles_info
         .is_writable()?
@@ -116,5 +102,12 @@ pub fn process_modify_oracles(accounts: &[AccountInfo<'_>], data: &[u8]) -> Prog
 
     oracles_data.copy_from_slice(&oracles_bytes);
 
+    EphemeralVrfEvent::OracleModified(OracleModified {
+        identity: args.identity,
+        vrf_pubkey: args.oracle_pubkey.0,
+        added: args.operation == 0,
+    })
+    .emit();
+
     Ok(())
 }
@@ -0,0 +1,87 @@
+use ephemeral_vrf_api::prelude::*;
+use solana_program::program::invoke;
+use solana_program::rent::Rent;
+use solana_system_interface::instruction as system_instruction;
+
+/// Process the modification of the programs whitelisted by a sponsor
+///
+/// Accounts:
+///
+/// 0. `[signer, writable]` authority_info - The sponsor authority, pays for the account growth
+/// 1. `[writable]` sponsor_info - The sponsor PDA ([SPONSOR, authority, index])
+/// 2. `[]` system_program - The system program
+///
+/// Requirements:
+///
+/// - The authority must be a signer
+/// - For adding a program (operation = 0), the program is added or its limit updated
+/// - For removing a program (operation = 1), the program must be whitelisted
+///
+/// 1. Load the sponsor
+/// 2. Add, update or remove the program
+/// 3. Resize the sponsor PDA, the authority pays the extra rent
+pub fn process_modify_sponsor(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    // Parse args
+    let args = ModifySponsor::try_from_bytes(data)?;
+
+    // Load accounts
+    let [authority_info, sponsor_info, system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    authority_info.is_signer()?;
+    sponsor_info
+        .is_writable()?
+        .has_owner(&ephemeral_vrf_api::ID)?
+        .has_seeds(
+            &[SPONSOR, authority_info.key.as_ref(), &[args.index]],
+            &ephemeral_vrf_api::ID,
+        )?;
+
+    let mut sponsor = Sponsor::try_from_bytes_with_discriminator(&sponsor_info.try_borrow_data()?)?;
+    let position = sponsor
+        .programs
+        .iter()
+        .position(|program| program.program_id.eq(&args.program_id));
+
+    match (args.operation, position) {
+        (0, Some(position)) => {
+            sponsor.programs[position].max_requests_per_window = args.max_requests_per_window;
+        }
+        (0, None) => sponsor.programs.push(SponsoredProgram {
+            program_id: args.program_id,
+            max_requests_per_window: args.max_requests_per_window,
+            ..Default::default()
+        }),
+        (1, Some(position)) => {
+            sponsor.programs.remove(position);
+        }
+        (1, None) => return Err(EphemeralVrfError::ProgramNotSponsored.into()),
+        _ => return Err(ProgramError::InvalidArgument),
+    }
+
+    // Resize without touching the sponsored funds: the authority pays for growth, and the rent
+    // freed by shrinking stays available for fees
+    let new_size = sponsor.size_with_discriminator();
+    let rent = Rent::get()?;
+    let extra_rent = rent
+        .minimum_balance(new_size)
+        .saturating_sub(rent.minimum_balance(sponsor_info.data_len()));
+    if extra_rent > 0 {
+        invoke(
+            &system_instruction::transfer(authority_info.key, sponsor_info.key, extra_rent),
+            &[
+                authority_info.clone(),
+                sponsor_info.clone(),
+                system_program.clone(),
+            ],
+        )?;
+    }
+    sponsor_info.resize(new_size)?;
+
+    let sponsor_bytes = sponsor.to_bytes_with_discriminator()?;
+    sponsor_info
+        .try_borrow_mut_data()?
+        .copy_from_slice(&sponsor_bytes);
+
+    Ok(())
+}
@@ -0,0 +1,37 @@
+use ephemeral_vrf_api::prelude::EphemeralVrfError::Unauthorized;
+use ephemeral_vrf_api::prelude::*;
+
+/// Process the proposal of a new admin authority
+///
+/// Accounts:
+///
+/// 0. `[signer]` authority_info - The current admin authority
+/// 1. `[writable]` admin_info - The admin account ([ADMIN])
+///
+/// Requirements:
+///
+/// - The signer must be the admin authority, signing through CPI if it is a multisig PDA
+///
+/// 1. Record the proposed authority, to be accepted with `AcceptAdmin`. The default pubkey
+///    cancels a pending transfer.
+pub fn process_propose_admin(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    // Parse args
+    let args = ProposeAdmin::try_from_bytes(data)?;
+
+    // Load accounts
+    let [authority_info, admin_info] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    authority_info.is_signer()?;
+    admin_info
+        .is_writable()?
+        .has_seeds(&[ADMIN], &ephemeral_vrf_api::ID)?;
+
+    let mut admin = admin_info.as_account_mut::<Admin>(&ephemeral_vrf_api::ID)?;
+    if admin.authority.ne(authority_info.key) {
+        return Err(Unauthorized.into());
+    }
+    admin.pending_authority = args.authority;
+
+    Ok(())
+}
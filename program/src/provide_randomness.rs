@@ -1,6 +1,9 @@
+use crate::fees::TokenFeeAccounts;
 use ephemeral_vrf_api::prelude::*;
-use ephemeral_vrf_api::verify::verify_vrf;
+use ephemeral_vrf_api::verify::{random_words, verify_vrf};
+use solana_curve25519::ristretto::PodRistrettoPoint;
 use solana_program::hash::hash;
+use std::cell::RefMut;
 
 /// Process the provide randomness instruction which verifies VRF proof and executes vrf-macro
 ///
@@ -11,19 +14,26 @@ use solana_program::hash::hash;
 /// 2. `[]` oracle_data_info - Oracle data account associated with the signer
 /// 3. `[writable]` oracle_queue_info - Queue storing randomness requests
 /// 4. `[]` callback_program_info - Program to call with the randomness
-/// 5. `[varies]` remaining_accounts - Accounts needed for the vrf-macro
+/// 5. `[varies]` remaining_accounts - Accounts needed for the vrf-macro (the result PDA for
+///    pull-mode requests): the fixed metas, then the accounts derived from the PDA metas,
+///    followed by the token fee accounts (see `token_fee_accounts`) when the request was paid
+///    in tokens, and by the receipt PDA of the request if it was created with one
 ///
 /// Requirements:
 ///
 /// - Signer must be a registered oracle with valid VRF keypair
+/// - The queue must be owned by the signer, or shared by the registered oracles
 /// - VRF proof must be valid for the given input and output
 /// - Request must exist in the oracle queue
+/// - Request must be due if scheduled
 /// - Oracle signer must not be included in vrf-macro accounts
+/// - Accounts of PDA metas must match their derivation
 ///
 /// 1. Verify the oracle signer and load oracle data
 /// 2. Verify the VRF proof
 /// 3. Remove the request from the queue
-/// 4. Invoke the vrf-macro with the randomness
+/// 4. Invoke the vrf-macro with the random words of the request, preceded by the fulfillment
+///    header and the proof if opted into, or write the randomness to the result PDA in pull mode
 pub fn process_provide_randomness(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
     // Parse args
     let args = ProvideRandomness::try_from_bytes(data)?;
@@ -37,6 +47,67 @@ pub fn process_provide_randomness(accounts: &[AccountInfo<'_>], data: &[u8]) ->
         return Err(ProgramError::NotEnoughAccountKeys);
     };
 
+    let oracle_vrf_pubkey = verify_oracle(oracle_info, oracle_data_info, oracle_queue_info)?;
+    verify_proof(&oracle_vrf_pubkey, args)?;
+    let removed = take_request(oracle_info, oracle_queue_info, &args.input)?;
+    fulfill(
+        oracle_info,
+        program_identity_info,
+        oracle_queue_info,
+        callback_program_info,
+        remaining_accounts,
+        args,
+        &oracle_vrf_pubkey,
+        removed,
+    )
+}
+
+/// A request removed from the queue with its callback metas, discriminator and args.
+pub(crate) struct RemovedRequest {
+    pub item: QueueItem,
+    pub metas: Vec<CompactAccountMeta>,
+    pub discriminator: Vec<u8>,
+    pub args: Vec<u8>,
+    pub pda_metas: Vec<PdaAccountMeta>,
+}
+
+/// Verify the oracle and the VRF proof, then remove the request from the queue.
+pub(crate) fn take_verified_request(
+    oracle_info: &AccountInfo<'_>,
+    oracle_data_info: &AccountInfo<'_>,
+    oracle_queue_info: &AccountInfo<'_>,
+    args: &ProvideRandomness,
+) -> Result<RemovedRequest, ProgramError> {
+    let oracle_vrf_pubkey = verify_oracle(oracle_info, oracle_data_info, oracle_queue_info)?;
+    verify_proof(&oracle_vrf_pubkey, args)?;
+    take_request(oracle_info, oracle_queue_info, &args.input)
+}
+
+/// Verify the VRF proof of the randomness against the VRF public key of the oracle.
+fn verify_proof(oracle_vrf_pubkey: &PodRistrettoPoint, args: &ProvideRandomness) -> ProgramResult {
+    let verified = verify_vrf(
+        oracle_vrf_pubkey,
+        &args.input,
+        &args.output,
+        (
+            &args.commitment_base_compressed,
+            &args.commitment_hash_compressed,
+            &args.scalar,
+        ),
+    );
+    if !verified {
+        return Err(EphemeralVrfError::InvalidProof.into());
+    }
+    Ok(())
+}
+
+/// Verify the oracle signer and its queue, or a shared queue, returning the VRF public key of
+/// the oracle.
+pub(crate) fn verify_oracle(
+    oracle_info: &AccountInfo<'_>,
+    oracle_data_info: &AccountInfo<'_>,
+    oracle_queue_info: &AccountInfo<'_>,
+) -> Result<PodRistrettoPoint, ProgramError> {
     // Verify signer
     oracle_info.is_signer()?;
 
@@ -52,93 +123,328 @@ pub fn process_provide_randomness(accounts: &[AccountInfo<'_>], data: &[u8]) ->
     };
 
     // Read queue header for index/seeds validation from full account data
-    let queue_index = {
+    let (queue_index, shared) = {
         let data_ref = oracle_queue_info.try_borrow_data()?;
         let header = Queue::try_from_bytes(&data_ref)?;
-        header.index
+        (header.index, header.shared == 1)
     };
     oracle_queue_info
         .is_writable()?
-        .has_owner(&ephemeral_vrf_api::ID)?
-        .has_seeds(
+        .has_owner(&ephemeral_vrf_api::ID)?;
+    // Shared queues can be served by any registered oracle, others only by their own oracle
+    if shared {
+        oracle_queue_info.has_seeds(&[SHARED_QUEUE, &[queue_index]], &ephemeral_vrf_api::ID)?;
+    } else {
+        oracle_queue_info.has_seeds(
             &[QUEUE, oracle_info.key.to_bytes().as_ref(), &[queue_index]],
             &ephemeral_vrf_api::ID,
         )?;
+    }
+
+    Ok(oracle_vrf_pubkey)
+}
 
-    let output = &args.output;
-    let commitment_base_compressed = &args.commitment_base_compressed;
-    let commitment_hash_compressed = &args.commitment_hash_compressed;
-    let s = &args.scalar;
+/// Remove a request from a verified queue, the proof of its randomness must be verified
+/// by the caller.
+pub(crate) fn take_request(
+    oracle_info: &AccountInfo<'_>,
+    oracle_queue_info: &AccountInfo<'_>,
+    input: &[u8; 32],
+) -> Result<RemovedRequest, ProgramError> {
+    let mut data = oracle_queue_info.try_borrow_mut_data()?;
+    if data.len() < 8 {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let queue_data = &mut data[8..];
+    let mut queue_acc = QueueAccount::load(queue_data)?;
 
-    let removed_item_and_buf = {
-        let mut data = oracle_queue_info.try_borrow_mut_data()?;
-        if data.len() < 8 {
-            return Err(ProgramError::InvalidAccountData);
-        }
-        let queue_data = &mut data[8..];
-        let mut queue_acc = QueueAccount::load(queue_data)?;
-
-        let (index, _item) = {
-            let (index, item) = queue_acc
-                .find_item_by_id(&args.input)
-                .ok_or::<ProgramError>(EphemeralVrfError::RandomnessRequestNotFound.into())?;
-
-            // Check that the oracle signer is not in the vrf-macro accounts
-            let oracle_in_accounts = {
-                let metas = item.account_metas(queue_acc.acc);
-                metas
-                    .iter()
-                    .any(|acc| Pubkey::new_from_array(acc.pubkey).eq(oracle_info.key))
-            };
-            if oracle_in_accounts {
-                return Err(EphemeralVrfError::InvalidCallbackAccounts.into());
-            }
-
-            // Ensure that fulfillment happens in a different (later) slot than the request
-            if Clock::get()?.slot <= item.slot {
-                return Err(ProgramError::from(
-                    EphemeralVrfError::OracleMustProvideInDifferentSlot,
-                ));
-            }
-
-            (index, item)
+    let (index, _item) = {
+        let (index, item) = queue_acc
+            .find_item_by_id(input)
+            .ok_or::<ProgramError>(EphemeralVrfError::RandomnessRequestNotFound.into())?;
+
+        // Check that the oracle signer is not in the vrf-macro accounts
+        let oracle_in_accounts = {
+            let metas = item.account_metas(queue_acc.acc);
+            metas
+                .iter()
+                .any(|acc| Pubkey::new_from_array(acc.pubkey).eq(oracle_info.key))
         };
+        if oracle_in_accounts {
+            return Err(EphemeralVrfError::InvalidCallbackAccounts.into());
+        }
 
-        // Verify proof
-        let verified = verify_vrf(
-            &oracle_vrf_pubkey,
-            &args.input,
-            output,
-            (commitment_base_compressed, commitment_hash_compressed, s),
-        );
-        if !verified {
-            return Err(EphemeralVrfError::InvalidProof.into());
+        // Ensure that fulfillment happens in a different (later) slot than the request
+        let clock = Clock::get()?;
+        if clock.slot <= item.slot {
+            return Err(ProgramError::from(
+                EphemeralVrfError::OracleMustProvideInDifferentSlot,
+            ));
         }
 
-        // Remove the item from the queue (capture removed item for building callback)
-        let removed_item = queue_acc.remove_item(index)?;
-        let metas = removed_item.account_metas(queue_acc.acc).to_vec();
-        let disc = removed_item.callback_discriminator(queue_acc.acc).to_vec();
-        let args_bytes = removed_item.callback_args(queue_acc.acc).to_vec();
-        (removed_item, metas, disc, args_bytes)
+        // Scheduled requests can't be fulfilled early
+        if !item.is_due(clock.slot, clock.unix_timestamp) {
+            return Err(EphemeralVrfError::RequestNotDue.into());
+        }
+
+        (index, item)
     };
 
-    let (removed_item, metas_vec, disc_vec, args_vec) = removed_item_and_buf;
+    // Remove the item from the queue (capture removed item for building callback)
+    let removed_item = queue_acc.remove_item(index)?;
+    let metas = removed_item.account_metas(queue_acc.acc).to_vec();
+    let disc = removed_item.callback_discriminator(queue_acc.acc).to_vec();
+    let args_bytes = removed_item.callback_args(queue_acc.acc).to_vec();
+    let pda_metas = removed_item.pda_account_metas(queue_acc.acc)?;
+    Ok(RemovedRequest {
+        item: removed_item,
+        metas,
+        discriminator: disc,
+        args: args_bytes,
+        pda_metas,
+    })
+}
+
+/// Deliver the randomness of a removed request, to its callback or to its result PDA in pull
+/// mode, then pay the fees to the oracle.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn fulfill<'info>(
+    oracle_info: &AccountInfo<'info>,
+    program_identity_info: &AccountInfo<'info>,
+    oracle_queue_info: &AccountInfo<'info>,
+    callback_program_info: &AccountInfo<'info>,
+    remaining_accounts: &[AccountInfo<'info>],
+    args: &ProvideRandomness,
+    oracle_vrf_pubkey: &PodRistrettoPoint,
+    removed: RemovedRequest,
+) -> ProgramResult {
+    let RemovedRequest {
+        item: removed_item,
+        metas: metas_vec,
+        discriminator: disc_vec,
+        args: args_vec,
+        pda_metas,
+    } = removed;
+
+    let (remaining_accounts, receipt_info) = split_receipt(&removed_item, remaining_accounts)?;
+
+    // Token fee accounts are appended after the callback accounts
+    let (remaining_accounts, token_fee_accounts) = if removed_item.is_token_fee() {
+        let split = remaining_accounts
+            .len()
+            .checked_sub(TokenFeeAccounts::LEN)
+            .ok_or(ProgramError::NotEnoughAccountKeys)?;
+        remaining_accounts.split_at(split)
+    } else {
+        (remaining_accounts, &[][..])
+    };
 
-    // Invoke vrf-macro with randomness
     callback_program_info.has_address(&Pubkey::new_from_array(removed_item.callback_program_id))?;
+    let rdn = hash(&args.output.0);
+
+    if removed_item.pull == 1 {
+        // Write the randomness and its proof to the result PDA stored in place of the metas
+        let [randomness_result_info, ..] = remaining_accounts else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+        let result_key = metas_vec
+            .first()
+            .map(|meta| Pubkey::new_from_array(meta.pubkey))
+            .ok_or(ProgramError::InvalidAccountData)?;
+        randomness_result_info
+            .is_writable()?
+            .has_address(&result_key)?;
+        let mut result =
+            randomness_result_info.as_account_mut::<RandomnessResult>(&ephemeral_vrf_api::ID)?;
+        // A newer request reusing the same result PDA supersedes this one
+        if result.request_id == args.input {
+            write_result(&mut result, args, &rdn.to_bytes(), oracle_info.key)?;
+        }
+    } else {
+        // Header and proof of the callback ABI the request opted into
+        let callback_abi = CallbackAbi::try_from(removed_item.callback_abi)
+            .map_err(|_| ProgramError::InvalidAccountData)?;
+        let mut prefix = Vec::new();
+        if callback_abi != CallbackAbi::Randomness {
+            let header = FulfillmentHeader {
+                request_id: args.input,
+                request_slot: removed_item.slot,
+                fulfillment_slot: Clock::get()?.slot,
+                oracle: *oracle_info.key,
+                queue: *oracle_queue_info.key,
+                output: args.output.0,
+            };
+            prefix.extend_from_slice(header.as_bytes());
+        }
+        if callback_abi == CallbackAbi::Proof {
+            let proof = FulfillmentProof {
+                commitment_base_compressed: args.commitment_base_compressed,
+                commitment_hash_compressed: args.commitment_hash_compressed,
+                scalar: args.scalar,
+                vrf_pubkey: *oracle_vrf_pubkey,
+            };
+            prefix.extend_from_slice(proof.as_bytes());
+        }
+        invoke_callback(
+            &removed_item,
+            &prefix,
+            &metas_vec,
+            &pda_metas,
+            &disc_vec,
+            &args_vec,
+            &rdn.to_bytes(),
+            callback_program_info,
+            program_identity_info,
+            remaining_accounts,
+        )?;
+    }
+
+    collect_fees(
+        oracle_info,
+        oracle_queue_info,
+        &removed_item,
+        token_fee_accounts,
+    )?;
+
+    let slot = Clock::get()?.slot;
+    if let Some(receipt_info) = receipt_info {
+        let mut receipt = load_receipt(receipt_info, &args.input)?;
+        receipt.randomness = rdn.to_bytes();
+        receipt.complete(ReceiptStatus::Fulfilled, oracle_info.key, slot);
+    }
+
+    EphemeralVrfEvent::RequestFulfilled(RequestFulfilled {
+        queue: *oracle_queue_info.key,
+        request_id: args.input,
+        oracle: *oracle_info.key,
+        callback_program_id: Pubkey::new_from_array(removed_item.callback_program_id),
+        slot,
+        randomness: rdn.to_bytes(),
+        fallback: false,
+    })
+    .emit();
+
+    Ok(())
+}
+
+/// Split the receipt PDA of a request, the last of its accounts, if it was created with one.
+pub(crate) fn split_receipt<'a, 'info>(
+    item: &QueueItem,
+    accounts: &'a [AccountInfo<'info>],
+) -> Result<(&'a [AccountInfo<'info>], Option<&'a AccountInfo<'info>>), ProgramError> {
+    if item.receipt == 0 {
+        return Ok((accounts, None));
+    }
+    let (receipt_info, accounts) = accounts
+        .split_last()
+        .ok_or(ProgramError::NotEnoughAccountKeys)?;
+    Ok((accounts, Some(receipt_info)))
+}
+
+/// Load the receipt of a request for writing, checking that it tracks the request.
+pub(crate) fn load_receipt<'a>(
+    receipt_info: &'a AccountInfo<'_>,
+    request_id: &[u8; 32],
+) -> Result<RefMut<'a, Receipt>, ProgramError> {
+    let receipt = receipt_info
+        .is_writable()?
+        .as_account_mut::<Receipt>(&ephemeral_vrf_api::ID)?;
+    if &receipt.request_id != request_id {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(receipt)
+}
+
+/// Record the randomness and its proof in a result account.
+pub(crate) fn write_result(
+    result: &mut RandomnessResult,
+    args: &ProvideRandomness,
+    rdn: &[u8; 32],
+    oracle: &Pubkey,
+) -> ProgramResult {
+    result.randomness = *rdn;
+    result.output = args.output;
+    result.commitment_base_compressed = args.commitment_base_compressed;
+    result.commitment_hash_compressed = args.commitment_hash_compressed;
+    result.scalar = args.scalar;
+    result.oracle = *oracle;
+    result.slot = Clock::get()?.slot;
+    result.fulfilled = 1;
+    Ok(())
+}
+
+/// Pay the fees escrowed for a fulfilled request to the oracle.
+///
+/// On the default ephemeral queue they accrue in the queue and are claimed on the base layer
+/// once the queue is committed (see `ClaimQueueFees`).
+pub(crate) fn collect_fees(
+    oracle_info: &AccountInfo<'_>,
+    oracle_queue_info: &AccountInfo<'_>,
+    removed_item: &QueueItem,
+    token_fee_accounts: &[AccountInfo<'_>],
+) -> ProgramResult {
+    if oracle_queue_info.key.eq(&DEFAULT_EPHEMERAL_QUEUE) {
+        return Ok(());
+    }
+    if removed_item.is_token_fee() {
+        let (token_accounts, fee_config) =
+            TokenFeeAccounts::load(token_fee_accounts, oracle_queue_info.key)?;
+        token_accounts.pay(
+            oracle_queue_info.key,
+            oracle_info.key,
+            &fee_config,
+            removed_item.fee,
+        )?;
+    }
+    crate::fees::transfer_fee(oracle_queue_info, oracle_info, removed_item.lamports_due())
+}
+
+/// Invoke the vrf-macro with the randomness and signed identity
+#[allow(clippy::too_many_arguments)]
+fn invoke_callback<'info>(
+    removed_item: &QueueItem,
+    prefix: &[u8],
+    metas_vec: &[CompactAccountMeta],
+    pda_metas: &[PdaAccountMeta],
+    disc_vec: &[u8],
+    args_vec: &[u8],
+    rdn: &[u8; 32],
+    callback_program_info: &AccountInfo<'info>,
+    program_identity_info: &AccountInfo<'info>,
+    remaining_accounts: &[AccountInfo<'info>],
+) -> ProgramResult {
     let mut accounts_metas = vec![AccountMeta {
         pubkey: *program_identity_info.key,
         is_signer: true,
         is_writable: false,
     }];
-    accounts_metas.extend(metas_vec.iter().map(|acc| acc.to_account_meta()));
+    accounts_metas.extend(metas_vec.iter().map(|acc| acc.to_callback_account_meta()));
 
-    let mut callback_data = Vec::with_capacity(disc_vec.len() + output.0.len() + args_vec.len());
-    callback_data.extend_from_slice(&disc_vec);
-    let rdn = hash(&output.0);
-    callback_data.extend_from_slice(rdn.to_bytes().as_ref());
-    callback_data.extend_from_slice(&args_vec);
+    // Accounts of PDA metas follow the fixed metas and must match their derivation
+    for (i, pda_meta) in pda_metas.iter().enumerate() {
+        let meta = pda_meta.to_account_meta();
+        let account = remaining_accounts
+            .get(metas_vec.len() + i)
+            .ok_or(ProgramError::NotEnoughAccountKeys)?;
+        if account.key.ne(&meta.pubkey) {
+            return Err(EphemeralVrfError::InvalidCallbackAccounts.into());
+        }
+        accounts_metas.push(meta);
+    }
+
+    // The words are passed as a fixed-size array, a single word being the randomness itself
+    let words = random_words(rdn, removed_item.num_words);
+    let mut callback_data = Vec::with_capacity(
+        disc_vec.len() + prefix.len() + words.len() * rdn.len() + args_vec.len() + 1,
+    );
+    callback_data.extend_from_slice(disc_vec);
+    callback_data.extend_from_slice(prefix);
+    callback_data.extend(words.iter().flatten());
+    callback_data.extend_from_slice(args_vec);
+    // Tell callbacks of every ABI that the request was moved from the queue it was made on
+    if removed_item.reassigned_slot != 0 {
+        callback_data.push(REASSIGNED_CALLBACK_FLAG);
+    }
 
     let ix = Instruction {
         program_id: Pubkey::new_from_array(removed_item.callback_program_id),
@@ -149,21 +455,27 @@ pub fn process_provide_randomness(accounts: &[AccountInfo<'_>], data: &[u8]) ->
     all_accounts.extend(vec![program_identity_info.clone()]);
     all_accounts.extend_from_slice(remaining_accounts);
 
-    // Invoke the vrf-macro with randomness and signed identity
+    // Invoke the vrf-macro with randomness, signed identity and callback signer if requested
     let id = program_identity_pda();
     program_identity_info.has_address(&id.0)?;
-    let pda_signer_seeds: &[&[&[u8]]] = &[&[IDENTITY, &[id.1]]];
-    solana_program::program::invoke_signed(&ix, &all_accounts, pda_signer_seeds)?;
-
-    // Collect the fees (unless we are using the default ephemeral queue)
-    if oracle_queue_info.key.ne(&DEFAULT_EPHEMERAL_QUEUE) {
-        let cost = if removed_item.priority_request == 1 {
-            VRF_HIGH_PRIORITY_LAMPORTS_COST
-        } else {
-            VRF_LAMPORTS_COST
-        };
-        crate::fees::transfer_fee(oracle_queue_info, oracle_info, cost)?;
+    let identity_seeds: &[&[u8]] = &[IDENTITY, &[id.1]];
+    let callback_program_id = Pubkey::new_from_array(removed_item.callback_program_id);
+    let callback_signer_bump = if metas_vec.iter().any(|meta| meta.is_signer != 0) {
+        Some(callback_signer_pda(&callback_program_id, &removed_item.caller_seed).1)
+    } else {
+        None
+    };
+    let callback_signer_seeds = callback_signer_bump.as_ref().map(|bump| {
+        [
+            CALLBACK_SIGNER,
+            callback_program_id.as_ref(),
+            &removed_item.caller_seed,
+            core::slice::from_ref(bump),
+        ]
+    });
+    let mut pda_signer_seeds = vec![identity_seeds];
+    if let Some(seeds) = callback_signer_seeds.as_ref() {
+        pda_signer_seeds.push(seeds);
     }
-
-    Ok(())
+    solana_program::program::invoke_signed(&ix, &all_accounts, &pda_signer_seeds)
 }
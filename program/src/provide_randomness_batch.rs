@@ -0,0 +1,77 @@
+use crate::provide_randomness::{fulfill, take_request, verify_oracle};
+use ephemeral_vrf_api::prelude::*;
+use ephemeral_vrf_api::verify::verify_vrf_batch;
+
+/// Fulfill several requests of the same queue in one instruction, verifying all the proofs at
+/// once.
+///
+/// Accounts:
+///
+/// 0. `[signer]` signer - The oracle signer providing randomness
+/// 1. `[]` program_identity_info - Used to allow the vrf-macro programs to verify the identity of the oracle program
+/// 2. `[]` oracle_data_info - Oracle data account associated with the signer
+/// 3. `[writable]` oracle_queue_info - Queue storing randomness requests
+/// 4. `[varies]` requests - For each request, in the order of the instruction data: its
+///    callback program followed by `accounts_len` accounts, laid out as the remaining accounts
+///    of `ProvideRandomness`
+///
+/// Requirements:
+///
+/// - Signer must be a registered oracle with valid VRF keypair
+/// - All VRF proofs must be valid for their input and output
+/// - All requests must exist in the oracle queue
+/// - Each request has the same requirements as in `ProvideRandomness`
+///
+/// 1. Verify the oracle signer and load oracle data
+/// 2. Verify all the VRF proofs with a single batch verification
+/// 3. Remove each request from the queue and deliver its randomness
+pub fn process_provide_randomness_batch(
+    accounts: &[AccountInfo<'_>],
+    data: &[u8],
+) -> ProgramResult {
+    // Parse args
+    let items = ProvideRandomnessBatch::try_from_bytes(data)?;
+
+    // Load accounts
+    let ([oracle_info, program_identity_info, oracle_data_info, oracle_queue_info], mut requests) =
+        accounts.split_at(4)
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    let oracle_vrf_pubkey = verify_oracle(oracle_info, oracle_data_info, oracle_queue_info)?;
+
+    // Verify proofs
+    let proofs = items
+        .iter()
+        .map(|item| item.randomness)
+        .collect::<Vec<ProvideRandomness>>();
+    if !verify_vrf_batch(&oracle_vrf_pubkey, &proofs) {
+        return Err(EphemeralVrfError::InvalidProof.into());
+    }
+
+    for item in items {
+        let Some((callback_program_info, rest)) = requests.split_first() else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+        if rest.len() < item.accounts_len as usize {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+        let (remaining_accounts, rest) = rest.split_at(item.accounts_len as usize);
+        requests = rest;
+
+        let removed = take_request(oracle_info, oracle_queue_info, &item.randomness.input)?;
+        fulfill(
+            oracle_info,
+            program_identity_info,
+            oracle_queue_info,
+            callback_program_info,
+            remaining_accounts,
+            &item.randomness,
+            &oracle_vrf_pubkey,
+            removed,
+        )?;
+    }
+
+    Ok(())
+}
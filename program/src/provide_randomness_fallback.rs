@@ -0,0 +1,105 @@
+use crate::provide_randomness::{
+    collect_fees, load_receipt, split_receipt, take_verified_request, write_result,
+};
+use ephemeral_vrf_api::prelude::*;
+use solana_program::hash::hash;
+
+/// Fulfill a request without invoking its callback, recording the verified randomness in the
+/// requester's result PDA so that the consumer can settle later.
+///
+/// Used by oracles when the callback of a request opting into the fallback keeps failing, so
+/// that the request is consumed instead of being retried until it expires.
+///
+/// Accounts:
+///
+/// 0. `[signer]` signer - The oracle signer providing randomness
+/// 1. `[]` oracle_data_info - Oracle data account associated with the signer
+/// 2. `[writable]` oracle_queue_info - Queue storing randomness requests
+/// 3. `[writable]` randomness_result_info - Result PDA of the callback program and the request's
+///    caller seed (see `randomness_result_pda`), created by the request
+/// 4. `[varies]` token_fee_accounts - The token fee accounts (see `token_fee_accounts`) when the
+///    request was paid in tokens
+/// 5. `[writable]` receipt_info - The receipt PDA of the request if it was created with one
+///
+/// Requirements:
+///
+/// - Signer must be a registered oracle with valid VRF keypair
+/// - VRF proof must be valid for the given input and output
+/// - Request must exist in the oracle queue and have opted into the fallback
+///
+/// 1. Verify the oracle signer, the VRF proof and remove the request from the queue
+/// 2. Record the randomness in the result PDA, unless a newer request reset it
+/// 3. Pay the fees to the oracle
+pub fn process_provide_randomness_fallback(
+    accounts: &[AccountInfo<'_>],
+    data: &[u8],
+) -> ProgramResult {
+    // Parse args
+    let args = &ProvideRandomnessFallback::try_from_bytes(data)?.randomness;
+
+    // Load accounts
+    let (
+        [oracle_info, oracle_data_info, oracle_queue_info, randomness_result_info],
+        token_fee_accounts,
+    ) = accounts.split_at(4)
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    let removed_item =
+        take_verified_request(oracle_info, oracle_data_info, oracle_queue_info, args)?.item;
+    if removed_item.fallback == 0 {
+        return Err(EphemeralVrfError::FallbackNotRequested.into());
+    }
+    let (token_fee_accounts, receipt_info) = split_receipt(&removed_item, token_fee_accounts)?;
+
+    randomness_result_info.is_writable()?.has_seeds(
+        &[
+            RANDOMNESS_RESULT,
+            &removed_item.callback_program_id,
+            &removed_item.caller_seed,
+        ],
+        &ephemeral_vrf_api::ID,
+    )?;
+    {
+        let mut result =
+            randomness_result_info.as_account_mut::<RandomnessResult>(&ephemeral_vrf_api::ID)?;
+        // A newer request reusing the same result PDA supersedes this one
+        if result.request_id == args.input {
+            write_result(
+                &mut result,
+                args,
+                &hash(&args.output.0).to_bytes(),
+                oracle_info.key,
+            )?;
+        }
+    }
+
+    collect_fees(
+        oracle_info,
+        oracle_queue_info,
+        &removed_item,
+        token_fee_accounts,
+    )?;
+
+    let slot = Clock::get()?.slot;
+    let randomness = hash(&args.output.0).to_bytes();
+    if let Some(receipt_info) = receipt_info {
+        let mut receipt = load_receipt(receipt_info, &args.input)?;
+        receipt.randomness = randomness;
+        receipt.complete(ReceiptStatus::Fulfilled, oracle_info.key, slot);
+    }
+
+    EphemeralVrfEvent::RequestFulfilled(RequestFulfilled {
+        queue: *oracle_queue_info.key,
+        request_id: args.input,
+        oracle: *oracle_info.key,
+        callback_program_id: Pubkey::new_from_array(removed_item.callback_program_id),
+        slot,
+        randomness,
+        fallback: true,
+    })
+    .emit();
+
+    Ok(())
+}
@@ -1,34 +1,62 @@
+use crate::fees::TokenFeeAccounts;
 use ephemeral_vrf_api::prelude::*;
 use solana_program::msg;
 
 /// Remove all requests in the queue whose age (current_slot - item.slot)
-/// exceeds the TTL.
+/// exceeds the TTL. The age of scheduled requests counts from their schedule.
 ///
 /// Accounts:
-/// 0. `[]` oracle_info               – The oracle public key used in the queue PDA seeds
+/// 0. `[]` oracle_info               – The oracle public key used in the queue PDA seeds, a
+///    signer registered oracle for shared queues
 /// 1. `[writable]` oracle_queue_info – The oracle queue account (PDA)
+/// 2. `[]` oracle_data_info          – Only for shared queues: the oracle data of the signer
+/// 3. `[varies]` token_fee_accounts  – Required when expired requests were paid in tokens
+///    (see `token_fee_accounts`), the destination token account must be owned by the oracle
+/// 4. `[writable]` receipts          – Receipt PDAs of expired requests created with one, in any
+///    order. Receipts not passed stay pending until closed by their payer.
+/// 5. `[writable]` refund_accounts   – Token accounts of the payers of passed receipts, in any
+///    order among the receipts: the fee of an expired request paid in tokens is refunded to
+///    the token account of its receipt payer when passed, and paid to the oracle otherwise.
 ///
 /// Requirements:
 /// - No signer needed (permissionless), anyone can call.
 /// - oracle_queue_info must match seeds [QUEUE, oracle_info.key, [index]].
+/// - Shared queues must match seeds [SHARED_QUEUE, [index]], their expired fees are paid to the
+///   signer, which must be a registered oracle.
 pub fn process_purge_expired_requests(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
     let args = PurgeExpiredRequests::try_from_bytes(data)?;
 
     // Accounts
-    let [oracle_info, oracle_queue_info] = accounts else {
+    let ([oracle_info, oracle_queue_info], remaining_accounts) = accounts.split_at(2) else {
         return Err(ProgramError::NotEnoughAccountKeys);
     };
 
     // Validate queue PDA seeds and ownership / writability
     oracle_queue_info
         .is_writable()?
-        .has_owner(&ephemeral_vrf_api::ID)?
-        .has_seeds(
+        .has_owner(&ephemeral_vrf_api::ID)?;
+    let shared = Queue::try_from_bytes(&oracle_queue_info.try_borrow_data()?)?.shared == 1;
+    let remaining_accounts = if shared {
+        oracle_queue_info.has_seeds(&[SHARED_QUEUE, &[args.index]], &ephemeral_vrf_api::ID)?;
+        let Some((oracle_data_info, remaining_accounts)) = remaining_accounts.split_first() else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+        oracle_info.is_signer()?;
+        oracle_data_info.has_seeds(
+            &[ORACLE_DATA, oracle_info.key.to_bytes().as_ref()],
+            &ephemeral_vrf_api::ID,
+        )?;
+        oracle_data_info.as_account::<Oracle>(&ephemeral_vrf_api::ID)?;
+        remaining_accounts
+    } else {
+        oracle_queue_info.has_seeds(
             &[QUEUE, oracle_info.key.to_bytes().as_ref(), &[args.index]],
             &ephemeral_vrf_api::ID,
         )?;
+        remaining_accounts
+    };
 
-    let current_slot = Clock::get()?.slot;
+    let clock = Clock::get()?;
 
     // Borrow queue data and scan/remove expired items using QueueAccount view
     let mut acc_data = oracle_queue_info.try_borrow_mut_data()?;
@@ -37,6 +65,8 @@ pub fn process_purge_expired_requests(accounts: &[AccountInfo<'_>], data: &[u8])
 
     // Scan and remove expired items by logical index
     let mut total_cost: u64 = 0;
+    let mut total_token_cost: u64 = 0;
+    let mut token_refunds: Vec<(Pubkey, u64)> = Vec::new();
     let mut i: usize = 0;
     msg!("Items in the queue: {}", queue_acc.len());
     while i < queue_acc.len() {
@@ -44,15 +74,28 @@ pub fn process_purge_expired_requests(accounts: &[AccountInfo<'_>], data: &[u8])
         let item = queue_acc
             .get_item_by_index(i)
             .ok_or(ProgramError::InvalidAccountData)?;
-        let age = current_slot.saturating_sub(item.slot);
-        if age > QUEUE_TTL_SLOTS {
-            let cost = if item.priority_request == 1 {
-                VRF_HIGH_PRIORITY_LAMPORTS_COST
-            } else {
-                VRF_LAMPORTS_COST
-            };
-            total_cost = total_cost.saturating_add(cost);
+        if item.is_expired(clock.slot, clock.unix_timestamp) {
+            total_cost = total_cost.saturating_add(item.lamports_due());
+            let request_id = item.id;
+            let payer = (item.receipt == 1)
+                .then(|| {
+                    expire_receipt(remaining_accounts, &request_id, oracle_info.key, clock.slot)
+                })
+                .flatten();
+            if item.is_token_fee() {
+                match payer {
+                    Some(payer) => token_refunds.push((payer, item.fee)),
+                    None => total_token_cost = total_token_cost.saturating_add(item.fee),
+                }
+            }
             let _ = queue_acc.remove_item(i)?;
+            EphemeralVrfEvent::RequestPurged(RequestPurged {
+                queue: *oracle_queue_info.key,
+                request_id,
+                oracle: *oracle_info.key,
+                slot: clock.slot,
+            })
+            .emit();
             msg!(
                 "Removing item {} from queue, new size {}",
                 i,
@@ -64,13 +107,77 @@ pub fn process_purge_expired_requests(accounts: &[AccountInfo<'_>], data: &[u8])
         }
     }
 
+    drop(acc_data);
+
     // Send the fees to the oracle.
     // The oracle also accrue fees on malformed/expired requests to
     // 1) incentivize queue cleaning and
     // 2) disincentivize creation of malformed requests
+    // Token fees are refunded instead when the receipt of the request and the token account of
+    // its payer are passed.
+    // Fees of the default ephemeral queue stay in the queue until claimed on the base layer.
     if total_cost > 0 && oracle_queue_info.key.ne(&DEFAULT_EPHEMERAL_QUEUE) {
         crate::fees::transfer_fee(oracle_queue_info, oracle_info, total_cost)?;
     }
+    let has_token_fees = total_token_cost > 0 || !token_refunds.is_empty();
+    if has_token_fees && oracle_queue_info.key.ne(&DEFAULT_EPHEMERAL_QUEUE) {
+        let token_fee_accounts = remaining_accounts
+            .get(..TokenFeeAccounts::LEN)
+            .unwrap_or(remaining_accounts);
+        let (token_accounts, fee_config) =
+            TokenFeeAccounts::load(token_fee_accounts, oracle_queue_info.key)?;
+        // Refund the payers whose token account is passed, the oracle is paid the other fees
+        for (payer, fee) in token_refunds {
+            let refund_account =
+                remaining_accounts
+                    .iter()
+                    .skip(TokenFeeAccounts::LEN)
+                    .find(|account| {
+                        account.is_writable && token_accounts.is_token_account_of(account, &payer)
+                    });
+            match refund_account {
+                Some(refund_account) => token_accounts.pay_to(
+                    refund_account,
+                    oracle_queue_info.key,
+                    &payer,
+                    &fee_config,
+                    fee,
+                )?,
+                None => total_token_cost = total_token_cost.saturating_add(fee),
+            }
+        }
+        if total_token_cost > 0 {
+            token_accounts.pay(
+                oracle_queue_info.key,
+                oracle_info.key,
+                &fee_config,
+                total_token_cost,
+            )?;
+        }
+    }
 
     Ok(())
 }
+
+/// Mark the receipt of an expired request as expired, if passed among `accounts`, returning its
+/// payer.
+fn expire_receipt(
+    accounts: &[AccountInfo<'_>],
+    request_id: &[u8; 32],
+    oracle: &Pubkey,
+    slot: u64,
+) -> Option<Pubkey> {
+    for account in accounts {
+        if account.owner != &ephemeral_vrf_api::ID || !account.is_writable {
+            continue;
+        }
+        let Ok(mut receipt) = account.as_account_mut::<Receipt>(&ephemeral_vrf_api::ID) else {
+            continue;
+        };
+        if &receipt.request_id == request_id {
+            receipt.complete(ReceiptStatus::Expired, oracle, slot);
+            return Some(receipt.payer);
+        }
+    }
+    None
+}
@@ -0,0 +1,187 @@
+use crate::fees::TokenFeeAccounts;
+use crate::provide_randomness::{load_receipt, split_receipt};
+use ephemeral_vrf_api::prelude::*;
+
+/// Process the reassignment of a pending request to the queue of another oracle
+///
+/// Permissionless, lets anyone move a request that its oracle left pending for the share of its
+/// TTL set in the admin account to the queue of any other registered oracle, chosen by the
+/// caller. The request keeps its id, its randomness is then computed with the
+/// key of the new oracle, its `FulfillmentHeader` names the new queue and its callback data is
+/// followed by `REASSIGNED_CALLBACK_FLAG` in every `CallbackAbi`. A reassigned request waits as
+/// long again before it can be moved further.
+///
+/// Accounts:
+///
+/// 0. `[]` source_oracle_info - The oracle owning the source queue
+/// 1. `[writable]` source_queue_info - The queue holding the request
+/// 2. `[]` target_oracle_info - The oracle owning the target queue
+/// 3. `[]` target_oracle_data_info - Oracle data account of the target oracle
+/// 4. `[writable]` target_queue_info - The queue receiving the request
+/// 5. `[]` oracles_info - The oracles account ([ORACLES])
+/// 6. `[]` admin_info - The admin account ([ADMIN]), holding the reassign delay
+/// 7. `[varies]` token_fee_accounts - When the request was paid in tokens, the token fee
+///    accounts of the source queue (see `token_fee_accounts`) with the escrow of the target
+///    queue as token account, followed by the token fee config of the target queue
+/// 8. `[writable]` receipt_info - The receipt PDA of the request if it was created with one
+///
+/// Requirements:
+///
+/// - Both queues must be oracle queues of different oracles, shared queues and the default
+///   ephemeral queue can't be reassigned from or to
+/// - The target oracle must be registered in the oracles account
+/// - The request must be due, not expired, and pending for the reassign delay of its TTL since
+///   it was due and since its latest reassignment
+/// - The target queue must not be paused
+/// - Requests paid in tokens can only move to a queue accepting the same mint
+///
+/// 1. Move the request from the source queue to the target queue, keeping its id and slot and
+///    recording the slot of the reassignment
+/// 2. Move its escrowed fee and tip to the target queue
+/// 3. Point its receipt to the target queue
+pub fn process_reassign_request(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    // Parse args
+    let args = ReassignRequest::try_from_bytes(data)?;
+
+    // Load accounts
+    let (
+        [source_oracle_info, source_queue_info, target_oracle_info, target_oracle_data_info, target_queue_info, oracles_info, admin_info],
+        remaining_accounts,
+    ) = accounts.split_at(7)
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if source_oracle_info.key.eq(target_oracle_info.key)
+        || source_queue_info.key.eq(&DEFAULT_EPHEMERAL_QUEUE)
+        || target_queue_info.key.eq(&DEFAULT_EPHEMERAL_QUEUE)
+    {
+        return Err(EphemeralVrfError::RequestNotReassignable.into());
+    }
+    target_oracle_data_info.has_seeds(
+        &[ORACLE_DATA, target_oracle_info.key.to_bytes().as_ref()],
+        &ephemeral_vrf_api::ID,
+    )?;
+    target_oracle_data_info.as_account::<Oracle>(&ephemeral_vrf_api::ID)?;
+    check_oracle_queue(source_oracle_info, source_queue_info)?;
+    check_oracle_queue(target_oracle_info, target_queue_info)?;
+
+    oracles_info.has_seeds(&[ORACLES], &ephemeral_vrf_api::ID)?;
+    let target_registered = {
+        let oracles_data = oracles_info.try_borrow_data()?;
+        Oracles::try_from_bytes_with_discriminator(&oracles_data)?
+            .oracles
+            .contains(target_oracle_info.key)
+    };
+    if !target_registered {
+        return Err(EphemeralVrfError::RequestNotReassignable.into());
+    }
+    admin_info.has_seeds(&[ADMIN], &ephemeral_vrf_api::ID)?;
+    let after_ttl_bps = admin_info
+        .as_account::<Admin>(&ephemeral_vrf_api::ID)?
+        .reassign_after_ttl_bps;
+
+    // Take the request from the source queue
+    let clock = Clock::get()?;
+    let (mut item, discriminator, metas, callback_args, pda_metas) = {
+        let mut data = source_queue_info.try_borrow_mut_data()?;
+        let mut queue_acc = QueueAccount::load(&mut data[8..])?;
+        let (index, item) = queue_acc
+            .find_item_by_id(&args.request_id)
+            .ok_or::<ProgramError>(EphemeralVrfError::RandomnessRequestNotFound.into())?;
+        if !item.is_reassignable(clock.slot, clock.unix_timestamp, after_ttl_bps) {
+            return Err(EphemeralVrfError::RequestNotReassignable.into());
+        }
+        let item = queue_acc.remove_item(index)?;
+        (
+            item,
+            item.callback_discriminator(queue_acc.acc).to_vec(),
+            item.account_metas(queue_acc.acc).to_vec(),
+            item.callback_args(queue_acc.acc).to_vec(),
+            item.pda_account_metas(queue_acc.acc)?,
+        )
+    };
+
+    // Append it to the target queue under the same id
+    item.reassigned_slot = clock.slot;
+    {
+        let mut data = target_queue_info.try_borrow_mut_data()?;
+        let mut queue_acc = QueueAccount::load(&mut data[8..])?;
+        if queue_acc.header.paused == 1 {
+            return Err(EphemeralVrfError::QueuePaused.into());
+        }
+        if queue_acc.find_item_by_id(&item.id).is_some() {
+            return Err(EphemeralVrfError::RequestNotReassignable.into());
+        }
+        queue_acc.add_item(&item, &discriminator, &metas, &callback_args, &pda_metas)?;
+    }
+
+    // Move the escrowed fees
+    let (token_fee_accounts, receipt_info) = split_receipt(&item, remaining_accounts)?;
+    if item.is_token_fee() {
+        let Some((target_config_info, token_fee_accounts)) = token_fee_accounts.split_last() else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+        let (token_accounts, fee_config) =
+            TokenFeeAccounts::load(token_fee_accounts, source_queue_info.key)?;
+        target_config_info.has_seeds(
+            &[TOKEN_FEE_CONFIG, target_queue_info.key.as_ref()],
+            &ephemeral_vrf_api::ID,
+        )?;
+        {
+            let target_config =
+                target_config_info.as_account::<TokenFeeConfig>(&ephemeral_vrf_api::ID)?;
+            if target_config.mint.ne(&fee_config.mint)
+                || target_config.escrow.ne(token_accounts.token_account.key)
+            {
+                return Err(EphemeralVrfError::RequestNotReassignable.into());
+            }
+        }
+        // The escrow of the target queue is owned by its config
+        token_accounts.pay(
+            source_queue_info.key,
+            target_config_info.key,
+            &fee_config,
+            item.fee,
+        )?;
+    }
+    crate::fees::transfer_fee(source_queue_info, target_queue_info, item.lamports_due())?;
+
+    if let Some(receipt_info) = receipt_info {
+        let mut receipt = load_receipt(receipt_info, &item.id)?;
+        receipt.queue = *target_queue_info.key;
+    }
+
+    EphemeralVrfEvent::RequestReassigned(RequestReassigned {
+        request_id: item.id,
+        from_queue: *source_queue_info.key,
+        to_queue: *target_queue_info.key,
+        slot: clock.slot,
+    })
+    .emit();
+
+    Ok(())
+}
+
+/// Check that a queue is a writable, non-shared oracle queue of the given oracle.
+fn check_oracle_queue(
+    oracle_info: &AccountInfo<'_>,
+    queue_info: &AccountInfo<'_>,
+) -> ProgramResult {
+    queue_info
+        .is_writable()?
+        .has_owner(&ephemeral_vrf_api::ID)?;
+    let (index, shared) = {
+        let data = queue_info.try_borrow_data()?;
+        let header = Queue::try_from_bytes(&data)?;
+        (header.index, header.shared == 1)
+    };
+    if shared {
+        return Err(EphemeralVrfError::RequestNotReassignable.into());
+    }
+    queue_info.has_seeds(
+        &[QUEUE, oracle_info.key.to_bytes().as_ref(), &[index]],
+        &ephemeral_vrf_api::ID,
+    )?;
+    Ok(())
+}
@@ -1,7 +1,8 @@
+use crate::fees::TokenFeeAccounts;
 use ephemeral_vrf_api::prelude::*;
 use solana_program::hash::hashv;
 use solana_program::msg;
-use solana_program::program::invoke;
+use solana_program::program::{invoke, set_return_data};
 use solana_program::sysvar::slot_hashes;
 use solana_system_interface::instruction as system_instruction;
 
@@ -9,17 +10,44 @@ use solana_system_interface::instruction as system_instruction;
 ///
 /// Accounts:
 ///
-/// 0. `[signer]` signer - The account requesting randomness and paying for the transaction
-/// 1. `[signer]` program_identity_info - The identity PDA of the calling program
+/// 0. `[signer]` signer - The account requesting randomness and paying for the transaction,
+///    unless the request is sponsored
+/// 1. `[signer]` program_identity_info - The identity PDA of the calling program, not checked
+///    for pull-mode requests made directly by the requester (`callback_program_id` is the signer)
 /// 2. `[]` oracle_queue_info - The oracle queue account that will store the randomness request
 /// 3. `[]` system_program_info - The system program
 /// 4. `[]` slothashes_account_info - The SlotHashes sysvar account
+/// 5. `[varies]` token_fee_accounts - When paying in tokens: the queue's token fee config,
+///    the fee escrow, the payer's token account, the fee mint and the token program.
+///    On the default ephemeral queue: the signer's delegated ephemeral balance.
+///    For sponsored requests: the sponsor and the signer's sponsor usage counter
+/// 6. `[writable]` randomness_result_info - Last account of pull-mode requests and of requests
+///    opting into the fallback: the result PDA of the requester (see `randomness_result_pda`),
+///    created by the signer or reset by the request
+/// 7. `[writable]` receipt_info - Last account of requests asking for a receipt, after the
+///    result PDA: the receipt PDA of the request (see `receipt_pda`), created by the request
 ///
 /// Requirements:
 ///
 /// - The signer must be a valid signer
 /// - The program identity must be a valid signer and derived from the vrf-macro program ID
-/// - The oracle queue must be properly initialized
+/// - The oracle queue must be properly initialized and not paused
+/// - The optional tip is always paid in lamports and escrowed in the queue with the fee
+/// - Requests on the default ephemeral queue must be paid from an ephemeral balance, except
+///   those of clients predating `fee_payment`, accepted for free until
+///   `LEGACY_EPHEMERAL_REQUESTS_DEADLINE`
+/// - Pull-mode requests must not specify callback accounts, the result PDA is stored instead
+/// - The result PDA must not be in use, the previous request with the same seeds must be
+///   fulfilled or expired
+/// - The receipt PDA must not be in use, the receipt of a previous request with the same seeds
+///   must be closed first
+/// - Callback PDA metas must have derivable seeds, at most `MAX_CALLBACK_ACCOUNTS` callback
+///   accounts in total, or `MAX_CALLBACK_ACCOUNTS_WITH_LOOKUP_TABLE` when naming a lookup table
+/// - At most `MAX_RANDOM_WORDS` random words can be requested
+/// - Scheduled requests must be due within `MAX_REQUEST_DELAY_SLOTS` or
+///   `MAX_REQUEST_DELAY_SECONDS`
+/// - The only callback account that can be a signer is the callback signer PDA of the request
+///   (see `callback_signer_pda`), signed for by the VRF program when invoking the callback
 /// - The request is stored in the oracle queue with a combined hash derived from:
 ///   - caller_seed
 ///   - current slot
@@ -34,6 +62,8 @@ use solana_system_interface::instruction as system_instruction;
 /// 5. Insert the request into the oracle queue
 /// 6. Resize the oracle queue PDA if needed
 /// 7. Update the oracle queue data
+/// 8. Emit a `RequestCreated` event
+/// 9. Return the request id and the queue (`request_id || queue`) as return data
 pub fn process_request_randomness(
     accounts: &[AccountInfo<'_>],
     data: &[u8],
@@ -42,8 +72,10 @@ pub fn process_request_randomness(
     let args = RequestRandomness::try_from_bytes(data)?;
 
     // Load accounts
-    let [signer_info, program_identity_info, oracle_queue_info, system_program_info, slothashes_account_info] =
-        accounts
+    let (
+        [signer_info, program_identity_info, oracle_queue_info, system_program_info, slothashes_account_info],
+        remaining_accounts,
+    ) = accounts.split_at(5)
     else {
         return Err(ProgramError::NotEnoughAccountKeys);
     };
@@ -51,10 +83,58 @@ pub fn process_request_randomness(
     // Verify signer
     signer_info.is_signer()?;
 
-    // Verify caller program
-    program_identity_info
-        .has_seeds(&[IDENTITY], &args.callback_program_id)?
-        .is_signer()?;
+    // Verify caller program. Pull-mode requests can also be made by the requester itself.
+    if !args.pull || args.callback_program_id.ne(signer_info.key) {
+        program_identity_info
+            .has_seeds(&[IDENTITY], &args.callback_program_id)?
+            .is_signer()?;
+    }
+
+    // The receipt PDA, if asked for, follows all the other accounts
+    let (remaining_accounts, receipt_info) = if args.receipt {
+        let Some((receipt_info, remaining_accounts)) = remaining_accounts.split_last() else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+        receipt_info.is_writable()?.has_seeds(
+            &[
+                RECEIPT,
+                args.callback_program_id.as_ref(),
+                &args.caller_seed,
+            ],
+            &ephemeral_vrf_api::ID,
+        )?;
+        if receipt_info.owner == &ephemeral_vrf_api::ID {
+            return Err(EphemeralVrfError::ReceiptInUse.into());
+        }
+        (remaining_accounts, Some(receipt_info))
+    } else {
+        (remaining_accounts, None)
+    };
+
+    // Pull-mode requests store the result PDA in place of the callback accounts, requests
+    // opting into the fallback create it for the oracle to write to
+    let (remaining_accounts, randomness_result_info) = if args.pull || args.fallback {
+        let Some((randomness_result_info, remaining_accounts)) = remaining_accounts.split_last()
+        else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+        if args.pull
+            && (!args.callback_accounts_metas.is_empty() || !args.callback_pda_metas.is_empty())
+        {
+            return Err(EphemeralVrfError::InvalidCallbackAccounts.into());
+        }
+        randomness_result_info.is_writable()?.has_seeds(
+            &[
+                RANDOMNESS_RESULT,
+                args.callback_program_id.as_ref(),
+                &args.caller_seed,
+            ],
+            &ephemeral_vrf_api::ID,
+        )?;
+        (remaining_accounts, Some(randomness_result_info))
+    } else {
+        (remaining_accounts, None)
+    };
 
     // Load slot and slothash
     slothashes_account_info.is_sysvar(&slot_hashes::id())?;
@@ -64,7 +144,39 @@ pub fn process_request_randomness(
     let slot = Clock::get()?.slot;
     let time = Clock::get()?.unix_timestamp;
 
-    {
+    // Resolve the fee. The default ephemeral queue only accepts payments from a delegated
+    // ephemeral balance, other queues only accept lamports and tokens. Until the deadline,
+    // clients predating the fee payment are still served for free on the ephemeral queue.
+    let is_ephemeral = oracle_queue_info.key.eq(&DEFAULT_EPHEMERAL_QUEUE);
+    let is_legacy_ephemeral = is_ephemeral
+        && time < LEGACY_EPHEMERAL_REQUESTS_DEADLINE
+        && RequestRandomness::is_legacy(data);
+    if is_legacy_ephemeral {
+        msg!("Deprecated: pay requests on the ephemeral queue with an ephemeral balance");
+    } else if is_ephemeral != (args.fee_payment == FeePayment::EphemeralBalance) {
+        return Err(EphemeralVrfError::InvalidFeePayment.into());
+    }
+    let token_fee = match args.fee_payment {
+        FeePayment::Token => {
+            let (token_accounts, fee_config) =
+                TokenFeeAccounts::load(remaining_accounts, oracle_queue_info.key)?;
+            if fee_config.price == 0 {
+                return Err(EphemeralVrfError::TokenFeeNotConfigured.into());
+            }
+            Some((token_accounts, fee_config))
+        }
+        _ => None,
+    };
+    let metas_len = args.callback_accounts_metas.len() + args.callback_pda_metas.len();
+    let data_len = request_data_len(&args.callback_args, &args.callback_pda_metas);
+    let fee = match &token_fee {
+        Some((_, fee_config)) => request_fee_tokens(fee_config.price, metas_len, data_len),
+        None if is_legacy_ephemeral => 0,
+        None => request_fee_lamports(high_priority, metas_len, data_len),
+    };
+    let tip = args.tip;
+
+    let request_id = {
         // Borrow queue account data and load QueueAccount view
         let mut data = oracle_queue_info.try_borrow_mut_data()?;
         if data.len() < 8 {
@@ -73,6 +185,9 @@ pub fn process_request_randomness(
         // Skip 8-byte discriminator
         let queue_data = &mut data[8..];
         let mut queue_acc = QueueAccount::load(queue_data)?;
+        if queue_acc.header.paused == 1 {
+            return Err(EphemeralVrfError::QueuePaused.into());
+        }
 
         // Compute a combined hash that includes a logical insertion index hint
         let idx = queue_acc.len() as u32;
@@ -89,16 +204,34 @@ pub fn process_request_randomness(
         // Log to simplify gathering all the information needed to recreate the combined_hash.
         msg!("Idx: {}", idx);
 
-        // Optionally validate discriminator length to 8 bytes max (borsh Vec allows larger, but callbacks typically use 8)
-        if args.callback_discriminator.len() > 8 {
-            return Err(ProgramError::from(EphemeralVrfError::ArgumentSizeTooLarge));
+        validate_callback(
+            &args.callback_program_id,
+            &args.caller_seed,
+            &args.callback_discriminator,
+            &args.callback_accounts_metas,
+            &args.callback_pda_metas,
+            args.lookup_table.is_some(),
+            args.num_words,
+        )?;
+
+        // Scheduled requests stay in the queue until due, bound how long
+        if args.not_before_slot > slot.saturating_add(MAX_REQUEST_DELAY_SLOTS)
+            || args.not_before_timestamp > time.saturating_add(MAX_REQUEST_DELAY_SECONDS)
+        {
+            return Err(EphemeralVrfError::InvalidSchedule.into());
         }
 
         // Build the base item; variable-length parts are appended by add_item()
         let base_item = QueueItem {
             slot,
             id: combined_hash.to_bytes(),
+            caller_seed: args.caller_seed,
             callback_program_id: args.callback_program_id.to_bytes(),
+            lookup_table: args.lookup_table.unwrap_or_default().to_bytes(),
+            fee,
+            tip,
+            not_before_slot: args.not_before_slot,
+            not_before_timestamp: args.not_before_timestamp,
             callback_discriminator_offset: 0,
             metas_offset: 0,
             args_offset: 0,
@@ -107,32 +240,140 @@ pub fn process_request_randomness(
             args_len: 0,
             priority_request: high_priority as u8,
             used: 0,
-            _padding: [0u8; 4],
+            fee_payment: args.fee_payment.into(),
+            pull: args.pull as u8,
+            pda_metas_len: 0,
+            pda_metas_offset: 0,
+            num_words: args.num_words,
+            callback_abi: args.callback_abi.into(),
+            receipt: args.receipt as u8,
+            fallback: args.fallback as u8,
+            reassigned_slot: 0,
+            _reserved: [0u8; 16],
         };
 
         // Append the item to the queue (writes discriminator, metas, args into the variable region)
-        let metas = args
-            .callback_accounts_metas
-            .iter()
-            .map(|ca| (*ca).into())
-            .collect::<Vec<CompactAccountMeta>>();
+        let metas = match randomness_result_info.filter(|_| args.pull) {
+            Some(randomness_result_info) => vec![CompactAccountMeta {
+                pubkey: randomness_result_info.key.to_bytes(),
+                is_writable: 1,
+                is_signer: 0,
+            }],
+            None => args
+                .callback_accounts_metas
+                .iter()
+                .map(|ca| (*ca).into())
+                .collect::<Vec<CompactAccountMeta>>(),
+        };
         let _logical_index = queue_acc.add_item(
             &base_item,
             &args.callback_discriminator,
             &metas,
             &args.callback_args,
+            &args.callback_pda_metas,
+        )?;
+        combined_hash.to_bytes()
+    };
+
+    // Create the result PDA on first use and reset it for the new request. Its rent is returned
+    // to the signer creating it when closed (see `CloseRandomnessResult`).
+    if let Some(randomness_result_info) = randomness_result_info {
+        if randomness_result_info.owner != &ephemeral_vrf_api::ID {
+            create_program_account::<RandomnessResult>(
+                randomness_result_info,
+                system_program_info,
+                signer_info,
+                &ephemeral_vrf_api::ID,
+                &[
+                    RANDOMNESS_RESULT,
+                    args.callback_program_id.as_ref(),
+                    &args.caller_seed,
+                ],
+            )?;
+        }
+        let mut result =
+            randomness_result_info.as_account_mut::<RandomnessResult>(&ephemeral_vrf_api::ID)?;
+        // The result of a pending request would be overwritten by the new one
+        if result.is_pending(slot, time) {
+            return Err(EphemeralVrfError::ResultInUse.into());
+        }
+        let payer = match result.payer {
+            payer if payer == Pubkey::default() => *signer_info.key,
+            payer => payer,
+        };
+        *result = RandomnessResult::zeroed();
+        result.payer = payer;
+        result.requester = args.callback_program_id;
+        result.caller_seed = args.caller_seed;
+        result.request_id = request_id;
+        result.request_slot = slot;
+        result.not_before_slot = args.not_before_slot;
+        result.not_before_timestamp = args.not_before_timestamp;
+    }
+
+    // Create the receipt of the request, paid by the signer
+    if let Some(receipt_info) = receipt_info {
+        create_program_account::<Receipt>(
+            receipt_info,
+            system_program_info,
+            signer_info,
+            &ephemeral_vrf_api::ID,
+            &[
+                RECEIPT,
+                args.callback_program_id.as_ref(),
+                &args.caller_seed,
+            ],
         )?;
+        let mut receipt = receipt_info.as_account_mut::<Receipt>(&ephemeral_vrf_api::ID)?;
+        receipt.payer = *signer_info.key;
+        receipt.queue = *oracle_queue_info.key;
+        receipt.request_id = request_id;
+        receipt.callback_program_id = args.callback_program_id;
+        receipt.caller_seed = args.caller_seed;
+        receipt.request_slot = slot;
+        receipt.status = ReceiptStatus::Pending.into();
     }
 
-    // Transfer request cost to the queue PDA (unless we are using the default ephemeral queue)
-    if oracle_queue_info.key.ne(&DEFAULT_EPHEMERAL_QUEUE) {
-        let cost = if high_priority {
-            VRF_HIGH_PRIORITY_LAMPORTS_COST
-        } else {
-            VRF_LAMPORTS_COST
+    // Escrow the request cost: tokens go to the queue's fee escrow, lamports and tip to the queue PDA.
+    // On the default ephemeral queue, lamports are debited from the payer's ephemeral balance,
+    // sponsored requests are paid by the sponsor.
+    let lamports = match token_fee {
+        Some((token_accounts, fee_config)) => {
+            token_accounts.collect(signer_info, &fee_config, fee)?;
+            tip
+        }
+        None => fee
+            .checked_add(tip)
+            .ok_or(ProgramError::ArithmeticOverflow)?,
+    };
+    if is_legacy_ephemeral {
+        // Nothing to pay, the fee of legacy requests is zero and they cannot tip
+    } else if is_ephemeral {
+        let [ephemeral_balance_info, ..] = remaining_accounts else {
+            return Err(ProgramError::NotEnoughAccountKeys);
         };
+        crate::fees::debit_ephemeral_balance(
+            ephemeral_balance_info,
+            signer_info.key,
+            oracle_queue_info,
+            lamports,
+        )?;
+    } else if args.fee_payment == FeePayment::Sponsor {
+        let [sponsor_info, sponsor_usage_info, ..] = remaining_accounts else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+        crate::fees::debit_sponsor(
+            sponsor_info,
+            sponsor_usage_info,
+            signer_info.key,
+            &args.callback_program_id,
+            oracle_queue_info,
+            system_program_info,
+            lamports,
+        )?;
+    } else if lamports > 0 {
         invoke(
-            &system_instruction::transfer(signer_info.key, oracle_queue_info.key, cost),
+            &system_instruction::transfer(signer_info.key, oracle_queue_info.key, lamports),
             &[
                 signer_info.clone(),
                 oracle_queue_info.clone(),
@@ -141,5 +382,64 @@ pub fn process_request_randomness(
         )?;
     }
 
+    EphemeralVrfEvent::RequestCreated(RequestCreated {
+        queue: *oracle_queue_info.key,
+        request_id,
+        callback_program_id: args.callback_program_id,
+        caller_seed: args.caller_seed,
+        slot,
+        fee,
+        tip,
+        fee_payment: args.fee_payment,
+        high_priority,
+        pull: args.pull,
+        num_words: args.num_words,
+        callback_abi: args.callback_abi,
+        not_before_slot: args.not_before_slot,
+        not_before_timestamp: args.not_before_timestamp,
+        subscription: None,
+    })
+    .emit();
+
+    // Set last, as invoking another program clears the return data
+    set_return_data(&[request_id.as_slice(), oracle_queue_info.key.as_ref()].concat());
+
+    Ok(())
+}
+
+/// Check the callback of a request or subscription before storing it.
+pub(crate) fn validate_callback(
+    callback_program_id: &Pubkey,
+    caller_seed: &[u8; 32],
+    callback_discriminator: &[u8],
+    callback_accounts_metas: &[SerializableAccountMeta],
+    callback_pda_metas: &[PdaAccountMeta],
+    has_lookup_table: bool,
+    num_words: u8,
+) -> ProgramResult {
+    // Only the callback signer PDA of the request can be signed for by the VRF program
+    if callback_accounts_metas.iter().any(|meta| meta.is_signer) {
+        let callback_signer = callback_signer_pda(callback_program_id, caller_seed).0;
+        if callback_accounts_metas
+            .iter()
+            .any(|meta| meta.is_signer && meta.pubkey != callback_signer.to_bytes())
+        {
+            return Err(EphemeralVrfError::InvalidCallbackAccounts.into());
+        }
+    }
+
+    // PDA metas are resolved at fulfillment, their seeds must be derivable
+    if callback_accounts_metas.len() + callback_pda_metas.len()
+        > QueueItem::max_callback_accounts(has_lookup_table)
+        || !callback_pda_metas.iter().all(PdaAccountMeta::is_valid)
+    {
+        return Err(EphemeralVrfError::InvalidCallbackAccounts.into());
+    }
+
+    // Optionally validate discriminator length to 8 bytes max (borsh Vec allows larger, but callbacks typically use 8)
+    if callback_discriminator.len() > 8 || num_words > MAX_RANDOM_WORDS {
+        return Err(ProgramError::from(EphemeralVrfError::ArgumentSizeTooLarge));
+    }
+
     Ok(())
 }
@@ -0,0 +1,38 @@
+use ephemeral_vrf_api::prelude::EphemeralVrfError::Unauthorized;
+use ephemeral_vrf_api::prelude::*;
+
+/// Process the assignment of an admin role
+///
+/// Accounts:
+///
+/// 0. `[signer]` authority_info - The admin authority
+/// 1. `[writable]` admin_info - The admin account ([ADMIN])
+///
+/// Requirements:
+///
+/// - The signer must be the admin authority
+/// - The role must be a valid `AdminRole`
+///
+/// 1. Assign the role to the member, the default pubkey returns it to the authority
+pub fn process_set_admin_role(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    // Parse args
+    let args = SetAdminRole::try_from_bytes(data)?;
+    let role = AdminRole::try_from(args.role).map_err(|_| ProgramError::InvalidInstructionData)?;
+
+    // Load accounts
+    let [authority_info, admin_info] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    authority_info.is_signer()?;
+    admin_info
+        .is_writable()?
+        .has_seeds(&[ADMIN], &ephemeral_vrf_api::ID)?;
+
+    let mut admin = admin_info.as_account_mut::<Admin>(&ephemeral_vrf_api::ID)?;
+    if admin.authority.ne(authority_info.key) {
+        return Err(Unauthorized.into());
+    }
+    admin.set_role(role, args.member);
+
+    Ok(())
+}
@@ -0,0 +1,39 @@
+use ephemeral_vrf_api::loaders::check_admin_role;
+use ephemeral_vrf_api::prelude::*;
+
+/// Process the pause or resumption of the intake of requests of a queue
+///
+/// Paused queues reject new requests, subscription rounds and reassigned requests. Queued
+/// requests are still fulfilled or purged.
+///
+/// Accounts:
+///
+/// 0. `[signer]` pauser_info - The admin authority or pauser
+/// 1. `[]` admin_info - The admin account ([ADMIN])
+/// 2. `[writable]` queue_info - The oracle or shared queue
+///
+/// Requirements:
+///
+/// - The signer must be the admin authority or the pauser
+/// - The queue must be owned by the program, delegated queues are paused before delegation
+///
+/// 1. Set the paused flag of the queue
+pub fn process_set_queue_paused(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    // Parse args
+    let args = SetQueuePaused::try_from_bytes(data)?;
+
+    // Load accounts
+    let [pauser_info, admin_info, queue_info] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    check_admin_role(admin_info, pauser_info, AdminRole::Pauser)?;
+    queue_info
+        .is_writable()?
+        .has_owner(&ephemeral_vrf_api::ID)?;
+
+    let mut data = queue_info.try_borrow_mut_data()?;
+    let header = Queue::try_from_bytes_mut(&mut data)?;
+    header.paused = (args.paused != 0) as u8;
+
+    Ok(())
+}
@@ -0,0 +1,35 @@
+use ephemeral_vrf_api::loaders::check_admin_role;
+use ephemeral_vrf_api::prelude::*;
+
+/// Process the update of the delay after which pending requests can be reassigned
+///
+/// Accounts:
+///
+/// 0. `[signer]` signer_info - The admin authority or oracle manager
+/// 1. `[writable]` admin_info - The admin account ([ADMIN])
+///
+/// Requirements:
+///
+/// - The signer must be the admin authority or the oracle manager
+/// - The delay must be a share of the TTL, between 1 and 9999 basis points
+///
+/// 1. Set the share of the TTL after which requests can be reassigned (see `ReassignRequest`)
+pub fn process_set_reassign_delay(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    // Parse args
+    let args = SetReassignDelay::try_from_bytes(data)?;
+
+    // Load accounts
+    let [signer_info, admin_info] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    check_admin_role(admin_info, signer_info, AdminRole::OracleManager)?;
+    admin_info.is_writable()?;
+    if args.after_ttl_bps == 0 || args.after_ttl_bps >= 10_000 {
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    let mut admin = admin_info.as_account_mut::<Admin>(&ephemeral_vrf_api::ID)?;
+    admin.reassign_after_ttl_bps = args.after_ttl_bps;
+
+    Ok(())
+}
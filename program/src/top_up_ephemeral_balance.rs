@@ -0,0 +1,63 @@
+use ephemeral_vrf_api::prelude::*;
+use solana_program::program::invoke;
+use solana_system_interface::instruction as system_instruction;
+
+/// Process the top up of a payer's ephemeral balance
+///
+/// Accounts:
+///
+/// 0. `[signer]` payer_info - The payer owning the balance and funding the top up
+/// 1. `[writable]` ephemeral_balance_info - The ephemeral balance PDA ([EPHEMERAL_BALANCE, payer, index])
+/// 2. `[]` system_program - The system program
+///
+/// Requirements:
+///
+/// - The payer must be a signer
+/// - The balance must not be delegated, top ups happen on the base layer
+///
+/// 1. Create the ephemeral balance on first use
+/// 2. Transfer the amount from the payer to the balance
+pub fn process_top_up_ephemeral_balance(
+    accounts: &[AccountInfo<'_>],
+    data: &[u8],
+) -> ProgramResult {
+    // Parse args
+    let args = TopUpEphemeralBalance::try_from_bytes(data)?;
+
+    // Load accounts
+    let [payer_info, ephemeral_balance_info, system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    payer_info.is_signer()?;
+
+    let seeds: &[&[u8]] = &[EPHEMERAL_BALANCE, payer_info.key.as_ref(), &[args.index]];
+    ephemeral_balance_info
+        .is_writable()?
+        .has_seeds(seeds, &ephemeral_vrf_api::ID)?;
+
+    // Create the balance on first use
+    if ephemeral_balance_info.owner != &ephemeral_vrf_api::ID {
+        create_program_account::<EphemeralBalance>(
+            ephemeral_balance_info,
+            system_program,
+            payer_info,
+            &ephemeral_vrf_api::ID,
+            seeds,
+        )?;
+        let mut balance =
+            ephemeral_balance_info.as_account_mut::<EphemeralBalance>(&ephemeral_vrf_api::ID)?;
+        balance.payer = *payer_info.key;
+        balance.index = args.index;
+    }
+
+    invoke(
+        &system_instruction::transfer(payer_info.key, ephemeral_balance_info.key, args.amount),
+        &[
+            payer_info.clone(),
+            ephemeral_balance_info.clone(),
+            system_program.clone(),
+        ],
+    )?;
+
+    Ok(())
+}
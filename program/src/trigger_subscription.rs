@@ -0,0 +1,169 @@
+use crate::provide_randomness::verify_oracle;
+use ephemeral_vrf_api::prelude::*;
+use solana_program::msg;
+use solana_program::sysvar::slot_hashes;
+
+/// Process the enqueuing of the next round of a recurring randomness request
+///
+/// Accounts:
+///
+/// 0. `[signer]` oracle_info - The oracle of the queue
+/// 1. `[]` oracle_data_info - The oracle data PDA
+/// 2. `[writable]` oracle_queue_info - The oracle queue of the subscription
+/// 3. `[writable]` subscription_info - The subscription PDA
+/// 4. `[]` slothashes_account_info - The SlotHashes sysvar account
+///
+/// Requirements:
+///
+/// - The oracle must be a signer and own the queue
+/// - The queue must be the queue of the subscription and not paused
+/// - The SlotHashes sysvar must hold the hash of the slot of the next round, or of the first
+///   slot after it when skipped
+/// - The subscription must hold the fee of the round above rent
+///
+/// 1. Insert the round into the queue as a regular request, its id derived from the
+///    subscription, its sequence number and the hash of the slot of the round, so that the
+///    oracle can't pick the input by choosing when to trigger it
+/// 2. Debit the fee of the round from the subscription into the queue
+/// 3. Schedule the next round, skipping the missed ones
+///
+/// A round whose slot hash left the sysvar is skipped without being enqueued, like a
+/// request left to expire.
+pub fn process_trigger_subscription(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    // Parse args
+    TriggerSubscription::try_from_bytes(data)?;
+
+    // Load accounts
+    let [oracle_info, oracle_data_info, oracle_queue_info, subscription_info, slothashes_account_info] =
+        accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    verify_oracle(oracle_info, oracle_data_info, oracle_queue_info)?;
+    subscription_info
+        .is_writable()?
+        .has_owner(&ephemeral_vrf_api::ID)?;
+    let mut subscription =
+        Subscription::try_from_bytes_with_discriminator(&subscription_info.try_borrow_data()?)?;
+    subscription_info.has_seeds(
+        &[
+            SUBSCRIPTION,
+            subscription.callback_program_id.as_ref(),
+            &subscription.caller_seed,
+        ],
+        &ephemeral_vrf_api::ID,
+    )?;
+    oracle_queue_info.has_address(&subscription.queue)?;
+
+    slothashes_account_info.is_sysvar(&slot_hashes::id())?;
+    let slot = Clock::get()?.slot;
+    let round_hash = find_round_hash(
+        &slothashes_account_info.try_borrow_data()?,
+        subscription.next_round_slot,
+    );
+    let (round_slot, slothash) = match round_hash {
+        RoundHash::Pending => return Err(EphemeralVrfError::RequestNotDue.into()),
+        RoundHash::Found(round_slot, slothash) => (round_slot, slothash),
+        RoundHash::Expired(_) => {
+            msg!("Subscription round skipped: {}", subscription.sequence);
+            subscription.advance(slot);
+            let subscription_bytes = subscription.to_bytes_with_discriminator()?;
+            subscription_info
+                .try_borrow_mut_data()?
+                .copy_from_slice(&subscription_bytes);
+            return Ok(());
+        }
+    };
+
+    let fee = request_fee_lamports(
+        false,
+        subscription.callback_accounts_metas.len() + subscription.callback_pda_metas.len(),
+        request_data_len(
+            &subscription.callback_args,
+            &subscription.callback_pda_metas,
+        ),
+    );
+    let request_id = Subscription::round_id(
+        subscription_info.key,
+        subscription.sequence,
+        round_slot,
+        &slothash,
+    );
+    {
+        let mut data = oracle_queue_info.try_borrow_mut_data()?;
+        let mut queue_acc = QueueAccount::load(&mut data[8..])?;
+        if queue_acc.header.paused == 1 {
+            return Err(EphemeralVrfError::QueuePaused.into());
+        }
+        let base_item = QueueItem {
+            slot,
+            id: request_id,
+            caller_seed: subscription.caller_seed,
+            callback_program_id: subscription.callback_program_id.to_bytes(),
+            lookup_table: [0; 32],
+            fee,
+            tip: 0,
+            not_before_slot: 0,
+            not_before_timestamp: 0,
+            callback_discriminator_offset: 0,
+            metas_offset: 0,
+            args_offset: 0,
+            callback_discriminator_len: 0,
+            metas_len: 0,
+            args_len: 0,
+            priority_request: 0,
+            used: 0,
+            fee_payment: FeePayment::Lamports.into(),
+            pull: 0,
+            pda_metas_len: 0,
+            pda_metas_offset: 0,
+            num_words: subscription.num_words,
+            callback_abi: subscription.callback_abi.into(),
+            receipt: 0,
+            ..QueueItem::default()
+        };
+        let metas = subscription
+            .callback_accounts_metas
+            .iter()
+            .map(|ca| (*ca).into())
+            .collect::<Vec<CompactAccountMeta>>();
+        queue_acc.add_item(
+            &base_item,
+            &subscription.callback_discriminator,
+            &metas,
+            &subscription.callback_args,
+            &subscription.callback_pda_metas,
+        )?;
+    }
+    msg!("Subscription round: {}", subscription.sequence);
+
+    crate::fees::transfer_above_rent(subscription_info, oracle_queue_info, fee)?;
+
+    EphemeralVrfEvent::RequestCreated(RequestCreated {
+        queue: *oracle_queue_info.key,
+        request_id,
+        callback_program_id: subscription.callback_program_id,
+        caller_seed: subscription.caller_seed,
+        slot,
+        fee,
+        tip: 0,
+        fee_payment: FeePayment::Lamports,
+        high_priority: false,
+        pull: false,
+        num_words: subscription.num_words,
+        callback_abi: subscription.callback_abi,
+        not_before_slot: 0,
+        not_before_timestamp: 0,
+        subscription: Some((*subscription_info.key, subscription.sequence)),
+    })
+    .emit();
+
+    // The serialized subscription keeps its length
+    subscription.advance(slot);
+    let subscription_bytes = subscription.to_bytes_with_discriminator()?;
+    subscription_info
+        .try_borrow_mut_data()?
+        .copy_from_slice(&subscription_bytes);
+
+    Ok(())
+}
@@ -0,0 +1,50 @@
+use ephemeral_rollups_sdk::ephem::commit_and_undelegate_accounts;
+use ephemeral_vrf_api::prelude::*;
+
+/// Process the undelegation of a payer's ephemeral balance
+///
+/// Commits the balance, settling the fees debited inside the ephemeral rollup to the base
+/// layer, and returns it to the ephemeral VRF program so the remainder can be withdrawn.
+///
+/// Accounts:
+///
+/// 0. `[signer]` The payer owning the ephemeral balance
+/// 1. `[writable]` The ephemeral balance account to be undelegated
+/// 2. `[]` The Magic context account
+/// 3. `[]` The Magic program
+///
+/// Requirements:
+///
+/// - The payer (account 0) must be a signer.
+/// - The ephemeral balance (account 1) must be a valid PDA with seeds [EPHEMERAL_BALANCE, payer.key, index].
+pub fn process_undelegate_ephemeral_balance(
+    accounts: &[AccountInfo<'_>],
+    data: &[u8],
+) -> ProgramResult {
+    let args = UndelegateEphemeralBalance::try_from_bytes(data)?;
+
+    // Load accounts.
+    let [payer_info, ephemeral_balance_info, magic_context, magic_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Checks
+    payer_info.is_signer()?;
+    magic_context.has_address(&MAGIC_CONTEXT_ID)?;
+    magic_program.has_address(&MAGIC_PROGRAM_ID)?;
+    let pda_seeds: &[&[u8]] = &[EPHEMERAL_BALANCE, &payer_info.key.to_bytes(), &[args.index]];
+    ephemeral_balance_info
+        .is_writable()?
+        .has_seeds(pda_seeds, &ephemeral_vrf_api::ID)?;
+
+    // Undelegate
+    commit_and_undelegate_accounts(
+        payer_info,
+        vec![ephemeral_balance_info],
+        magic_context,
+        magic_program,
+        None,
+    )?;
+
+    Ok(())
+}
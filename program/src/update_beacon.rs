@@ -0,0 +1,83 @@
+use ephemeral_vrf_api::prelude::*;
+use ephemeral_vrf_api::verify::verify_vrf;
+use solana_program::hash::hash;
+use solana_program::sysvar::slot_hashes;
+
+/// Process the publication of a round of the randomness beacon of an oracle
+///
+/// Accounts:
+///
+/// 0. `[signer]` oracle_info - The oracle of the beacon
+/// 1. `[]` oracle_data_info - The oracle data PDA
+/// 2. `[writable]` beacon_info - The beacon PDA ([BEACON, oracle])
+/// 3. `[]` slothashes_account_info - The SlotHashes sysvar account
+///
+/// Requirements:
+///
+/// - The oracle must be a signer and registered
+/// - The slot of the round must be the first slot with a hash in the SlotHashes sysvar from
+///   `interval_slots` after the slot of the previous round, skipping the rounds whose slot
+///   hashes left the sysvar, so that the oracle can't pick the input of the round. The first
+///   round may be over any slot of the sysvar
+/// - The proof must be valid for the input derived from the oracle, the slot and its hash
+///
+/// 1. Verify the proof of the round
+/// 2. Append the round to the ring buffer of the beacon
+pub fn process_update_beacon(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    // Parse args
+    let args = UpdateBeacon::try_from_bytes(data)?;
+
+    // Load accounts
+    let [oracle_info, oracle_data_info, beacon_info, slothashes_account_info] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    oracle_info.is_signer()?;
+    oracle_data_info.has_seeds(
+        &[ORACLE_DATA, oracle_info.key.to_bytes().as_ref()],
+        &ephemeral_vrf_api::ID,
+    )?;
+    let vrf_pubkey = oracle_data_info
+        .as_account::<Oracle>(&ephemeral_vrf_api::ID)?
+        .vrf_pubkey;
+    beacon_info
+        .is_writable()?
+        .has_seeds(&[BEACON, oracle_info.key.as_ref()], &ephemeral_vrf_api::ID)?;
+    slothashes_account_info.is_sysvar(&slot_hashes::id())?;
+
+    let mut beacon = beacon_info.as_account_mut::<Beacon>(&ephemeral_vrf_api::ID)?;
+    let slot_hashes = slothashes_account_info.try_borrow_data()?;
+    let slothash = match beacon.next_round_hash(&slot_hashes) {
+        None => find_slot_hash(&slot_hashes, args.slot),
+        Some(RoundHash::Found(slot, slothash)) if slot == args.slot => Some(slothash),
+        Some(_) => None,
+    }
+    .ok_or(EphemeralVrfError::InvalidBeaconRound)?;
+
+    // Verify proof
+    let input = Beacon::round_input(oracle_info.key, args.slot, &slothash);
+    if !verify_vrf(
+        &vrf_pubkey,
+        &input,
+        &args.output,
+        (
+            &args.commitment_base_compressed,
+            &args.commitment_hash_compressed,
+            &args.scalar,
+        ),
+    ) {
+        return Err(EphemeralVrfError::InvalidProof.into());
+    }
+
+    beacon.push_round(BeaconRound {
+        slot: args.slot,
+        slothash,
+        randomness: hash(&args.output.0).to_bytes(),
+        output: args.output,
+        commitment_base_compressed: args.commitment_base_compressed,
+        commitment_hash_compressed: args.commitment_hash_compressed,
+        scalar: args.scalar,
+        vrf_pubkey,
+    });
+
+    Ok(())
+}
@@ -0,0 +1,50 @@
+use ephemeral_vrf_api::prelude::*;
+use solana_program::rent::Rent;
+
+/// Process a withdrawal from a payer's ephemeral balance
+///
+/// Accounts:
+///
+/// 0. `[signer, writable]` payer_info - The payer owning the balance, receives the lamports
+/// 1. `[writable]` ephemeral_balance_info - The ephemeral balance PDA ([EPHEMERAL_BALANCE, payer, index])
+///
+/// Requirements:
+///
+/// - The payer must be a signer
+/// - The balance must be undelegated (owned by the ephemeral VRF program)
+/// - The balance must remain rent-exempt, unless it is withdrawn entirely, which closes it
+pub fn process_withdraw_ephemeral_balance(
+    accounts: &[AccountInfo<'_>],
+    data: &[u8],
+) -> ProgramResult {
+    // Parse args
+    let args = WithdrawEphemeralBalance::try_from_bytes(data)?;
+
+    // Load accounts
+    let [payer_info, ephemeral_balance_info] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    payer_info.is_signer()?;
+    ephemeral_balance_info
+        .is_writable()?
+        .has_owner(&ephemeral_vrf_api::ID)?
+        .has_seeds(
+            &[EPHEMERAL_BALANCE, payer_info.key.as_ref(), &[args.index]],
+            &ephemeral_vrf_api::ID,
+        )?;
+
+    // Withdrawing everything closes the balance
+    if args.amount == ephemeral_balance_info.lamports() {
+        return close_account(ephemeral_balance_info, payer_info);
+    }
+
+    let available = ephemeral_balance_info
+        .lamports()
+        .saturating_sub(Rent::get()?.minimum_balance(ephemeral_balance_info.data_len()));
+    if args.amount > available {
+        return Err(ProgramError::InsufficientFunds);
+    }
+    crate::fees::transfer_fee(ephemeral_balance_info, payer_info, args.amount)?;
+
+    Ok(())
+}
@@ -0,0 +1,46 @@
+use ephemeral_vrf_api::prelude::*;
+use solana_program::rent::Rent;
+
+/// Process a withdrawal of the funds of a sponsor
+///
+/// Accounts:
+///
+/// 0. `[signer, writable]` authority_info - The sponsor authority, receives the lamports
+/// 1. `[writable]` sponsor_info - The sponsor PDA ([SPONSOR, authority, index])
+///
+/// Requirements:
+///
+/// - The authority must be a signer
+/// - The sponsor must remain rent-exempt, unless it is withdrawn entirely, which closes it
+pub fn process_withdraw_sponsor(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    // Parse args
+    let args = WithdrawSponsor::try_from_bytes(data)?;
+
+    // Load accounts
+    let [authority_info, sponsor_info] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    authority_info.is_signer()?;
+    sponsor_info
+        .is_writable()?
+        .has_owner(&ephemeral_vrf_api::ID)?
+        .has_seeds(
+            &[SPONSOR, authority_info.key.as_ref(), &[args.index]],
+            &ephemeral_vrf_api::ID,
+        )?;
+
+    // Withdrawing everything closes the sponsor
+    if args.amount == sponsor_info.lamports() {
+        return close_account(sponsor_info, authority_info);
+    }
+
+    let available = sponsor_info
+        .lamports()
+        .saturating_sub(Rent::get()?.minimum_balance(sponsor_info.data_len()));
+    if args.amount > available {
+        return Err(ProgramError::InsufficientFunds);
+    }
+    crate::fees::transfer_fee(sponsor_info, authority_info, args.amount)?;
+
+    Ok(())
+}
@@ -4,14 +4,29 @@ use crate::fixtures::{TEST_AUTHORITY, TEST_CALLBACK_PROGRAM, TEST_ORACLE};
 use ephemeral_rollups_sdk::consts::DELEGATION_PROGRAM_ID;
 use ephemeral_vrf::vrf::{compute_vrf, generate_vrf_keypair, verify_vrf};
 use ephemeral_vrf_api::prelude::*;
+use ephemeral_vrf_sdk::instructions::{
+    create_close_receipt_ix, create_request_randomness_ix, quote_request_randomness,
+    quote_request_randomness_lamports, RequestRandomnessParams, TokenFeePayment,
+};
+use ephemeral_vrf_sdk::types::{split_reassigned_flag, RequestReturnData, SerializableAccountMeta};
 use solana_compute_budget_interface::ComputeBudgetInstruction;
 use solana_curve25519::ristretto::PodRistrettoPoint;
 use solana_curve25519::scalar::PodScalar;
+use solana_loader_v3_interface::state::UpgradeableLoaderState;
+use solana_program::hash::Hash;
+use solana_program::program_error::ProgramError;
 use solana_program::rent::Rent;
+use solana_program::slot_hashes::SlotHashes;
 use solana_program::sysvar::slot_hashes;
+use solana_program_test::BanksClientError;
 use solana_program_test::{processor, read_file, ProgramTest, ProgramTestContext};
 use solana_sdk::account::Account;
+use solana_sdk::instruction::InstructionError;
+use solana_sdk::transaction::TransactionError;
 use solana_sdk::{pubkey, signature::Keypair, signer::Signer, transaction::Transaction};
+use spl_token_2022_interface::extension::{ExtensionType, StateWithExtensions};
+use spl_token_2022_interface::instruction as token_instruction;
+use spl_token_2022_interface::state::{Account as TokenAccount, Mint};
 
 async fn setup() -> ProgramTestContext {
     let mut program_test = ProgramTest::new(
@@ -83,13 +98,13 @@ async fn run_test() {
     let authority_keypair = Keypair::try_from(&TEST_AUTHORITY[..]).unwrap();
     let oracle_keypair = Keypair::try_from(&TEST_ORACLE[..]).unwrap();
 
-    // Submit initialize transaction.
-    let ix = initialize(context.payer.pubkey());
+    // Submit initialize transaction, signed by the upgrade authority.
+    let ix = initialize(authority_keypair.pubkey());
     let blockhash = banks.get_latest_blockhash().await.unwrap();
     let tx = Transaction::new_signed_with_payer(
         &[ix],
-        Some(&context.payer.pubkey()),
-        &[&context.payer],
+        Some(&authority_keypair.pubkey()),
+        &[&authority_keypair],
         blockhash,
     );
     let res = banks.process_transaction(tx).await;
@@ -102,6 +117,11 @@ async fn run_test() {
     assert_eq!(oracles_account.owner, ephemeral_vrf_api::ID);
     assert_eq!(oracles.oracles.len(), 0);
 
+    // Verify the admin was set to the authority.
+    let admin_account = banks.get_account(admin_pda().0).await.unwrap().unwrap();
+    let admin = Admin::try_from_bytes(&admin_account.data).unwrap();
+    assert_eq!(admin.authority, authority_keypair.pubkey());
+
     // Submit add oracle transaction.
     let (oracle_vrf_sk, oracle_vrf_pk) = generate_vrf_keypair(&oracle_keypair);
     let ix = add_oracle(
@@ -552,3 +572,1550 @@ pub fn request_randomness_to_queue(
         data,
     }
 }
+
+/// Custom error code of a failed transaction
+fn error_code(result: Result<(), BanksClientError>) -> u32 {
+    match result.unwrap_err().unwrap() {
+        TransactionError::InstructionError(_, InstructionError::Custom(code)) => code,
+        err => panic!("unexpected error: {err:?}"),
+    }
+}
+
+/// Native stand-in for a consumer program, registered in `setup_native`
+const CONSUMER_PROGRAM: Pubkey = Pubkey::new_from_array([42; 32]);
+
+/// Callback of the consumer program accepting the randomness
+const CONSUMER_CALLBACK: [u8; 8] = [1; 8];
+
+/// Callback of the consumer program always failing
+const CONSUMER_FAILING_CALLBACK: [u8; 8] = [2; 8];
+
+/// Callback of the consumer program failing unless its request was reassigned, taking the
+/// randomness and two bytes of callback args
+const CONSUMER_REASSIGNED_CALLBACK: [u8; 8] = [4; 8];
+
+/// Instruction of the consumer program forwarding a request to the VRF program, signed by its
+/// identity
+const CONSUMER_REQUEST: [u8; 8] = [3; 8];
+
+fn process_consumer(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> solana_program::entrypoint::ProgramResult {
+    if data.starts_with(&CONSUMER_FAILING_CALLBACK) {
+        return Err(ProgramError::Custom(0));
+    }
+    if let Some(data) = data.strip_prefix(&CONSUMER_REASSIGNED_CALLBACK) {
+        return match data
+            .get(32..)
+            .and_then(|data| split_reassigned_flag(data, 2))
+        {
+            Some((_, true)) => Ok(()),
+            _ => Err(ProgramError::Custom(1)),
+        };
+    }
+    let Some(request) = data.strip_prefix(&CONSUMER_REQUEST) else {
+        return Ok(());
+    };
+    let (identity, bump) = Pubkey::find_program_address(&[IDENTITY], program_id);
+    let ix = Instruction {
+        program_id: ephemeral_vrf_api::ID,
+        accounts: accounts
+            .iter()
+            .filter(|info| info.key != &ephemeral_vrf_api::ID)
+            .map(|info| AccountMeta {
+                pubkey: *info.key,
+                is_signer: info.is_signer || info.key == &identity,
+                is_writable: info.is_writable,
+            })
+            .collect(),
+        data: request.to_vec(),
+    };
+    solana_program::program::invoke_signed(&ix, accounts, &[&[IDENTITY, &[bump]]])
+}
+
+/// Wraps a request of the consumer program so that it is signed by its identity
+fn consumer_request(mut ix: Instruction) -> Instruction {
+    for meta in ix.accounts.iter_mut() {
+        meta.is_signer &= meta.pubkey != program_identity(&CONSUMER_PROGRAM);
+    }
+    ix.accounts
+        .push(AccountMeta::new_readonly(ephemeral_vrf_api::ID, false));
+    Instruction {
+        program_id: CONSUMER_PROGRAM,
+        accounts: ix.accounts,
+        data: [CONSUMER_REQUEST.as_slice(), &ix.data].concat(),
+    }
+}
+
+fn program_identity(program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[IDENTITY], program_id).0
+}
+
+/// Program running natively with the consumer program, an initialized admin, a registered
+/// oracle and its queue 0
+struct TestEnv {
+    context: ProgramTestContext,
+    oracle: Keypair,
+    oracle_vrf_sk: curve25519_dalek::Scalar,
+    queue: Pubkey,
+}
+
+async fn setup_native() -> TestEnv {
+    let mut program_test = ProgramTest::new(
+        "ephemeral_vrf_program",
+        ephemeral_vrf_api::ID,
+        processor!(ephemeral_vrf_program::process_instruction),
+    );
+    program_test.add_program("consumer", CONSUMER_PROGRAM, processor!(process_consumer));
+
+    let authority = Keypair::try_from(&TEST_AUTHORITY[..]).unwrap();
+    let oracle = Keypair::try_from(&TEST_ORACLE[..]).unwrap();
+    for key in [authority.pubkey(), oracle.pubkey()] {
+        program_test.add_account(
+            key,
+            Account {
+                lamports: 10_000_000_000,
+                owner: system_program::id(),
+                ..Account::default()
+            },
+        );
+    }
+
+    // The program data names the test authority as upgrade authority
+    let program_data = bincode::serialize(&UpgradeableLoaderState::ProgramData {
+        slot: 0,
+        upgrade_authority_address: Some(authority.pubkey()),
+    })
+    .unwrap();
+    program_test.add_account(
+        Pubkey::find_program_address(
+            &[ephemeral_vrf_api::ID.as_ref()],
+            &solana_sdk_ids::bpf_loader_upgradeable::id(),
+        )
+        .0,
+        Account {
+            lamports: Rent::default().minimum_balance(program_data.len()),
+            data: program_data,
+            owner: solana_sdk_ids::bpf_loader_upgradeable::id(),
+            ..Account::default()
+        },
+    );
+
+    let context = program_test.start_with_context().await;
+    let (oracle_vrf_sk, oracle_vrf_pk) = generate_vrf_keypair(&oracle);
+    let mut env = TestEnv {
+        context,
+        oracle: oracle.insecure_clone(),
+        oracle_vrf_sk,
+        queue: oracle_queue_pda(&oracle.pubkey(), 0).0,
+    };
+
+    env.send(&[initialize(authority.pubkey())], &[&authority])
+        .await
+        .unwrap();
+    env.send(
+        &[add_oracle(
+            authority.pubkey(),
+            oracle.pubkey(),
+            oracle_vrf_pk.compress().to_bytes(),
+        )],
+        &[&authority],
+    )
+    .await
+    .unwrap();
+    env.warp(200).await;
+    env.send(
+        &initialize_oracle_queue(oracle.pubkey(), oracle.pubkey(), 0, Some(20_000)),
+        &[&oracle],
+    )
+    .await
+    .unwrap();
+    env
+}
+
+impl TestEnv {
+    fn payer(&self) -> Keypair {
+        self.context.payer.insecure_clone()
+    }
+
+    /// Sends the instructions in a transaction paid by the first signer
+    async fn send(
+        &mut self,
+        ixs: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<(), BanksClientError> {
+        let mut instructions = vec![ComputeBudgetInstruction::set_compute_unit_limit(1_400_000)];
+        instructions.extend_from_slice(ixs);
+        let blockhash = self.context.get_new_latest_blockhash().await.unwrap();
+        let tx = Transaction::new_signed_with_payer(
+            &instructions,
+            Some(&signers[0].pubkey()),
+            signers,
+            blockhash,
+        );
+        self.context.banks_client.process_transaction(tx).await
+    }
+
+    async fn warp(&mut self, slots: u64) {
+        let slot = self.clock().await.slot;
+        self.context.warp_to_slot(slot + slots).unwrap();
+    }
+
+    async fn clock(&mut self) -> Clock {
+        self.context
+            .banks_client
+            .get_sysvar::<Clock>()
+            .await
+            .unwrap()
+    }
+
+    async fn account(&mut self, key: Pubkey) -> Option<Account> {
+        self.context.banks_client.get_account(key).await.unwrap()
+    }
+
+    async fn queue_items(&mut self, queue: Pubkey) -> (Vec<QueueItem>, Vec<u8>) {
+        let mut data = self.account(queue).await.unwrap().data;
+        let items = QueueAccount::load(&mut data[8..])
+            .unwrap()
+            .iter_items()
+            .collect();
+        (items, data[8..].to_vec())
+    }
+
+    /// Request parameters of the consumer program on the queue of the oracle
+    fn request_params(&self, caller_seed: u8) -> RequestRandomnessParams {
+        RequestRandomnessParams {
+            payer: self.context.payer.pubkey(),
+            oracle_queue: self.queue,
+            callback_program_id: CONSUMER_PROGRAM,
+            callback_discriminator: CONSUMER_CALLBACK.to_vec(),
+            caller_seed: [caller_seed; 32],
+            ..Default::default()
+        }
+    }
+
+    /// Sends a request of the consumer program
+    async fn request(&mut self, params: RequestRandomnessParams) -> Result<(), BanksClientError> {
+        let payer = self.payer();
+        self.send(
+            &[consumer_request(create_request_randomness_ix(params))],
+            &[&payer],
+        )
+        .await
+    }
+
+    fn proof(&self, input: [u8; 32]) -> ProvideRandomness {
+        let (output, (commitment_base, commitment_hash, s)) =
+            compute_vrf(self.oracle_vrf_sk, &input);
+        ProvideRandomness {
+            input,
+            output: PodRistrettoPoint(output.to_bytes()),
+            commitment_base_compressed: PodRistrettoPoint(commitment_base.to_bytes()),
+            commitment_hash_compressed: PodRistrettoPoint(commitment_hash.to_bytes()),
+            scalar: PodScalar(s.to_bytes()),
+        }
+    }
+
+    /// Fulfills the item of `queue`, passing its callback accounts followed by `accounts`
+    async fn provide(
+        &mut self,
+        queue: Pubkey,
+        item: &QueueItem,
+        accounts: &[AccountMeta],
+    ) -> Result<(), BanksClientError> {
+        let (_, queue_data) = self.queue_items(queue).await;
+        let proof = self.proof(item.id);
+        let mut ix = provide_randomness(
+            self.oracle.pubkey(),
+            queue,
+            Pubkey::new_from_array(item.callback_program_id),
+            item.id,
+            proof.output,
+            proof.commitment_base_compressed,
+            proof.commitment_hash_compressed,
+            proof.scalar,
+        );
+        ix.accounts.extend(
+            item.account_metas(&queue_data)
+                .iter()
+                .map(CompactAccountMeta::to_account_meta),
+        );
+        ix.accounts.extend_from_slice(accounts);
+        let oracle = self.oracle.insecure_clone();
+        self.send(&[ix], &[&oracle]).await
+    }
+
+    /// Creates a Token-2022 mint of the payer
+    async fn create_mint(&mut self) -> Pubkey {
+        let payer = self.payer();
+        let mint = Keypair::new();
+        let len = ExtensionType::try_calculate_account_len::<Mint>(&[]).unwrap();
+        self.send(
+            &[
+                solana_system_interface::instruction::create_account(
+                    &payer.pubkey(),
+                    &mint.pubkey(),
+                    Rent::default().minimum_balance(len),
+                    len as u64,
+                    &spl_token_2022_interface::ID,
+                ),
+                token_instruction::initialize_mint2(
+                    &spl_token_2022_interface::ID,
+                    &mint.pubkey(),
+                    &payer.pubkey(),
+                    None,
+                    0,
+                )
+                .unwrap(),
+            ],
+            &[&payer, &mint],
+        )
+        .await
+        .unwrap();
+        mint.pubkey()
+    }
+
+    /// Creates a token account of `owner`, minting `amount` tokens to it
+    async fn create_token_account(&mut self, mint: Pubkey, owner: Pubkey, amount: u64) -> Pubkey {
+        let payer = self.payer();
+        let account = Keypair::new();
+        let len = ExtensionType::try_calculate_account_len::<TokenAccount>(&[]).unwrap();
+        self.send(
+            &[
+                solana_system_interface::instruction::create_account(
+                    &payer.pubkey(),
+                    &account.pubkey(),
+                    Rent::default().minimum_balance(len),
+                    len as u64,
+                    &spl_token_2022_interface::ID,
+                ),
+                token_instruction::initialize_account3(
+                    &spl_token_2022_interface::ID,
+                    &account.pubkey(),
+                    &mint,
+                    &owner,
+                )
+                .unwrap(),
+                token_instruction::mint_to(
+                    &spl_token_2022_interface::ID,
+                    &mint,
+                    &account.pubkey(),
+                    &payer.pubkey(),
+                    &[],
+                    amount,
+                )
+                .unwrap(),
+            ],
+            &[&payer, &account],
+        )
+        .await
+        .unwrap();
+        account.pubkey()
+    }
+
+    /// Copies the queue of the oracle to the default ephemeral queue, returning its address
+    async fn ephemeral_queue(&mut self) -> Pubkey {
+        let queue = self.account(self.queue).await.unwrap();
+        self.context
+            .set_account(&DEFAULT_EPHEMERAL_QUEUE, &queue.into());
+        DEFAULT_EPHEMERAL_QUEUE
+    }
+
+    async fn token_amount(&mut self, account: Pubkey) -> u64 {
+        let data = self.account(account).await.unwrap().data;
+        StateWithExtensions::<TokenAccount>::unpack(&data)
+            .unwrap()
+            .base
+            .amount
+    }
+}
+
+#[tokio::test]
+async fn token_fee_is_escrowed_and_paid_to_the_oracle() {
+    let mut env = setup_native().await;
+    let oracle = env.oracle.insecure_clone();
+    let payer = env.payer();
+    let mint = env.create_mint().await;
+    let payer_token_account = env.create_token_account(mint, payer.pubkey(), 10_000).await;
+    env.send(
+        &[configure_token_fee(
+            oracle.pubkey(),
+            0,
+            mint,
+            spl_token_2022_interface::ID,
+            1_000,
+        )],
+        &[&oracle],
+    )
+    .await
+    .unwrap();
+
+    let queue_lamports = env.account(env.queue).await.unwrap().lamports;
+    let mut params = env.request_params(1);
+    params.token_fee = Some(TokenFeePayment {
+        payer_token_account,
+        mint,
+        token_program: spl_token_2022_interface::ID,
+    });
+    env.request(params).await.unwrap();
+
+    // The fee is escrowed in tokens, not in lamports
+    let (items, _) = env.queue_items(env.queue).await;
+    assert_eq!(items.len(), 1);
+    assert!(items[0].is_token_fee());
+    assert_eq!(items[0].fee, 1_000);
+    assert_eq!(env.token_amount(payer_token_account).await, 9_000);
+    assert_eq!(
+        env.token_amount(token_fee_escrow_pda(&env.queue).0).await,
+        1_000
+    );
+    assert_eq!(
+        env.account(env.queue).await.unwrap().lamports,
+        queue_lamports
+    );
+
+    // Fulfilling the request pays the escrowed tokens to the oracle
+    env.warp(1).await;
+    let oracle_token_account = env.create_token_account(mint, oracle.pubkey(), 0).await;
+    let accounts = token_fee_accounts(
+        &env.queue,
+        oracle_token_account,
+        mint,
+        spl_token_2022_interface::ID,
+    );
+    env.provide(env.queue, &items[0], &accounts).await.unwrap();
+    assert!(env.queue_items(env.queue).await.0.is_empty());
+    assert_eq!(env.token_amount(oracle_token_account).await, 1_000);
+    assert_eq!(
+        env.token_amount(token_fee_escrow_pda(&env.queue).0).await,
+        0
+    );
+}
+
+#[tokio::test]
+async fn token_fee_scales_with_the_callback_accounts_and_args() {
+    let mut env = setup_native().await;
+    let oracle = env.oracle.insecure_clone();
+    let payer = env.payer();
+    let mint = env.create_mint().await;
+    let payer_token_account = env.create_token_account(mint, payer.pubkey(), 10_000).await;
+    env.send(
+        &[configure_token_fee(
+            oracle.pubkey(),
+            0,
+            mint,
+            spl_token_2022_interface::ID,
+            1_000,
+        )],
+        &[&oracle],
+    )
+    .await
+    .unwrap();
+
+    let mut params = env.request_params(1);
+    params.accounts_metas = Some(vec![
+        SerializableAccountMeta {
+            pubkey: Pubkey::new_unique(),
+            is_signer: false,
+            is_writable: true,
+        };
+        2
+    ]);
+    params.callback_args = Some(vec![7; 100]);
+    params.token_fee = Some(TokenFeePayment {
+        payer_token_account,
+        mint,
+        token_program: spl_token_2022_interface::ID,
+    });
+    env.request(params).await.unwrap();
+
+    // A heavier callback costs more tokens, like it costs more lamports
+    let fee = request_fee_tokens(1_000, 2, 100);
+    assert!(fee > 1_000);
+    let (items, _) = env.queue_items(env.queue).await;
+    assert_eq!(items[0].fee, fee);
+    assert_eq!(env.token_amount(payer_token_account).await, 10_000 - fee);
+    assert_eq!(
+        env.token_amount(token_fee_escrow_pda(&env.queue).0).await,
+        fee
+    );
+}
+
+#[tokio::test]
+async fn token_fee_of_expired_requests_is_refunded_to_the_receipt_payer() {
+    let mut env = setup_native().await;
+    let oracle = env.oracle.insecure_clone();
+    let payer = env.payer();
+    let mint = env.create_mint().await;
+    let payer_token_account = env.create_token_account(mint, payer.pubkey(), 10_000).await;
+    env.send(
+        &[configure_token_fee(
+            oracle.pubkey(),
+            0,
+            mint,
+            spl_token_2022_interface::ID,
+            1_000,
+        )],
+        &[&oracle],
+    )
+    .await
+    .unwrap();
+    for (caller_seed, receipt) in [(1, true), (2, false)] {
+        let mut params = env.request_params(caller_seed);
+        params.receipt = receipt;
+        params.token_fee = Some(TokenFeePayment {
+            payer_token_account,
+            mint,
+            token_program: spl_token_2022_interface::ID,
+        });
+        env.request(params).await.unwrap();
+    }
+    assert_eq!(env.token_amount(payer_token_account).await, 8_000);
+
+    // The request with a receipt is refunded to its payer, the oracle is paid the other one
+    env.warp(QUEUE_TTL_SLOTS + 1).await;
+    let oracle_token_account = env.create_token_account(mint, oracle.pubkey(), 0).await;
+    let receipt = receipt_pda(&CONSUMER_PROGRAM, &[1; 32]).0;
+    let mut purge = purge_expired_requests(oracle.pubkey(), 0);
+    purge.accounts.extend(token_fee_accounts(
+        &env.queue,
+        oracle_token_account,
+        mint,
+        spl_token_2022_interface::ID,
+    ));
+    purge.accounts.push(AccountMeta::new(receipt, false));
+    purge
+        .accounts
+        .push(AccountMeta::new(payer_token_account, false));
+    env.send(&[purge], &[&payer]).await.unwrap();
+
+    assert!(env.queue_items(env.queue).await.0.is_empty());
+    assert_eq!(env.token_amount(payer_token_account).await, 9_000);
+    assert_eq!(env.token_amount(oracle_token_account).await, 1_000);
+    assert_eq!(
+        env.token_amount(token_fee_escrow_pda(&env.queue).0).await,
+        0
+    );
+    let receipt = env.account(receipt).await.unwrap();
+    assert_eq!(
+        Receipt::try_from_bytes(&receipt.data).unwrap().status(),
+        Some(ReceiptStatus::Expired)
+    );
+}
+
+#[tokio::test]
+async fn tip_is_escrowed_and_paid_with_the_fee() {
+    let mut env = setup_native().await;
+    let queue_rent = env.account(env.queue).await.unwrap().lamports;
+    let mut params = env.request_params(1);
+    params.tip = 7_000;
+    env.request(params).await.unwrap();
+
+    let (items, _) = env.queue_items(env.queue).await;
+    assert_eq!(items[0].tip, 7_000);
+    assert_eq!(items[0].lamports_due(), items[0].fee + 7_000);
+    assert_eq!(
+        env.account(env.queue).await.unwrap().lamports,
+        queue_rent + items[0].lamports_due()
+    );
+
+    env.warp(1).await;
+    let oracle_lamports = env.account(env.oracle.pubkey()).await.unwrap().lamports;
+    env.provide(env.queue, &items[0], &[]).await.unwrap();
+    assert_eq!(env.account(env.queue).await.unwrap().lamports, queue_rent);
+    assert_eq!(
+        env.account(env.oracle.pubkey()).await.unwrap().lamports,
+        oracle_lamports + items[0].lamports_due() - 5_000
+    );
+}
+
+#[tokio::test]
+async fn fee_prices_callback_accounts_and_args() {
+    let mut env = setup_native().await;
+    let mut params = env.request_params(1);
+    params.accounts_metas = Some(vec![
+        SerializableAccountMeta {
+            pubkey: Pubkey::new_unique(),
+            is_signer: false,
+            is_writable: true,
+        };
+        2
+    ]);
+    params.callback_args = Some(vec![7; 10]);
+    let quote = quote_request_randomness_lamports(&params, true);
+    env.request(params).await.unwrap();
+
+    let (items, queue_data) = env.queue_items(env.queue).await;
+    assert_eq!(items[0].account_metas(&queue_data).len(), 2);
+    assert_eq!(items[0].callback_args(&queue_data), &[7; 10]);
+    assert_eq!(items[0].priority_request, 1);
+    assert_eq!(items[0].fee, request_fee_lamports(true, 2, 10));
+    assert_eq!(items[0].lamports_due(), quote);
+}
+
+#[tokio::test]
+async fn quote_covers_the_rent_of_the_result_and_receipt() {
+    let mut env = setup_native().await;
+    let payer = env.payer();
+    let mut params = env.request_params(1);
+    params.tip = 3_000;
+    params.fallback = true;
+    params.receipt = true;
+    let quote = quote_request_randomness(&params, true);
+    let payer_lamports = env.account(payer.pubkey()).await.unwrap().lamports;
+    env.request(params).await.unwrap();
+
+    let (items, _) = env.queue_items(env.queue).await;
+    assert_eq!(items[0].fee, quote.fee);
+    assert_eq!(items[0].tip, quote.tip);
+    let result = env
+        .account(randomness_result_pda(&CONSUMER_PROGRAM, &[1; 32]).0)
+        .await
+        .unwrap();
+    let receipt = env
+        .account(receipt_pda(&CONSUMER_PROGRAM, &[1; 32]).0)
+        .await
+        .unwrap();
+    assert_eq!(quote.rent, result.lamports + receipt.lamports);
+    assert_eq!(
+        env.account(payer.pubkey()).await.unwrap().lamports,
+        payer_lamports - quote.total() - 5_000
+    );
+}
+
+#[tokio::test]
+async fn ephemeral_queue_is_paid_from_the_ephemeral_balance() {
+    let mut env = setup_native().await;
+    let payer = env.payer();
+    let queue = env.ephemeral_queue().await;
+    env.send(
+        &[top_up_ephemeral_balance(payer.pubkey(), 0, 10_000_000)],
+        &[&payer],
+    )
+    .await
+    .unwrap();
+    let balance = ephemeral_balance_pda(&payer.pubkey(), 0).0;
+    let balance_lamports = env.account(balance).await.unwrap().lamports;
+    let queue_lamports = env.account(queue).await.unwrap().lamports;
+
+    let mut params = env.request_params(1);
+    params.oracle_queue = queue;
+    env.request(params).await.unwrap();
+
+    let (items, _) = env.queue_items(queue).await;
+    assert_eq!(items[0].fee_payment, u8::from(FeePayment::EphemeralBalance));
+    assert_eq!(items[0].fee, request_fee_lamports(true, 0, 0));
+    assert_eq!(
+        env.account(balance).await.unwrap().lamports,
+        balance_lamports - items[0].fee
+    );
+    assert_eq!(
+        env.account(queue).await.unwrap().lamports,
+        queue_lamports + items[0].fee
+    );
+}
+
+#[tokio::test]
+async fn ephemeral_queue_accepts_legacy_requests_until_the_deadline() {
+    let mut env = setup_native().await;
+    let queue = env.ephemeral_queue().await;
+
+    // Clients predating the fee payment send neither the fee payment nor the balance account
+    let mut params = env.request_params(1);
+    params.oracle_queue = queue;
+    let mut legacy = create_request_randomness_ix(params);
+    legacy.accounts.truncate(5);
+    let args = RequestRandomness::try_from_bytes(&legacy.data[8..]).unwrap();
+    legacy.data.truncate(8);
+    borsh::to_writer(
+        &mut legacy.data,
+        &(
+            args.caller_seed,
+            args.callback_program_id,
+            args.callback_discriminator,
+            args.callback_accounts_metas,
+            args.callback_args,
+        ),
+    )
+    .unwrap();
+    assert!(RequestRandomness::is_legacy(&legacy.data[8..]));
+
+    let payer = env.payer();
+    let queue_lamports = env.account(queue).await.unwrap().lamports;
+    env.send(&[consumer_request(legacy.clone())], &[&payer])
+        .await
+        .unwrap();
+    let (items, _) = env.queue_items(queue).await;
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0].fee, 0);
+    assert_eq!(env.account(queue).await.unwrap().lamports, queue_lamports);
+
+    // Explicitly paying in lamports is rejected
+    let mut lamports = legacy.clone();
+    lamports.data.push(u8::from(FeePayment::Lamports));
+    assert_eq!(
+        error_code(env.send(&[consumer_request(lamports)], &[&payer]).await),
+        EphemeralVrfError::InvalidFeePayment as u32
+    );
+
+    // Past the deadline, legacy requests are rejected
+    let mut clock = env.clock().await;
+    clock.unix_timestamp = LEGACY_EPHEMERAL_REQUESTS_DEADLINE;
+    env.context.set_sysvar(&clock);
+    assert_eq!(
+        error_code(env.send(&[consumer_request(legacy)], &[&payer]).await),
+        EphemeralVrfError::InvalidFeePayment as u32
+    );
+}
+
+#[tokio::test]
+async fn sponsor_pays_requests_of_whitelisted_programs() {
+    let mut env = setup_native().await;
+    let payer = env.payer();
+    let authority = Keypair::new();
+    let sponsor = sponsor_pda(&authority.pubkey(), 0).0;
+    env.send(
+        &[solana_system_interface::instruction::transfer(
+            &payer.pubkey(),
+            &authority.pubkey(),
+            1_000_000_000,
+        )],
+        &[&payer],
+    )
+    .await
+    .unwrap();
+    env.send(
+        &[
+            configure_sponsor(authority.pubkey(), 0, 100, 1),
+            solana_system_interface::instruction::transfer(
+                &authority.pubkey(),
+                &sponsor,
+                100_000_000,
+            ),
+        ],
+        &[&authority],
+    )
+    .await
+    .unwrap();
+
+    // Programs missing from the whitelist are not sponsored
+    let mut params = env.request_params(1);
+    params.sponsor = Some(sponsor);
+    assert_eq!(
+        error_code(env.request(params).await),
+        EphemeralVrfError::ProgramNotSponsored as u32
+    );
+
+    env.send(
+        &[add_sponsored_program(
+            authority.pubkey(),
+            0,
+            CONSUMER_PROGRAM,
+            0,
+        )],
+        &[&authority],
+    )
+    .await
+    .unwrap();
+    let payer_lamports = env.account(payer.pubkey()).await.unwrap().lamports;
+    let sponsor_lamports = env.account(sponsor).await.unwrap().lamports;
+    let mut params = env.request_params(1);
+    params.sponsor = Some(sponsor);
+    env.request(params).await.unwrap();
+
+    // The payer only pays the transaction, the sponsor the fee and its usage counter
+    let (items, _) = env.queue_items(env.queue).await;
+    assert_eq!(items[0].fee_payment, u8::from(FeePayment::Sponsor));
+    let usage = env
+        .account(sponsor_usage_pda(&sponsor, &payer.pubkey()).0)
+        .await
+        .unwrap();
+    assert_eq!(
+        env.account(payer.pubkey()).await.unwrap().lamports,
+        payer_lamports - 5_000
+    );
+    assert_eq!(
+        env.account(sponsor).await.unwrap().lamports,
+        sponsor_lamports - items[0].fee - usage.lamports
+    );
+
+    // The sponsor serves one request per user and window
+    let mut params = env.request_params(2);
+    params.sponsor = Some(sponsor);
+    assert_eq!(
+        error_code(env.request(params).await),
+        EphemeralVrfError::SponsorRateLimitExceeded as u32
+    );
+}
+
+#[tokio::test]
+async fn request_returns_its_id_and_queue() {
+    let mut env = setup_native().await;
+    let payer = env.payer();
+    let blockhash = env.context.get_new_latest_blockhash().await.unwrap();
+    let tx = Transaction::new_signed_with_payer(
+        &[consumer_request(create_request_randomness_ix(
+            env.request_params(1),
+        ))],
+        Some(&payer.pubkey()),
+        &[&payer],
+        blockhash,
+    );
+    // Simulated for its return data: processing it in the working bank, as
+    // `process_transaction_with_metadata` does, can race the retries of earlier transactions
+    let simulation = env
+        .context
+        .banks_client
+        .simulate_transaction(tx.clone())
+        .await
+        .unwrap();
+    simulation.result.unwrap().unwrap();
+    let return_data = simulation.simulation_details.unwrap().return_data.unwrap();
+    assert_eq!(return_data.program_id, ephemeral_vrf_api::ID);
+    env.context
+        .banks_client
+        .process_transaction(tx)
+        .await
+        .unwrap();
+
+    let request = RequestReturnData::decode(&return_data.data).unwrap();
+    let (items, _) = env.queue_items(env.queue).await;
+    assert_eq!(request.request_id, items[0].id);
+    assert_eq!(request.queue.to_bytes(), env.queue.to_bytes());
+}
+
+#[tokio::test]
+async fn pull_result_is_kept_until_fulfilled_or_expired() {
+    let mut env = setup_native().await;
+    let payer = env.payer();
+    let result = randomness_result_pda(&payer.pubkey(), &[1; 32]).0;
+
+    // The requester pulls the randomness itself, without a program identity signing
+    let mut params = env.request_params(1);
+    params.callback_program_id = payer.pubkey();
+    params.pull = true;
+    let request = create_request_randomness_ix(params);
+    env.send(std::slice::from_ref(&request), &[&payer])
+        .await
+        .unwrap();
+    let (items, _) = env.queue_items(env.queue).await;
+
+    // The result of the pending request can't be taken over by another one
+    env.warp(1).await;
+    assert_eq!(
+        error_code(env.send(std::slice::from_ref(&request), &[&payer]).await),
+        EphemeralVrfError::ResultInUse as u32
+    );
+
+    env.provide(env.queue, &items[0], &[]).await.unwrap();
+    let account = env.account(result).await.unwrap();
+    let fulfilled = ephemeral_vrf_sdk::result::RandomnessResult::try_from_account(
+        &account.owner,
+        &account.data,
+    )
+    .unwrap();
+    assert_eq!(fulfilled.request_id, items[0].id);
+    assert_eq!(fulfilled.request_slot, items[0].slot);
+    assert_eq!(
+        fulfilled.randomness(),
+        Some(solana_program::hash::hash(&env.proof(items[0].id).output.0).to_bytes())
+    );
+
+    // Once fulfilled, or once the pending request expired, the result is reset
+    env.send(std::slice::from_ref(&request), &[&payer])
+        .await
+        .unwrap();
+    env.warp(QUEUE_TTL_SLOTS + 1).await;
+    env.send(&[request], &[&payer]).await.unwrap();
+    let (items, _) = env.queue_items(env.queue).await;
+    let account = env.account(result).await.unwrap();
+    let pending = RandomnessResult::try_from_bytes(&account.data).unwrap();
+    assert_eq!(pending.request_id, items[1].id);
+    assert_eq!(pending.fulfilled, 0);
+}
+
+#[tokio::test]
+async fn fallback_records_the_randomness_of_opted_in_requests() {
+    let mut env = setup_native().await;
+    let payer = env.payer();
+    let oracle = env.oracle.insecure_clone();
+    let result = randomness_result_pda(&CONSUMER_PROGRAM, &[1; 32]).0;
+
+    // Only requests opting into the fallback create the result PDA and can be fulfilled by it
+    let mut params = env.request_params(1);
+    params.callback_discriminator = CONSUMER_FAILING_CALLBACK.to_vec();
+    params.fallback = true;
+    env.request(params).await.unwrap();
+    let mut params = env.request_params(2);
+    params.callback_discriminator = CONSUMER_FAILING_CALLBACK.to_vec();
+    env.request(params).await.unwrap();
+    assert!(env
+        .account(randomness_result_pda(&CONSUMER_PROGRAM, &[2; 32]).0)
+        .await
+        .is_none());
+    let result_rent = env.account(result).await.unwrap().lamports;
+
+    let (items, _) = env.queue_items(env.queue).await;
+    let fallback = |env: &TestEnv, item: &QueueItem| {
+        let proof = env.proof(item.id);
+        provide_randomness_fallback(
+            env.oracle.pubkey(),
+            env.queue,
+            CONSUMER_PROGRAM,
+            item.caller_seed,
+            item.id,
+            proof.output,
+            proof.commitment_base_compressed,
+            proof.commitment_hash_compressed,
+            proof.scalar,
+        )
+    };
+    env.warp(1).await;
+    let ix = fallback(&env, &items[1]);
+    assert_eq!(
+        error_code(env.send(&[ix], &[&oracle]).await),
+        EphemeralVrfError::FallbackNotRequested as u32
+    );
+
+    // The result of a pending request can't be closed
+    let close = close_randomness_result(payer.pubkey(), CONSUMER_PROGRAM, [1; 32]);
+    assert_eq!(
+        error_code(env.send(std::slice::from_ref(&close), &[&payer]).await),
+        EphemeralVrfError::ResultInUse as u32
+    );
+
+    let ix = fallback(&env, &items[0]);
+    env.send(&[ix], &[&oracle]).await.unwrap();
+    let account = env.account(result).await.unwrap();
+    let recorded = RandomnessResult::try_from_bytes(&account.data).unwrap();
+    assert_eq!(recorded.request_id, items[0].id);
+    assert_eq!(recorded.fulfilled, 1);
+    assert_eq!(recorded.payer, payer.pubkey());
+
+    // Only the payer can close the result, recovering its rent
+    let other = Keypair::new();
+    env.send(
+        &[solana_system_interface::instruction::transfer(
+            &payer.pubkey(),
+            &other.pubkey(),
+            1_000_000_000,
+        )],
+        &[&payer],
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        error_code(
+            env.send(
+                &[close_randomness_result(
+                    other.pubkey(),
+                    CONSUMER_PROGRAM,
+                    [1; 32]
+                )],
+                &[&other]
+            )
+            .await
+        ),
+        EphemeralVrfError::Unauthorized as u32
+    );
+    let payer_lamports = env.account(payer.pubkey()).await.unwrap().lamports;
+    env.send(&[close], &[&payer]).await.unwrap();
+    assert!(env.account(result).await.is_none());
+    assert_eq!(
+        env.account(payer.pubkey()).await.unwrap().lamports,
+        payer_lamports + result_rent - 5_000
+    );
+}
+
+#[tokio::test]
+async fn subscription_rounds_take_the_hash_of_their_slot() {
+    let mut env = setup_native().await;
+    let payer = env.payer();
+    let oracle = env.oracle.insecure_clone();
+    let first_round_slot = env.clock().await.slot + 5;
+    let args = CreateSubscription {
+        caller_seed: [5; 32],
+        callback_program_id: CONSUMER_PROGRAM,
+        callback_discriminator: CONSUMER_CALLBACK.to_vec(),
+        interval_slots: 10,
+        first_round_slot,
+        budget: 100_000_000,
+        ..Default::default()
+    };
+    env.send(
+        &[consumer_request(create_subscription(
+            payer.pubkey(),
+            env.queue,
+            args,
+        ))],
+        &[&payer],
+    )
+    .await
+    .unwrap();
+    let subscription = subscription_pda(&CONSUMER_PROGRAM, &[5; 32]).0;
+    let trigger = trigger_subscription(oracle.pubkey(), env.queue, subscription);
+
+    // The slot of the round was skipped, its input is the hash of the next slot whatever the
+    // slot it is triggered in
+    env.warp(8).await;
+    let slot_hash = |slot: u64| (slot, Hash::new_from_array([slot as u8; 32]));
+    env.context.set_sysvar(&SlotHashes::new(&[
+        slot_hash(first_round_slot + 2),
+        slot_hash(first_round_slot + 1),
+        slot_hash(first_round_slot - 1),
+    ]));
+    env.send(std::slice::from_ref(&trigger), &[&oracle])
+        .await
+        .unwrap();
+    let (items, _) = env.queue_items(env.queue).await;
+    assert_eq!(items.len(), 1);
+    assert_eq!(
+        items[0].id,
+        Subscription::round_id(
+            &subscription,
+            0,
+            first_round_slot + 1,
+            &[(first_round_slot + 1) as u8; 32]
+        )
+    );
+
+    // The next round waits for the hash of its slot
+    assert_eq!(
+        error_code(env.send(std::slice::from_ref(&trigger), &[&oracle]).await),
+        EphemeralVrfError::RequestNotDue as u32
+    );
+
+    // A round whose slot hash left the sysvar is skipped
+    env.warp(30).await;
+    env.context.set_sysvar(&SlotHashes::new(&[
+        slot_hash(first_round_slot + 31),
+        slot_hash(first_round_slot + 30),
+    ]));
+    env.send(&[trigger], &[&oracle]).await.unwrap();
+    let (items, _) = env.queue_items(env.queue).await;
+    assert_eq!(items.len(), 1);
+    let account = env.account(subscription).await.unwrap();
+    let state = Subscription::try_from_bytes_with_discriminator(&account.data).unwrap();
+    assert_eq!(state.sequence, 2);
+    assert!(state.next_round_slot > first_round_slot + 31);
+}
+
+#[tokio::test]
+async fn beacon_rounds_take_the_hash_of_their_scheduled_slot() {
+    let mut env = setup_native().await;
+    let oracle = env.oracle.insecure_clone();
+    env.send(&[configure_beacon(oracle.pubkey(), 10)], &[&oracle])
+        .await
+        .unwrap();
+    let slot_hash = |slot: u64| (slot, Hash::new_from_array([slot as u8; 32]));
+    let publish = |env: &TestEnv, slot: u64| {
+        let input = Beacon::round_input(&oracle.pubkey(), slot, &[slot as u8; 32]);
+        let proof = env.proof(input);
+        update_beacon(
+            oracle.pubkey(),
+            slot,
+            proof.output,
+            proof.commitment_base_compressed,
+            proof.commitment_hash_compressed,
+            proof.scalar,
+        )
+    };
+
+    // The first round is over any slot of the sysvar
+    env.warp(20).await;
+    let slot = env.clock().await.slot;
+    env.context.set_sysvar(&SlotHashes::new(&[
+        slot_hash(slot - 1),
+        slot_hash(slot - 3),
+    ]));
+    let ix = publish(&env, slot - 3);
+    env.send(&[ix], &[&oracle]).await.unwrap();
+
+    // The next one is over the first slot with a hash an interval later
+    env.warp(20).await;
+    env.context.set_sysvar(&SlotHashes::new(&[
+        slot_hash(slot + 9),
+        slot_hash(slot + 8),
+        slot_hash(slot + 6),
+        slot_hash(slot + 4),
+    ]));
+    for early_or_late in [slot + 6, slot + 9] {
+        let ix = publish(&env, early_or_late);
+        assert_eq!(
+            error_code(env.send(&[ix], &[&oracle]).await),
+            EphemeralVrfError::InvalidBeaconRound as u32
+        );
+    }
+    let ix = publish(&env, slot + 8);
+    env.send(&[ix], &[&oracle]).await.unwrap();
+
+    let account = env.account(beacon_pda(&oracle.pubkey()).0).await.unwrap();
+    let beacon = ephemeral_vrf_sdk::beacon::Beacon::try_from_account(
+        &beacon_pda(&oracle.pubkey()).0,
+        &account.owner,
+        &account.data,
+    )
+    .unwrap();
+    assert_eq!(beacon.round_count, 2);
+    let latest = beacon.latest_round().unwrap();
+    assert_eq!(latest.slot, slot + 8);
+    assert!(latest.verify(&oracle.pubkey()));
+}
+
+#[tokio::test]
+async fn pending_receipts_are_kept_while_their_request_may_be_queued() {
+    let mut env = setup_native().await;
+    let payer = env.payer();
+    let mut params = env.request_params(3);
+    params.receipt = true;
+    let request = consumer_request(create_request_randomness_ix(params));
+    env.send(std::slice::from_ref(&request), &[&payer])
+        .await
+        .unwrap();
+    let receipt = receipt_pda(&CONSUMER_PROGRAM, &[3; 32]).0;
+    let close = create_close_receipt_ix(payer.pubkey(), receipt, env.queue);
+
+    // The receipt PDA is shared by the requests with the same caller seed
+    env.warp(1).await;
+    assert_eq!(
+        error_code(env.send(&[request], &[&payer]).await),
+        EphemeralVrfError::ReceiptInUse as u32
+    );
+    assert_eq!(
+        error_code(env.send(std::slice::from_ref(&close), &[&payer]).await),
+        EphemeralVrfError::ReceiptInUse as u32
+    );
+
+    // The requests of a delegated queue can't be read
+    let queue = env.account(env.queue).await.unwrap();
+    let mut delegated = queue.clone();
+    delegated.owner = DELEGATION_PROGRAM_ID;
+    env.context.set_account(&env.queue, &delegated.into());
+    assert_eq!(
+        error_code(env.send(std::slice::from_ref(&close), &[&payer]).await),
+        EphemeralVrfError::ReceiptInUse as u32
+    );
+    env.context.set_account(&env.queue, &queue.into());
+
+    let (items, _) = env.queue_items(env.queue).await;
+    env.provide(env.queue, &items[0], &[AccountMeta::new(receipt, false)])
+        .await
+        .unwrap();
+    let account = env.account(receipt).await.unwrap();
+    let fulfilled =
+        ephemeral_vrf_sdk::receipt::Receipt::try_from_account(&account.owner, &account.data)
+            .unwrap();
+    assert_eq!(fulfilled.request_id, items[0].id);
+    assert!(fulfilled.randomness().is_some());
+    env.send(&[close], &[&payer]).await.unwrap();
+    assert!(env.account(receipt).await.is_none());
+}
+
+#[tokio::test]
+async fn shared_queue_pays_the_first_registered_oracle_to_fulfill() {
+    let mut env = setup_native().await;
+    let authority = Keypair::try_from(&TEST_AUTHORITY[..]).unwrap();
+    let shared_queue = shared_queue_pda(0).0;
+    env.send(
+        &initialize_shared_queue(authority.pubkey(), 0, Some(20_000)),
+        &[&authority],
+    )
+    .await
+    .unwrap();
+
+    // A second registered oracle
+    let second_oracle = Keypair::new();
+    let (second_vrf_sk, second_vrf_pk) = generate_vrf_keypair(&second_oracle);
+    env.context.set_account(
+        &second_oracle.pubkey(),
+        &Account {
+            lamports: 1_000_000_000,
+            owner: system_program::id(),
+            ..Account::default()
+        }
+        .into(),
+    );
+    env.send(
+        &[add_oracle(
+            authority.pubkey(),
+            second_oracle.pubkey(),
+            second_vrf_pk.compress().to_bytes(),
+        )],
+        &[&authority],
+    )
+    .await
+    .unwrap();
+
+    let mut params = env.request_params(4);
+    params.oracle_queue = shared_queue;
+    env.request(params).await.unwrap();
+    let (items, _) = env.queue_items(shared_queue).await;
+    env.warp(1).await;
+
+    // Oracles which aren't registered can't fulfill it
+    let first_oracle = std::mem::replace(&mut env.oracle, Keypair::new());
+    let first_vrf_sk = std::mem::replace(&mut env.oracle_vrf_sk, second_vrf_sk);
+    env.context.set_account(
+        &env.oracle.pubkey(),
+        &Account {
+            lamports: 1_000_000_000,
+            owner: system_program::id(),
+            ..Account::default()
+        }
+        .into(),
+    );
+    assert!(env.provide(shared_queue, &items[0], &[]).await.is_err());
+
+    // The first oracle to fulfill it is paid
+    env.oracle = second_oracle.insecure_clone();
+    let before = env.account(second_oracle.pubkey()).await.unwrap().lamports;
+    env.provide(shared_queue, &items[0], &[]).await.unwrap();
+    let after = env.account(second_oracle.pubkey()).await.unwrap().lamports;
+    assert_eq!(after + 5_000 - before, items[0].lamports_due());
+
+    env.oracle = first_oracle;
+    env.oracle_vrf_sk = first_vrf_sk;
+    assert_eq!(
+        error_code(env.provide(shared_queue, &items[0], &[]).await),
+        EphemeralVrfError::RandomnessRequestNotFound as u32
+    );
+}
+
+#[tokio::test]
+async fn requests_are_reassigned_to_another_oracle_once_per_delay() {
+    let mut env = setup_native().await;
+    let authority = Keypair::try_from(&TEST_AUTHORITY[..]).unwrap();
+
+    // Two more registered oracles with a queue
+    let [second_oracle, third_oracle] = [Keypair::new(), Keypair::new()];
+    for oracle in [&second_oracle, &third_oracle] {
+        env.context.set_account(
+            &oracle.pubkey(),
+            &Account {
+                lamports: 1_000_000_000,
+                owner: system_program::id(),
+                ..Account::default()
+            }
+            .into(),
+        );
+        let (_, vrf_pk) = generate_vrf_keypair(oracle);
+        env.send(
+            &[add_oracle(
+                authority.pubkey(),
+                oracle.pubkey(),
+                vrf_pk.compress().to_bytes(),
+            )],
+            &[&authority],
+        )
+        .await
+        .unwrap();
+    }
+    env.warp(200).await;
+    for oracle in [&second_oracle, &third_oracle] {
+        env.send(
+            &initialize_oracle_queue(oracle.pubkey(), oracle.pubkey(), 0, Some(20_000)),
+            &[oracle],
+        )
+        .await
+        .unwrap();
+    }
+    let second_queue = oracle_queue_pda(&second_oracle.pubkey(), 0).0;
+    let third_queue = oracle_queue_pda(&third_oracle.pubkey(), 0).0;
+
+    let reassigned_params = |env: &TestEnv, caller_seed| {
+        let mut params = env.request_params(caller_seed);
+        params.callback_discriminator = CONSUMER_REASSIGNED_CALLBACK.to_vec();
+        params.callback_args = Some(vec![5; 2]);
+        params
+    };
+    env.request(reassigned_params(&env, 9)).await.unwrap();
+    let (items, _) = env.queue_items(env.queue).await;
+    let request_id = items[0].id;
+    let payer = env.payer();
+    let first = env.oracle.pubkey();
+    let (second, third) = (second_oracle.pubkey(), third_oracle.pubkey());
+
+    // Not before half of its TTL
+    env.warp(QUEUE_TTL_SLOTS / 2 - 1).await;
+    let to_third = reassign_request(first, 0, third, 0, request_id);
+    assert_eq!(
+        error_code(env.send(std::slice::from_ref(&to_third), &[&payer]).await),
+        EphemeralVrfError::RequestNotReassignable as u32
+    );
+
+    // Then to any other registered oracle, whatever their registration order
+    env.warp(1).await;
+    assert_eq!(
+        error_code(
+            env.send(
+                &[reassign_request(first, 0, first, 0, request_id)],
+                &[&payer]
+            )
+            .await
+        ),
+        EphemeralVrfError::RequestNotReassignable as u32
+    );
+    env.send(&[to_third], &[&payer]).await.unwrap();
+    let slot = env.clock().await.slot;
+    let (items, _) = env.queue_items(third_queue).await;
+    assert_eq!(items[0].id, request_id);
+    assert_eq!(items[0].reassigned_slot, slot);
+
+    // Not again before the delay passed since the reassignment
+    let to_second = reassign_request(third, 0, second, 0, request_id);
+    env.warp(1).await;
+    assert_eq!(
+        error_code(env.send(std::slice::from_ref(&to_second), &[&payer]).await),
+        EphemeralVrfError::RequestNotReassignable as u32
+    );
+
+    // The delay is set by the admin
+    assert_eq!(
+        error_code(
+            env.send(&[set_reassign_delay(payer.pubkey(), 2_500)], &[&payer])
+                .await
+        ),
+        EphemeralVrfError::Unauthorized as u32
+    );
+    assert!(matches!(
+        env.send(
+            &[set_reassign_delay(authority.pubkey(), 10_000)],
+            &[&authority]
+        )
+        .await
+        .unwrap_err()
+        .unwrap(),
+        TransactionError::InstructionError(_, InstructionError::InvalidArgument)
+    ));
+    env.send(
+        &[set_reassign_delay(authority.pubkey(), 2_500)],
+        &[&authority],
+    )
+    .await
+    .unwrap();
+    env.warp(QUEUE_TTL_SLOTS / 4 - 1).await;
+    env.send(&[to_second], &[&payer]).await.unwrap();
+    let (items, _) = env.queue_items(second_queue).await;
+    assert_eq!(items[0].id, request_id);
+    assert!(env.queue_items(third_queue).await.0.is_empty());
+
+    // Its callback is told it was reassigned, unlike the one of a request fulfilled in place
+    env.request(reassigned_params(&env, 10)).await.unwrap();
+    let (in_place, _) = env.queue_items(env.queue).await;
+    env.warp(1).await;
+    assert_eq!(
+        error_code(env.provide(env.queue, &in_place[0], &[]).await),
+        1
+    );
+    env.oracle = second_oracle.insecure_clone();
+    env.oracle_vrf_sk = generate_vrf_keypair(&second_oracle).0;
+    env.provide(second_queue, &items[0], &[]).await.unwrap();
+}
+
+#[tokio::test]
+async fn admin_delegates_roles_and_transfers_in_two_steps() {
+    let mut env = setup_native().await;
+    let authority = Keypair::try_from(&TEST_AUTHORITY[..]).unwrap();
+    let payer = env.payer();
+    let [manager, pauser] = [Keypair::new(), Keypair::new()];
+    let multisig = program_identity(&CONSUMER_PROGRAM);
+    // Adding an oracle pays for its data account
+    for member in [manager.pubkey(), multisig] {
+        env.context.set_account(
+            &member,
+            &Account {
+                lamports: 1_000_000_000,
+                owner: system_program::id(),
+                ..Account::default()
+            }
+            .into(),
+        );
+    }
+    let (_, vrf_pk) = generate_vrf_keypair(&manager);
+    let add = |signer: Pubkey| {
+        add_oracle(
+            signer,
+            Keypair::new().pubkey(),
+            vrf_pk.compress().to_bytes(),
+        )
+    };
+
+    // Roles are held by the authority until delegated
+    assert_eq!(
+        error_code(
+            env.send(&[add(manager.pubkey())], &[&payer, &manager])
+                .await
+        ),
+        EphemeralVrfError::Unauthorized as u32
+    );
+    for (role, member) in [
+        (AdminRole::OracleManager, manager.pubkey()),
+        (AdminRole::Pauser, pauser.pubkey()),
+    ] {
+        env.send(
+            &[set_admin_role(authority.pubkey(), role, member)],
+            &[&authority],
+        )
+        .await
+        .unwrap();
+    }
+    env.send(&[add(manager.pubkey())], &[&payer, &manager])
+        .await
+        .unwrap();
+    env.send(&[add(authority.pubkey())], &[&authority])
+        .await
+        .unwrap();
+
+    // Members only hold their role
+    assert_eq!(
+        error_code(
+            env.send(
+                &[set_queue_paused(manager.pubkey(), env.queue, true)],
+                &[&payer, &manager]
+            )
+            .await
+        ),
+        EphemeralVrfError::Unauthorized as u32
+    );
+    assert_eq!(
+        error_code(
+            env.send(
+                &[set_admin_role(
+                    manager.pubkey(),
+                    AdminRole::Pauser,
+                    manager.pubkey()
+                )],
+                &[&payer, &manager]
+            )
+            .await
+        ),
+        EphemeralVrfError::Unauthorized as u32
+    );
+    env.send(
+        &[set_queue_paused(pauser.pubkey(), env.queue, true)],
+        &[&payer, &pauser],
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        error_code(env.request(env.request_params(10)).await),
+        EphemeralVrfError::QueuePaused as u32
+    );
+    env.send(
+        &[set_queue_paused(pauser.pubkey(), env.queue, false)],
+        &[&payer, &pauser],
+    )
+    .await
+    .unwrap();
+    env.request(env.request_params(10)).await.unwrap();
+
+    // The authority is transferred to a PDA of the consumer program once it accepts
+    env.send(
+        &[propose_admin(authority.pubkey(), multisig)],
+        &[&authority],
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        error_code(
+            env.send(&[accept_admin(manager.pubkey())], &[&payer, &manager])
+                .await
+        ),
+        EphemeralVrfError::Unauthorized as u32
+    );
+    env.send(&[add(authority.pubkey())], &[&authority])
+        .await
+        .unwrap();
+    env.send(&[consumer_request(accept_admin(multisig))], &[&payer])
+        .await
+        .unwrap();
+    let admin = *Admin::try_from_bytes(&env.account(admin_pda().0).await.unwrap().data).unwrap();
+    assert_eq!(admin.authority, multisig);
+    assert_eq!(admin.pending_authority, Pubkey::default());
+
+    // The previous authority lost its rights, the PDA signs through CPI
+    assert_eq!(
+        error_code(env.send(&[add(authority.pubkey())], &[&authority]).await),
+        EphemeralVrfError::Unauthorized as u32
+    );
+    env.send(&[consumer_request(add(multisig))], &[&payer])
+        .await
+        .unwrap();
+    env.send(
+        &[consumer_request(set_admin_role(
+            multisig,
+            AdminRole::OracleManager,
+            Pubkey::default(),
+        ))],
+        &[&payer],
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        error_code(
+            env.send(&[add(manager.pubkey())], &[&payer, &manager])
+                .await
+        ),
+        EphemeralVrfError::Unauthorized as u32
+    );
+}
+
+#[tokio::test]
+async fn batch_fulfills_every_request_or_none() {
+    let mut env = setup_native().await;
+    let oracle = env.oracle.insecure_clone();
+    for caller_seed in 1..=4 {
+        env.request(env.request_params(caller_seed)).await.unwrap();
+    }
+    env.warp(1).await;
+    let (items, queue_data) = env.queue_items(env.queue).await;
+    let batch = |env: &TestEnv, items: &[QueueItem]| {
+        let requests = items
+            .iter()
+            .map(|item| {
+                let accounts = item
+                    .account_metas(&queue_data)
+                    .iter()
+                    .map(CompactAccountMeta::to_account_meta)
+                    .collect();
+                (env.proof(item.id), CONSUMER_PROGRAM, accounts)
+            })
+            .collect::<Vec<_>>();
+        provide_randomness_batch(env.oracle.pubkey(), env.queue, &requests)
+    };
+
+    // A single invalid proof fails the whole batch
+    let mut ix = batch(&env, &items[2..]);
+    // Output of the first proof, following its input
+    ix.data[8 + 32] ^= 1;
+    assert_eq!(
+        error_code(env.send(&[ix], &[&oracle]).await),
+        EphemeralVrfError::InvalidProof as u32
+    );
+    assert_eq!(env.queue_items(env.queue).await.0.len(), 4);
+
+    let oracle_lamports = env.account(oracle.pubkey()).await.unwrap().lamports;
+    let ix = batch(&env, &items[..2]);
+    env.send(&[ix], &[&oracle]).await.unwrap();
+    let (remaining, _) = env.queue_items(env.queue).await;
+    assert_eq!(
+        remaining.iter().map(|item| item.id).collect::<Vec<_>>(),
+        [items[2].id, items[3].id]
+    );
+    assert_eq!(
+        env.account(oracle.pubkey()).await.unwrap().lamports,
+        oracle_lamports + items[0].lamports_due() + items[1].lamports_due() - 5_000
+    );
+}
+
+#[test]
+fn sdk_derives_the_random_words_of_the_program() {
+    for num_words in [0, 1, 2, MAX_RANDOM_WORDS] {
+        assert_eq!(
+            ephemeral_vrf_sdk::rnd::random_words(&[5; 32], num_words),
+            ephemeral_vrf_api::verify::random_words(&[5; 32], num_words)
+        );
+    }
+}
@@ -0,0 +1,228 @@
+use crate::compat::Pubkey;
+use crate::consts;
+use crate::pda::beacon_pda;
+use crate::verify::verify_vrf;
+use solana_program::hash::{hash, hashv};
+
+const DISCRIMINATOR: u8 = 10;
+const HEADER_LEN: usize = 8;
+const ROUND_LEN: usize = 232;
+const ROUNDS_OFFSET: usize = 48;
+const LEN: usize = HEADER_LEN + ROUNDS_OFFSET + ROUND_LEN * consts::BEACON_ROUNDS;
+
+/// Randomness beacon of an oracle, a ring buffer of the latest `consts::BEACON_ROUNDS` rounds
+/// published every `interval_slots` (see `pda::beacon_pda`).
+///
+/// A round is public from the transaction publishing it, before any program consumes it: anyone
+/// can read it and front-run its consumer. Only use the randomness of a round published after
+/// the outcome it decides was committed, e.g. the first round after the slot a bet was placed,
+/// never the latest round at the time of the bet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Beacon {
+    pub oracle: Pubkey,
+    pub interval_slots: u64,
+    /// Number of rounds published so far
+    pub round_count: u64,
+    rounds: Vec<BeaconRound>,
+}
+
+/// Round of a beacon, the VRF output over the hash of `slot`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BeaconRound {
+    pub slot: u64,
+    pub slothash: [u8; 32],
+    /// Hash of the VRF output
+    pub randomness: [u8; 32],
+    pub output: [u8; 32],
+    pub commitment_base_compressed: [u8; 32],
+    pub commitment_hash_compressed: [u8; 32],
+    pub scalar: [u8; 32],
+    /// VRF public key of the oracle when the round was published
+    pub vrf_pubkey: [u8; 32],
+}
+
+impl Beacon {
+    /// Decodes a beacon account, returning `None` unless it is owned by the VRF program and
+    /// `key` is the beacon PDA of its oracle
+    pub fn try_from_account(key: &Pubkey, owner: &Pubkey, data: &[u8]) -> Option<Self> {
+        if owner != &consts::VRF_PROGRAM_ID || data.len() < LEN || data[0] != DISCRIMINATOR {
+            return None;
+        }
+        let data = &data[HEADER_LEN..LEN];
+        let u64_at = |offset: usize| -> u64 {
+            u64::from_le_bytes(
+                data[offset..offset + 8]
+                    .try_into()
+                    .expect("slice of 8 bytes"),
+            )
+        };
+        let oracle = Pubkey::new_from_array(data[..32].try_into().expect("slice of 32 bytes"));
+        if &beacon_pda(&oracle) != key {
+            return None;
+        }
+        let rounds = data[ROUNDS_OFFSET..]
+            .chunks_exact(ROUND_LEN)
+            .map(|round| {
+                let bytes32 = |offset: usize| -> [u8; 32] {
+                    round[offset..offset + 32]
+                        .try_into()
+                        .expect("slice of 32 bytes")
+                };
+                BeaconRound {
+                    slot: u64::from_le_bytes(round[..8].try_into().expect("slice of 8 bytes")),
+                    slothash: bytes32(8),
+                    randomness: bytes32(40),
+                    output: bytes32(72),
+                    commitment_base_compressed: bytes32(104),
+                    commitment_hash_compressed: bytes32(136),
+                    scalar: bytes32(168),
+                    vrf_pubkey: bytes32(200),
+                }
+            })
+            .collect();
+        Some(Self {
+            oracle,
+            interval_slots: u64_at(32),
+            round_count: u64_at(40),
+            rounds,
+        })
+    }
+
+    /// Latest round, if any
+    pub fn latest_round(&self) -> Option<&BeaconRound> {
+        self.round(self.round_count.checked_sub(1)?)
+    }
+
+    /// Round number `round`, counted from 0, if still in the ring buffer
+    pub fn round(&self, round: u64) -> Option<&BeaconRound> {
+        if round >= self.round_count || self.round_count - round > consts::BEACON_ROUNDS as u64 {
+            return None;
+        }
+        self.rounds
+            .get((round % consts::BEACON_ROUNDS as u64) as usize)
+    }
+
+    /// Randomness of the latest round, if published at most `max_age_slots` before
+    /// `current_slot`. The round may be known to anyone before the transaction reading it, see
+    /// `Beacon`
+    pub fn fresh_randomness(&self, current_slot: u64, max_age_slots: u64) -> Option<[u8; 32]> {
+        let round = self.latest_round()?;
+        (current_slot.saturating_sub(round.slot) <= max_age_slots).then_some(round.randomness)
+    }
+}
+
+impl BeaconRound {
+    /// VRF input of the round in the beacon of `oracle`
+    pub fn input(&self, oracle: &Pubkey) -> [u8; 32] {
+        hashv(&[
+            consts::VRF_PREFIX_BEACON,
+            oracle.as_ref(),
+            &self.slot.to_le_bytes(),
+            &self.slothash,
+        ])
+        .to_bytes()
+    }
+
+    /// Verifies the proof of the round, already verified by the VRF program when published
+    pub fn verify(&self, oracle: &Pubkey) -> bool {
+        hash(&self.output).to_bytes() == self.randomness
+            && verify_vrf(
+                &self.vrf_pubkey,
+                &self.input(oracle),
+                &self.output,
+                (
+                    &self.commitment_base_compressed,
+                    &self.commitment_hash_compressed,
+                    &self.scalar,
+                ),
+            )
+    }
+}
+
+/// Reads the randomness of the latest round of a beacon, checking that the account is the
+/// beacon of an oracle owned by the VRF program and that the round is at most `max_age_slots`
+/// old
+pub fn read_beacon_randomness(
+    key: &Pubkey,
+    owner: &Pubkey,
+    data: &[u8],
+    current_slot: u64,
+    max_age_slots: u64,
+) -> Option<[u8; 32]> {
+    Beacon::try_from_account(key, owner, data)?.fresh_randomness(current_slot, max_age_slots)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account(oracle: &Pubkey, round_count: u64) -> Vec<u8> {
+        let mut data = vec![0u8; LEN];
+        data[0] = DISCRIMINATOR;
+        let body = &mut data[HEADER_LEN..];
+        body[..32].copy_from_slice(oracle.as_ref());
+        body[32..40].copy_from_slice(&10u64.to_le_bytes());
+        body[40..48].copy_from_slice(&round_count.to_le_bytes());
+        for (index, round) in body[ROUNDS_OFFSET..]
+            .chunks_exact_mut(ROUND_LEN)
+            .enumerate()
+        {
+            round[..8].copy_from_slice(&(100 + index as u64).to_le_bytes());
+            round[40..72].copy_from_slice(&[index as u8; 32]);
+        }
+        data
+    }
+
+    #[test]
+    fn decodes_the_beacon_of_an_oracle() {
+        let oracle = Pubkey::new_unique();
+        let beacon = Beacon::try_from_account(
+            &beacon_pda(&oracle),
+            &consts::VRF_PROGRAM_ID,
+            &account(&oracle, 3),
+        )
+        .unwrap();
+        assert_eq!(beacon.oracle, oracle);
+        assert_eq!(beacon.interval_slots, 10);
+        assert_eq!(beacon.latest_round().unwrap().slot, 102);
+        assert_eq!(beacon.round(0).unwrap().randomness, [0; 32]);
+        assert_eq!(beacon.round(3), None);
+        assert_eq!(beacon.fresh_randomness(110, 8), Some([2; 32]));
+        assert_eq!(beacon.fresh_randomness(111, 8), None);
+    }
+
+    #[test]
+    fn overwritten_rounds_are_gone() {
+        let oracle = Pubkey::new_unique();
+        let round_count = consts::BEACON_ROUNDS as u64 + 1;
+        let beacon = Beacon::try_from_account(
+            &beacon_pda(&oracle),
+            &consts::VRF_PROGRAM_ID,
+            &account(&oracle, round_count),
+        )
+        .unwrap();
+        assert_eq!(beacon.round(0), None);
+        assert_eq!(beacon.latest_round().unwrap().slot, 100);
+        assert!(beacon.round(1).is_some());
+    }
+
+    #[test]
+    fn rejects_accounts_other_than_the_beacon_of_their_oracle() {
+        let oracle = Pubkey::new_unique();
+        let data = account(&oracle, 1);
+        let key = beacon_pda(&oracle);
+        assert!(Beacon::try_from_account(&key, &Pubkey::default(), &data).is_none());
+        assert!(Beacon::try_from_account(
+            &beacon_pda(&Pubkey::new_unique()),
+            &consts::VRF_PROGRAM_ID,
+            &data
+        )
+        .is_none());
+        assert!(
+            Beacon::try_from_account(&key, &consts::VRF_PROGRAM_ID, &data[..LEN - 1]).is_none()
+        );
+        let mut data = data;
+        data[0] = DISCRIMINATOR + 1;
+        assert!(Beacon::try_from_account(&key, &consts::VRF_PROGRAM_ID, &data).is_none());
+    }
+}
@@ -0,0 +1,62 @@
+use crate::compat::{ProgramError, Pubkey};
+use crate::consts::VRF_PROGRAM_IDENTITY;
+use crate::types::FulfillmentHeader;
+
+/// Reasons to reject a callback
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallbackError {
+    /// The callback is not signed by the VRF program identity
+    InvalidIdentity,
+    /// No request is pending, e.g. the callback is replayed
+    NoPendingRequest,
+    /// The callback answers another request than the pending one
+    RequestMismatch,
+}
+
+impl From<CallbackError> for ProgramError {
+    fn from(error: CallbackError) -> Self {
+        match error {
+            CallbackError::InvalidIdentity => ProgramError::MissingRequiredSignature,
+            CallbackError::NoPendingRequest | CallbackError::RequestMismatch => {
+                ProgramError::InvalidArgument
+            }
+        }
+    }
+}
+
+/// Checks that a callback is signed by the VRF program identity, its first account
+pub fn verify_identity(key: &Pubkey, is_signer: bool) -> Result<(), CallbackError> {
+    if !is_signer || key != &VRF_PROGRAM_IDENTITY {
+        return Err(CallbackError::InvalidIdentity);
+    }
+    Ok(())
+}
+
+/// Checks that a callback answers the pending request, whose id was stored by the consumer
+/// when making it (see `instructions::read_request_return_data`), and clears it so that the
+/// callback can't be replayed
+pub fn consume_pending_request(
+    pending_request_id: &mut [u8; 32],
+    header: &FulfillmentHeader,
+) -> Result<(), CallbackError> {
+    if *pending_request_id == [0; 32] {
+        return Err(CallbackError::NoPendingRequest);
+    }
+    if *pending_request_id != header.request_id {
+        return Err(CallbackError::RequestMismatch);
+    }
+    *pending_request_id = [0; 32];
+    Ok(())
+}
+
+/// Authenticates a callback of a request made with `types::CallbackAbi::Header`: checks the
+/// VRF program identity and consumes the pending request
+pub fn verify_callback(
+    identity: &Pubkey,
+    identity_is_signer: bool,
+    pending_request_id: &mut [u8; 32],
+    header: &FulfillmentHeader,
+) -> Result<(), CallbackError> {
+    verify_identity(identity, identity_is_signer)?;
+    consume_pending_request(pending_request_id, header)
+}
@@ -14,6 +14,8 @@ compile_error!("feature `anchor-modern` cannot be combined with `backward-compat
 mod backward_compat {
     pub use borsh_compat as borsh;
     pub use solana_program_compat::instruction::{AccountMeta, Instruction};
+    pub use solana_program_compat::program::get_return_data;
+    pub use solana_program_compat::program_error::ProgramError;
     #[allow(deprecated)]
     pub use solana_program_compat::system_program;
     pub use solana_program_compat::{pubkey, pubkey::Pubkey};
@@ -32,6 +34,10 @@ pub(crate) mod latest {
     pub use borsh_current as borsh;
     pub use solana_program::instruction::{AccountMeta, Instruction};
     #[cfg(not(feature = "backward-compat"))]
+    pub use solana_program::program::get_return_data;
+    #[cfg(not(feature = "backward-compat"))]
+    pub use solana_program::program_error::ProgramError;
+    #[cfg(not(feature = "backward-compat"))]
     pub use solana_program::pubkey;
     pub use solana_program::pubkey::Pubkey;
     pub use solana_system_interface::program as system_program;
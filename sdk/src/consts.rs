@@ -12,5 +12,103 @@ pub const DEFAULT_EPHEMERAL_QUEUE: Pubkey = pubkey!("5hBR571xnXppuCPveTrctfTU7tJ
 /// Vrf program identity PDA
 pub const VRF_PROGRAM_IDENTITY: Pubkey = pubkey!("9irBy75QS2BN81FUgXuHcjqceJJRuc9oDkAe8TKVvvAw");
 
+/// Base fee of a high priority randomness request, in lamports
+pub const VRF_HIGH_PRIORITY_LAMPORTS_COST: u64 = 800000;
+
+/// Base fee of a regular randomness request, in lamports
+pub const VRF_LAMPORTS_COST: u64 = 500000;
+
+/// Surcharge per callback account meta stored with a request, in lamports
+pub const VRF_LAMPORTS_COST_PER_ACCOUNT_META: u64 = 5000;
+
+/// Surcharge per callback argument byte stored with a request, in lamports
+pub const VRF_LAMPORTS_COST_PER_ARG_BYTE: u64 = 100;
+
 /// Seed of the identity PDA
 pub const IDENTITY: &[u8] = b"identity";
+
+/// Seed of the per-queue token fee config PDA
+pub const TOKEN_FEE_CONFIG: &[u8] = b"token-fee";
+
+/// Seed of the per-queue token fee escrow PDA
+pub const TOKEN_FEE_ESCROW: &[u8] = b"token-escrow";
+
+/// Seed of the sponsor PDA
+pub const SPONSOR: &[u8] = b"sponsor";
+
+/// Seed of the per-user request counter PDA of a sponsor
+pub const SPONSOR_USAGE: &[u8] = b"sponsor-usage";
+
+/// Seed of the admin PDA of the program
+pub const ADMIN: &[u8] = b"admin";
+
+/// Seed of the PDA listing the registered oracles
+pub const ORACLES: &[u8] = b"oracles";
+
+/// Seed of the data PDA of a registered oracle
+pub const ORACLE_DATA: &[u8] = b"oracle";
+
+/// Seed of the queues shared by the registered oracles
+pub const SHARED_QUEUE: &[u8] = b"shared-queue";
+
+/// Seed of the pull-mode randomness result PDA
+pub const RANDOMNESS_RESULT: &[u8] = b"result";
+
+/// Seed of the receipt PDA of a request
+pub const RECEIPT: &[u8] = b"receipt";
+
+/// Seed of the subscription PDA of a recurring request
+pub const SUBSCRIPTION: &[u8] = b"subscription";
+
+/// Seed of the randomness beacon PDA of an oracle
+pub const BEACON: &[u8] = b"beacon";
+
+/// Number of recent rounds kept by a randomness beacon
+pub const BEACON_ROUNDS: usize = 16;
+
+/// Prefix of the hash deriving the VRF input of a beacon round
+pub const VRF_PREFIX_BEACON: &[u8] = b"VRF-Ephem-Beacon";
+
+/// Seed of the PDA signed for by the VRF program when invoking a callback
+pub const CALLBACK_SIGNER: &[u8] = b"callback-signer";
+
+/// Maximum number of callback accounts of a request
+pub const MAX_CALLBACK_ACCOUNTS: usize = 20;
+
+/// Maximum number of callback accounts of a request naming an address lookup table
+pub const MAX_CALLBACK_ACCOUNTS_WITH_LOOKUP_TABLE: usize = 48;
+
+/// Slots after which an unfulfilled request expires, counted from its `not_before_slot` if later
+pub const QUEUE_TTL_SLOTS: u64 = 240;
+
+/// Seconds after its `not_before_timestamp` from which a scheduled request can expire
+pub const QUEUE_TTL_SECONDS: i64 = 120;
+
+/// Initial share of the TTL of a request, in basis points, after which it can be reassigned to
+/// the queue of another oracle, the current one is in the admin account
+pub const REASSIGN_AFTER_TTL_BPS: u64 = 5_000;
+
+/// Byte appended after the callback args of reassigned requests, whatever their callback ABI
+/// (see `types::split_reassigned_flag`)
+pub const REASSIGNED_CALLBACK_FLAG: u8 = 1;
+
+/// Maximum delay of a request's `not_before_slot`
+pub const MAX_REQUEST_DELAY_SLOTS: u64 = 1_512_000;
+
+/// Maximum delay of a request's `not_before_timestamp`, in seconds
+pub const MAX_REQUEST_DELAY_SECONDS: i64 = 604_800;
+
+/// Maximum number of random words delivered by a request
+pub const MAX_RANDOM_WORDS: u8 = 32;
+
+/// Prefix of the hash deriving each random word from the randomness
+pub const VRF_PREFIX_RANDOM_WORD: &[u8] = b"VRF-Ephem-RandomWord";
+
+/// Prefix of the hash computing the challenge of a VRF proof
+pub const VRF_PREFIX_CHALLENGE: &[u8] = b"VRF-Ephem-Challenge";
+
+/// Prefix of the hash mapping a VRF input to a point
+pub const VRF_PREFIX_HASH_TO_POINT: &[u8] = b"VRF-Ephem-HashToPoint";
+
+/// Prefix of the hash mapping bytes to a scalar
+pub const VRF_PREFIX_HASH_TO_SCALAR: &[u8] = b"VRF-Ephem-HashToScalar";
@@ -1,6 +1,15 @@
 use crate::compat::{self, Compat, Modern, Pubkey};
 use crate::consts;
-use crate::types::{RequestRandomness, SerializableAccountMeta};
+use crate::pda::{
+    admin_pda, ephemeral_balance_pda_from_payer, oracle_data_pda, oracles_pda,
+    randomness_result_pda, receipt_pda, sponsor_usage_pda, subscription_pda, token_fee_config_pda,
+    token_fee_escrow_pda,
+};
+use crate::types::{
+    CallbackAbi, CreateSubscription, FeePayment, PdaAccountMeta, RequestRandomness,
+    RequestReturnData, SerializableAccountMeta,
+};
+use solana_program::rent::Rent;
 
 /// Parameters for creating a request randomness instruction
 #[derive(Default)]
@@ -10,8 +19,57 @@ pub struct RequestRandomnessParams {
     pub callback_program_id: Pubkey,
     pub callback_discriminator: Vec<u8>,
     pub accounts_metas: Option<Vec<SerializableAccountMeta>>,
+    /// Callback accounts derived from seeds at fulfillment, passed after `accounts_metas`
+    pub pda_accounts_metas: Option<Vec<PdaAccountMeta>>,
+    /// Address lookup table holding the callback accounts, used by the oracle to send the
+    /// callback in a v0 transaction. Raises the callback accounts limit from
+    /// `consts::MAX_CALLBACK_ACCOUNTS` to `consts::MAX_CALLBACK_ACCOUNTS_WITH_LOOKUP_TABLE`
+    pub lookup_table: Option<Pubkey>,
     pub caller_seed: [u8; 32],
     pub callback_args: Option<Vec<u8>>,
+    /// Pay the request fee in the queue's fee mint instead of lamports
+    pub token_fee: Option<TokenFeePayment>,
+    /// Lamports paid to the oracle on top of the fee, higher tips are served first
+    pub tip: u64,
+    /// Index of the payer's ephemeral balance charged on the default ephemeral queue
+    pub ephemeral_balance_index: u8,
+    /// Sponsor paying the request fee, see `pda::sponsor_pda`
+    pub sponsor: Option<Pubkey>,
+    /// Write the randomness to the result PDA of `callback_program_id` and `caller_seed`
+    /// (see `pda::randomness_result_pda` and `result::read_randomness`) instead of invoking
+    /// a callback. `callback_program_id` may be the payer itself.
+    pub pull: bool,
+    /// Number of random words passed to the callback in place of the randomness, as a
+    /// `[[u8; 32]; num_words]` array (see `rnd::random_words` and `rnd::decode_random_words`).
+    /// At most `consts::MAX_RANDOM_WORDS`, 0 and 1 pass the randomness.
+    pub num_words: u8,
+    /// Layout of the callback data, `CallbackAbi::Header` passes a `types::FulfillmentHeader`
+    /// before the random words
+    pub callback_abi: CallbackAbi,
+    /// Slot from which the request can be fulfilled, 0 to fulfill it as soon as possible.
+    /// At most `consts::MAX_REQUEST_DELAY_SLOTS` ahead, the request expires
+    /// `consts::QUEUE_TTL_SLOTS` after it
+    pub not_before_slot: u64,
+    /// Unix timestamp from which the request can be fulfilled, 0 to fulfill it as soon as
+    /// possible. At most `consts::MAX_REQUEST_DELAY_SECONDS` ahead, the request expires
+    /// `consts::QUEUE_TTL_SECONDS` after it
+    pub not_before_timestamp: i64,
+    /// Create the receipt PDA of the request (see `pda::receipt_pda` and `receipt::Receipt`),
+    /// paid by the payer and tracking the outcome of the request. The receipt of a previous
+    /// request with the same `caller_seed` must be closed first
+    pub receipt: bool,
+    /// Let the oracle record the randomness in the result PDA of `callback_program_id` and
+    /// `caller_seed`, in place of a callback that keeps failing. The result PDA is created by
+    /// the request and paid by the payer, who can close it once fulfilled or expired (see
+    /// `create_close_randomness_result_ix`)
+    pub fallback: bool,
+}
+
+/// Token accounts used to pay the request fee in the queue's fee mint
+pub struct TokenFeePayment {
+    pub payer_token_account: Pubkey,
+    pub mint: Pubkey,
+    pub token_program: Pubkey,
 }
 
 pub fn create_request_randomness_ix(params: RequestRandomnessParams) -> compat::Instruction {
@@ -21,21 +79,86 @@ pub fn create_request_randomness_ix(params: RequestRandomnessParams) -> compat::
     let program_identity =
         compat::latest::Pubkey::find_program_address(&[consts::IDENTITY], &callback_program_id).0;
 
+    // Pull-mode requests made by the requester itself are not signed by a program identity
+    let identity_signs = !params.pull || params.callback_program_id != params.payer;
+
+    let mut accounts = vec![
+        compat::latest::AccountMeta::new(payer, true),
+        compat::latest::AccountMeta::new_readonly(program_identity, identity_signs),
+        compat::latest::AccountMeta::new(oracle_queue, false),
+        compat::latest::AccountMeta::new_readonly(compat::latest::system_program::ID, false),
+        compat::latest::AccountMeta::new_readonly(compat::latest::slot_hashes::ID, false),
+    ];
+    let fee_payment = match (params.sponsor, &params.token_fee) {
+        _ if params.oracle_queue == consts::DEFAULT_EPHEMERAL_QUEUE => {
+            accounts.push(compat::latest::AccountMeta::new(
+                ephemeral_balance_pda_from_payer(&params.payer, params.ephemeral_balance_index)
+                    .modern(),
+                false,
+            ));
+            FeePayment::EphemeralBalance
+        }
+        (Some(sponsor), _) => {
+            accounts.extend([
+                compat::latest::AccountMeta::new(sponsor.modern(), false),
+                compat::latest::AccountMeta::new(
+                    sponsor_usage_pda(&sponsor, &params.payer).modern(),
+                    false,
+                ),
+            ]);
+            FeePayment::Sponsor
+        }
+        (None, Some(token_fee)) => {
+            accounts.extend([
+                compat::latest::AccountMeta::new_readonly(
+                    token_fee_config_pda(&params.oracle_queue).modern(),
+                    false,
+                ),
+                compat::latest::AccountMeta::new(
+                    token_fee_escrow_pda(&params.oracle_queue).modern(),
+                    false,
+                ),
+                compat::latest::AccountMeta::new(token_fee.payer_token_account.modern(), false),
+                compat::latest::AccountMeta::new_readonly(token_fee.mint.modern(), false),
+                compat::latest::AccountMeta::new_readonly(token_fee.token_program.modern(), false),
+            ]);
+            FeePayment::Token
+        }
+        (None, None) => FeePayment::Lamports,
+    };
+    if params.pull || params.fallback {
+        accounts.push(compat::latest::AccountMeta::new(
+            randomness_result_pda(&params.callback_program_id, &params.caller_seed).modern(),
+            false,
+        ));
+    }
+    if params.receipt {
+        accounts.push(compat::latest::AccountMeta::new(
+            receipt_pda(&params.callback_program_id, &params.caller_seed).modern(),
+            false,
+        ));
+    }
+
     compat::latest::Instruction {
         program_id: consts::VRF_PROGRAM_ID.modern(),
-        accounts: vec![
-            compat::latest::AccountMeta::new(payer, true),
-            compat::latest::AccountMeta::new_readonly(program_identity, true),
-            compat::latest::AccountMeta::new(oracle_queue, false),
-            compat::latest::AccountMeta::new_readonly(compat::latest::system_program::ID, false),
-            compat::latest::AccountMeta::new_readonly(compat::latest::slot_hashes::ID, false),
-        ],
+        accounts,
         data: RequestRandomness {
             caller_seed: params.caller_seed,
             callback_program_id: params.callback_program_id,
             callback_discriminator: params.callback_discriminator,
             callback_accounts_metas: params.accounts_metas.unwrap_or_default(),
             callback_args: params.callback_args.unwrap_or_default(),
+            fee_payment,
+            tip: params.tip,
+            pull: params.pull,
+            callback_pda_metas: params.pda_accounts_metas.unwrap_or_default(),
+            lookup_table: params.lookup_table,
+            num_words: params.num_words,
+            callback_abi: params.callback_abi,
+            not_before_slot: params.not_before_slot,
+            not_before_timestamp: params.not_before_timestamp,
+            receipt: params.receipt,
+            fallback: params.fallback,
         }
         .to_bytes(),
     }
@@ -49,3 +172,245 @@ pub fn create_request_regular_randomness_ix(
     ix.data[0] = 8;
     ix
 }
+
+/// Parameters for creating a recurring randomness request, whose rounds are enqueued by the
+/// oracle of the queue every `interval_slots`, without a keeper sending requests
+#[derive(Default)]
+pub struct CreateSubscriptionParams {
+    /// Pays for the subscription and its budget, can cancel it
+    pub payer: Pubkey,
+    pub oracle_queue: Pubkey,
+    pub callback_program_id: Pubkey,
+    pub callback_discriminator: Vec<u8>,
+    pub accounts_metas: Option<Vec<SerializableAccountMeta>>,
+    /// Callback accounts derived from seeds at each fulfillment, passed after `accounts_metas`
+    pub pda_accounts_metas: Option<Vec<PdaAccountMeta>>,
+    /// Seed of the subscription PDA, see `pda::subscription_pda`
+    pub caller_seed: [u8; 32],
+    pub callback_args: Option<Vec<u8>>,
+    pub num_words: u8,
+    pub callback_abi: CallbackAbi,
+    /// Slots between two rounds
+    pub interval_slots: u64,
+    /// Slot of the first round, 0 for the current slot
+    pub first_round_slot: u64,
+    /// Lamports transferred to the subscription to pay the fees of the rounds. Each round costs
+    /// the fee of a regular request, the subscription can be topped up with plain transfers
+    pub budget: u64,
+}
+
+pub fn create_subscription_ix(params: CreateSubscriptionParams) -> compat::Instruction {
+    let callback_program_id = params.callback_program_id.modern();
+    let program_identity =
+        compat::latest::Pubkey::find_program_address(&[consts::IDENTITY], &callback_program_id).0;
+
+    compat::latest::Instruction {
+        program_id: consts::VRF_PROGRAM_ID.modern(),
+        accounts: vec![
+            compat::latest::AccountMeta::new(params.payer.modern(), true),
+            compat::latest::AccountMeta::new_readonly(program_identity, true),
+            compat::latest::AccountMeta::new(
+                subscription_pda(&params.callback_program_id, &params.caller_seed).modern(),
+                false,
+            ),
+            compat::latest::AccountMeta::new_readonly(params.oracle_queue.modern(), false),
+            compat::latest::AccountMeta::new_readonly(compat::latest::system_program::ID, false),
+        ],
+        data: CreateSubscription {
+            caller_seed: params.caller_seed,
+            callback_program_id: params.callback_program_id,
+            callback_discriminator: params.callback_discriminator,
+            callback_accounts_metas: params.accounts_metas.unwrap_or_default(),
+            callback_args: params.callback_args.unwrap_or_default(),
+            callback_pda_metas: params.pda_accounts_metas.unwrap_or_default(),
+            num_words: params.num_words,
+            callback_abi: params.callback_abi,
+            interval_slots: params.interval_slots,
+            first_round_slot: params.first_round_slot,
+            budget: params.budget,
+        }
+        .to_bytes(),
+    }
+    .compat()
+}
+
+/// Cancels a subscription, returning its lamports to `authority`, the payer of its creation
+pub fn create_cancel_subscription_ix(
+    authority: Pubkey,
+    subscription: Pubkey,
+) -> compat::Instruction {
+    compat::latest::Instruction {
+        program_id: consts::VRF_PROGRAM_ID.modern(),
+        accounts: vec![
+            compat::latest::AccountMeta::new(authority.modern(), true),
+            compat::latest::AccountMeta::new(subscription.modern(), false),
+        ],
+        data: vec![22, 0, 0, 0, 0, 0, 0, 0],
+    }
+    .compat()
+}
+
+/// Closes the receipt of a request no longer pending in `oracle_queue`, returning its lamports
+/// to `payer`, the payer of the request
+pub fn create_close_receipt_ix(
+    payer: Pubkey,
+    receipt: Pubkey,
+    oracle_queue: Pubkey,
+) -> compat::Instruction {
+    compat::latest::Instruction {
+        program_id: consts::VRF_PROGRAM_ID.modern(),
+        accounts: vec![
+            compat::latest::AccountMeta::new(payer.modern(), true),
+            compat::latest::AccountMeta::new(receipt.modern(), false),
+            compat::latest::AccountMeta::new_readonly(oracle_queue.modern(), false),
+        ],
+        data: vec![26, 0, 0, 0, 0, 0, 0, 0],
+    }
+    .compat()
+}
+
+/// Closes the result PDA of `callback_program_id` and `caller_seed` once its latest request is
+/// fulfilled or expired, returning its lamports to `payer`, the payer of its creation
+pub fn create_close_randomness_result_ix(
+    payer: Pubkey,
+    callback_program_id: Pubkey,
+    caller_seed: [u8; 32],
+) -> compat::Instruction {
+    compat::latest::Instruction {
+        program_id: consts::VRF_PROGRAM_ID.modern(),
+        accounts: vec![
+            compat::latest::AccountMeta::new(payer.modern(), true),
+            compat::latest::AccountMeta::new(
+                randomness_result_pda(&callback_program_id, &caller_seed).modern(),
+                false,
+            ),
+        ],
+        data: vec![34, 0, 0, 0, 0, 0, 0, 0],
+    }
+    .compat()
+}
+
+/// Moves a request left pending in `source_queue` of `source_oracle` to `target_queue` of
+/// `target_oracle`, any other oracle registered in `oracles_pda`, once the reassign delay set in
+/// `admin_pda` passed (`REASSIGN_AFTER_TTL_BPS` of its TTL initially). Requests paid in tokens
+/// need the token fee accounts of the source queue, with the escrow of the target queue as
+/// token account, and the token fee config of the target queue appended, and requests created
+/// with a receipt their receipt PDA last
+pub fn create_reassign_request_ix(
+    source_oracle: Pubkey,
+    source_queue: Pubkey,
+    target_oracle: Pubkey,
+    target_queue: Pubkey,
+    request_id: [u8; 32],
+) -> compat::Instruction {
+    let target_oracle_data = oracle_data_pda(&target_oracle);
+    let mut data = vec![28, 0, 0, 0, 0, 0, 0, 0];
+    data.extend_from_slice(&request_id);
+    compat::latest::Instruction {
+        program_id: consts::VRF_PROGRAM_ID.modern(),
+        accounts: vec![
+            compat::latest::AccountMeta::new_readonly(source_oracle.modern(), false),
+            compat::latest::AccountMeta::new(source_queue.modern(), false),
+            compat::latest::AccountMeta::new_readonly(target_oracle.modern(), false),
+            compat::latest::AccountMeta::new_readonly(target_oracle_data.modern(), false),
+            compat::latest::AccountMeta::new(target_queue.modern(), false),
+            compat::latest::AccountMeta::new_readonly(oracles_pda().modern(), false),
+            compat::latest::AccountMeta::new_readonly(admin_pda().modern(), false),
+        ],
+        data,
+    }
+    .compat()
+}
+
+/// Reads the id and queue of the request made by the last invoked instruction, to be called
+/// right after invoking a request instruction
+pub fn read_request_return_data() -> Option<RequestReturnData> {
+    let (program_id, data) = compat::get_return_data()?;
+    if program_id != consts::VRF_PROGRAM_ID {
+        return None;
+    }
+    RequestReturnData::decode(&data)
+}
+
+/// Lamports a request costs when sent, see `quote_request_randomness`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RequestQuote {
+    /// Request fee escrowed in lamports, 0 when paid in tokens or by a sponsor
+    pub fee: u64,
+    /// Tip escrowed for the oracle, 0 when paid by a sponsor
+    pub tip: u64,
+    /// Rent of the result and receipt PDAs created by the request and paid by the payer,
+    /// returned when they are closed
+    pub rent: u64,
+}
+
+impl RequestQuote {
+    pub fn total(&self) -> u64 {
+        self.fee.saturating_add(self.tip).saturating_add(self.rent)
+    }
+}
+
+/// Lamports charged when sending a request built from `params`: the fee (unless paid in tokens)
+/// and the tip, escrowed until fulfillment, plus the rent of the PDAs created by the request.
+/// On the default ephemeral queue the fee and tip are debited from the payer's ephemeral
+/// balance, sponsored requests only cost the payer the rent.
+/// The rent of the result PDA is only paid by the first request using it (pull mode or
+/// `fallback`), the quote assumes it does not exist yet.
+/// `high_priority` selects `create_request_randomness_ix` over `create_request_regular_randomness_ix`.
+pub fn quote_request_randomness(
+    params: &RequestRandomnessParams,
+    high_priority: bool,
+) -> RequestQuote {
+    let rent = Rent::default();
+    let mut quote = RequestQuote::default();
+    if params.pull || params.fallback {
+        quote.rent = quote
+            .rent
+            .saturating_add(rent.minimum_balance(crate::result::LEN));
+    }
+    if params.receipt {
+        quote.rent = quote
+            .rent
+            .saturating_add(rent.minimum_balance(crate::receipt::LEN));
+    }
+    let is_ephemeral = params.oracle_queue == consts::DEFAULT_EPHEMERAL_QUEUE;
+    if !is_ephemeral && params.sponsor.is_some() {
+        return quote;
+    }
+    quote.tip = params.tip;
+    if !is_ephemeral && params.token_fee.is_some() {
+        return quote;
+    }
+    let base = if high_priority {
+        consts::VRF_HIGH_PRIORITY_LAMPORTS_COST
+    } else {
+        consts::VRF_LAMPORTS_COST
+    };
+    let metas_len = (params.accounts_metas.as_ref().map_or(0, Vec::len)
+        + params.pda_accounts_metas.as_ref().map_or(0, Vec::len)) as u64;
+    // The seeds of the callback PDA metas are charged like callback args
+    let seeds_len = params
+        .pda_accounts_metas
+        .iter()
+        .flatten()
+        .flat_map(|meta| meta.seeds.iter())
+        .map(Vec::len);
+    let data_len = params
+        .callback_args
+        .as_ref()
+        .map_or(0, Vec::len)
+        .saturating_add(seeds_len.sum()) as u64;
+    quote.fee = base
+        .saturating_add(consts::VRF_LAMPORTS_COST_PER_ACCOUNT_META.saturating_mul(metas_len))
+        .saturating_add(consts::VRF_LAMPORTS_COST_PER_ARG_BYTE.saturating_mul(data_len));
+    quote
+}
+
+/// Total lamports charged when sending a request built from `params`, see
+/// `quote_request_randomness` for its breakdown.
+pub fn quote_request_randomness_lamports(
+    params: &RequestRandomnessParams,
+    high_priority: bool,
+) -> u64 {
+    quote_request_randomness(params, high_priority).total()
+}
@@ -3,12 +3,21 @@ extern crate borsh_compat as borsh;
 
 #[cfg(feature = "anchor-support")]
 pub mod anchor;
+pub mod beacon;
+pub mod callback;
+/// Discovery of the registered oracles and the health of their queues, with
+/// `client::pick_best_queue` to choose the queue to send requests to.
+#[cfg(feature = "client")]
+pub use ephemeral_vrf_api::client;
 pub mod compat;
 pub mod consts;
 pub mod instructions;
 pub mod pda;
+pub mod receipt;
+pub mod result;
 pub mod rnd;
 pub mod types;
+pub mod verify;
 
 pub use compat::Pubkey;
 
@@ -170,3 +170,150 @@ pub fn ephemeral_balance_pda_from_payer(payer: &Pubkey, index: u8) -> Pubkey {
     .0
     .compat()
 }
+
+/// Registered oracles of the program, the oracles requests can be reassigned to
+pub fn oracles_pda() -> Pubkey {
+    crate::compat::latest::Pubkey::find_program_address(
+        &[crate::consts::ORACLES],
+        &crate::id().modern(),
+    )
+    .0
+    .compat()
+}
+
+/// Admin of the program, holding the delay after which requests can be reassigned
+pub fn admin_pda() -> Pubkey {
+    crate::compat::latest::Pubkey::find_program_address(
+        &[crate::consts::ADMIN],
+        &crate::id().modern(),
+    )
+    .0
+    .compat()
+}
+
+/// Data of the registered oracle `identity`
+pub fn oracle_data_pda(identity: &Pubkey) -> Pubkey {
+    crate::compat::latest::Pubkey::find_program_address(
+        &[crate::consts::ORACLE_DATA, identity.as_ref()],
+        &crate::id().modern(),
+    )
+    .0
+    .compat()
+}
+
+/// Queue whose requests can be fulfilled by any registered oracle, to pass as `oracle_queue`
+pub fn shared_queue_pda(index: u8) -> Pubkey {
+    crate::compat::latest::Pubkey::find_program_address(
+        &[crate::consts::SHARED_QUEUE, &[index]],
+        &crate::id().modern(),
+    )
+    .0
+    .compat()
+}
+
+pub fn token_fee_config_pda(oracle_queue: &Pubkey) -> Pubkey {
+    crate::compat::latest::Pubkey::find_program_address(
+        &[crate::consts::TOKEN_FEE_CONFIG, oracle_queue.as_ref()],
+        &crate::id().modern(),
+    )
+    .0
+    .compat()
+}
+
+pub fn token_fee_escrow_pda(oracle_queue: &Pubkey) -> Pubkey {
+    crate::compat::latest::Pubkey::find_program_address(
+        &[crate::consts::TOKEN_FEE_ESCROW, oracle_queue.as_ref()],
+        &crate::id().modern(),
+    )
+    .0
+    .compat()
+}
+
+pub fn sponsor_pda(authority: &Pubkey, index: u8) -> Pubkey {
+    crate::compat::latest::Pubkey::find_program_address(
+        &[crate::consts::SPONSOR, authority.as_ref(), &[index]],
+        &crate::id().modern(),
+    )
+    .0
+    .compat()
+}
+
+pub fn sponsor_usage_pda(sponsor: &Pubkey, user: &Pubkey) -> Pubkey {
+    crate::compat::latest::Pubkey::find_program_address(
+        &[
+            crate::consts::SPONSOR_USAGE,
+            sponsor.as_ref(),
+            user.as_ref(),
+        ],
+        &crate::id().modern(),
+    )
+    .0
+    .compat()
+}
+
+pub fn randomness_result_pda(requester: &Pubkey, caller_seed: &[u8; 32]) -> Pubkey {
+    crate::compat::latest::Pubkey::find_program_address(
+        &[
+            crate::consts::RANDOMNESS_RESULT,
+            requester.as_ref(),
+            caller_seed,
+        ],
+        &crate::id().modern(),
+    )
+    .0
+    .compat()
+}
+
+/// Randomness beacon published by `oracle`
+pub fn beacon_pda(oracle: &Pubkey) -> Pubkey {
+    crate::compat::latest::Pubkey::find_program_address(
+        &[crate::consts::BEACON, oracle.as_ref()],
+        &crate::id().modern(),
+    )
+    .0
+    .compat()
+}
+
+/// Receipt of the request of `callback_program_id` with `caller_seed`, see `receipt::Receipt`.
+/// Requests with the same caller seed share it, one at a time
+pub fn receipt_pda(callback_program_id: &Pubkey, caller_seed: &[u8; 32]) -> Pubkey {
+    crate::compat::latest::Pubkey::find_program_address(
+        &[
+            crate::consts::RECEIPT,
+            callback_program_id.as_ref(),
+            caller_seed,
+        ],
+        &crate::id().modern(),
+    )
+    .0
+    .compat()
+}
+
+/// Subscription of the recurring request of `callback_program_id` with `caller_seed`
+pub fn subscription_pda(callback_program_id: &Pubkey, caller_seed: &[u8; 32]) -> Pubkey {
+    crate::compat::latest::Pubkey::find_program_address(
+        &[
+            crate::consts::SUBSCRIPTION,
+            callback_program_id.as_ref(),
+            caller_seed,
+        ],
+        &crate::id().modern(),
+    )
+    .0
+    .compat()
+}
+
+/// PDA signed for by the VRF program when invoking the callback of a request made with
+/// `caller_seed`. It is the only callback account that can be marked as a signer.
+pub fn callback_signer_pda(callback_program_id: &Pubkey, caller_seed: &[u8; 32]) -> Pubkey {
+    crate::compat::latest::Pubkey::find_program_address(
+        &[
+            crate::consts::CALLBACK_SIGNER,
+            callback_program_id.as_ref(),
+            caller_seed,
+        ],
+        &crate::id().modern(),
+    )
+    .0
+    .compat()
+}
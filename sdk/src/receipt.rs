@@ -0,0 +1,93 @@
+use crate::compat::Pubkey;
+use crate::consts;
+
+const DISCRIMINATOR: u8 = 11;
+const HEADER_LEN: usize = 8;
+pub(crate) const LEN: usize = HEADER_LEN + 248;
+
+/// Status of a request tracked by a receipt
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReceiptStatus {
+    /// The request is waiting in its queue
+    Pending,
+    /// The randomness was delivered, to the callback or to the result PDA
+    Fulfilled,
+    /// The request outlived its TTL and was purged from its queue
+    Expired,
+}
+
+/// Outcome of a request created with a receipt (see `pda::receipt_pda`). The receipt PDA is
+/// shared by the requests with the same caller seed, one at a time: close the receipt before
+/// requesting another one with the same seed, or use a new caller seed.
+///
+/// Clients track a request by polling or subscribing to its receipt account and decoding it
+/// with `Receipt::try_from_account` until it is no longer pending, then close it with
+/// `instructions::create_close_receipt_ix`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Receipt {
+    /// Payer of the request, allowed to close the receipt
+    pub payer: Pubkey,
+    pub queue: Pubkey,
+    pub request_id: [u8; 32],
+    pub callback_program_id: Pubkey,
+    pub caller_seed: [u8; 32],
+    /// Hash of the VRF output, zero unless fulfilled
+    pub randomness: [u8; 32],
+    /// Oracle which fulfilled or purged the request
+    pub oracle: Pubkey,
+    /// Slot in which the request was made
+    pub request_slot: u64,
+    /// Slot in which the request was fulfilled or purged
+    pub completion_slot: u64,
+    pub status: ReceiptStatus,
+}
+
+impl Receipt {
+    /// Decodes a receipt account, returning `None` unless it is owned by the VRF program
+    pub fn try_from_account(owner: &Pubkey, data: &[u8]) -> Option<Self> {
+        if owner != &consts::VRF_PROGRAM_ID || data.len() < LEN || data[0] != DISCRIMINATOR {
+            return None;
+        }
+        let data = &data[HEADER_LEN..LEN];
+        let bytes32 = |offset: usize| -> [u8; 32] {
+            data[offset..offset + 32]
+                .try_into()
+                .expect("slice of 32 bytes")
+        };
+        let u64_at = |offset: usize| -> u64 {
+            u64::from_le_bytes(
+                data[offset..offset + 8]
+                    .try_into()
+                    .expect("slice of 8 bytes"),
+            )
+        };
+        let status = match data[240] {
+            0 => ReceiptStatus::Pending,
+            1 => ReceiptStatus::Fulfilled,
+            2 => ReceiptStatus::Expired,
+            _ => return None,
+        };
+        Some(Self {
+            payer: Pubkey::new_from_array(bytes32(0)),
+            queue: Pubkey::new_from_array(bytes32(32)),
+            request_id: bytes32(64),
+            callback_program_id: Pubkey::new_from_array(bytes32(96)),
+            caller_seed: bytes32(128),
+            randomness: bytes32(160),
+            oracle: Pubkey::new_from_array(bytes32(192)),
+            request_slot: u64_at(224),
+            completion_slot: u64_at(232),
+            status,
+        })
+    }
+
+    /// Whether the request is still waiting in its queue
+    pub fn is_pending(&self) -> bool {
+        self.status == ReceiptStatus::Pending
+    }
+
+    /// Randomness of the request, once fulfilled
+    pub fn randomness(&self) -> Option<[u8; 32]> {
+        (self.status == ReceiptStatus::Fulfilled).then_some(self.randomness)
+    }
+}
@@ -0,0 +1,142 @@
+use crate::compat::Pubkey;
+use crate::consts;
+
+const DISCRIMINATOR: u8 = 8;
+const HEADER_LEN: usize = 8;
+pub(crate) const LEN: usize = HEADER_LEN + 360;
+
+/// Randomness written by the oracle to the result PDA of a pull-mode request, or of a request
+/// opting into the fallback whose callback kept failing (see `pda::randomness_result_pda`).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RandomnessResult {
+    pub requester: Pubkey,
+    pub caller_seed: [u8; 32],
+    /// Id of the latest request using this result PDA
+    pub request_id: [u8; 32],
+    /// Hash of the VRF output, the value passed to callbacks
+    pub randomness: [u8; 32],
+    pub output: [u8; 32],
+    pub commitment_base_compressed: [u8; 32],
+    pub commitment_hash_compressed: [u8; 32],
+    pub scalar: [u8; 32],
+    /// Oracle which fulfilled the request
+    pub oracle: Pubkey,
+    /// Slot in which the request was fulfilled
+    pub slot: u64,
+    pub fulfilled: bool,
+    /// Slot in which the latest request was made
+    pub request_slot: u64,
+    /// Schedule of the latest request, a new request is rejected until the latest one is
+    /// fulfilled or expired
+    pub not_before_slot: u64,
+    pub not_before_timestamp: i64,
+    /// Payer of the account, refunded when it is closed (see
+    /// `instructions::create_close_randomness_result_ix`)
+    pub payer: Pubkey,
+}
+
+impl RandomnessResult {
+    /// Decodes a result account, returning `None` unless it is owned by the VRF program
+    pub fn try_from_account(owner: &Pubkey, data: &[u8]) -> Option<Self> {
+        if owner != &consts::VRF_PROGRAM_ID || data.len() < LEN || data[0] != DISCRIMINATOR {
+            return None;
+        }
+        let data = &data[HEADER_LEN..LEN];
+        let bytes32 = |offset: usize| -> [u8; 32] {
+            data[offset..offset + 32]
+                .try_into()
+                .expect("slice of 32 bytes")
+        };
+        let bytes8 = |offset: usize| -> [u8; 8] {
+            data[offset..offset + 8]
+                .try_into()
+                .expect("slice of 8 bytes")
+        };
+        Some(Self {
+            requester: Pubkey::new_from_array(bytes32(0)),
+            caller_seed: bytes32(32),
+            request_id: bytes32(64),
+            randomness: bytes32(96),
+            output: bytes32(128),
+            commitment_base_compressed: bytes32(160),
+            commitment_hash_compressed: bytes32(192),
+            scalar: bytes32(224),
+            oracle: Pubkey::new_from_array(bytes32(256)),
+            slot: u64::from_le_bytes(bytes8(288)),
+            fulfilled: data[296] != 0,
+            request_slot: u64::from_le_bytes(bytes8(304)),
+            not_before_slot: u64::from_le_bytes(bytes8(312)),
+            not_before_timestamp: i64::from_le_bytes(bytes8(320)),
+            payer: Pubkey::new_from_array(bytes32(328)),
+        })
+    }
+
+    /// Randomness of the latest request, once fulfilled
+    pub fn randomness(&self) -> Option<[u8; 32]> {
+        self.fulfilled.then_some(self.randomness)
+    }
+}
+
+/// Reads the randomness of a fulfilled pull-mode request from its result account,
+/// checking that the account is owned by the VRF program
+pub fn read_randomness(owner: &Pubkey, data: &[u8]) -> Option<[u8; 32]> {
+    RandomnessResult::try_from_account(owner, data)?.randomness()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account(fulfilled: bool) -> Vec<u8> {
+        let mut data = vec![0u8; LEN];
+        data[0] = DISCRIMINATOR;
+        let body = &mut data[HEADER_LEN..];
+        body[..32].copy_from_slice(&[1; 32]);
+        body[64..96].copy_from_slice(&[2; 32]);
+        body[96..128].copy_from_slice(&[3; 32]);
+        body[288..296].copy_from_slice(&9u64.to_le_bytes());
+        body[296] = fulfilled as u8;
+        body[304..312].copy_from_slice(&7u64.to_le_bytes());
+        body[312..320].copy_from_slice(&8u64.to_le_bytes());
+        body[320..328].copy_from_slice(&(-1i64).to_le_bytes());
+        body[328..360].copy_from_slice(&[4; 32]);
+        data
+    }
+
+    #[test]
+    fn decodes_a_fulfilled_result() {
+        let result =
+            RandomnessResult::try_from_account(&consts::VRF_PROGRAM_ID, &account(true)).unwrap();
+        assert_eq!(result.requester, Pubkey::new_from_array([1; 32]));
+        assert_eq!(result.request_id, [2; 32]);
+        assert_eq!(result.slot, 9);
+        assert_eq!(result.request_slot, 7);
+        assert_eq!(result.not_before_slot, 8);
+        assert_eq!(result.not_before_timestamp, -1);
+        assert_eq!(result.payer, Pubkey::new_from_array([4; 32]));
+        assert_eq!(
+            read_randomness(&consts::VRF_PROGRAM_ID, &account(true)),
+            Some([3; 32])
+        );
+    }
+
+    #[test]
+    fn pending_result_has_no_randomness() {
+        assert_eq!(
+            read_randomness(&consts::VRF_PROGRAM_ID, &account(false)),
+            None
+        );
+    }
+
+    #[test]
+    fn rejects_foreign_or_short_accounts() {
+        let data = account(true);
+        assert!(RandomnessResult::try_from_account(&Pubkey::default(), &data).is_none());
+        assert!(
+            RandomnessResult::try_from_account(&consts::VRF_PROGRAM_ID, &data[..LEN - 1]).is_none()
+        );
+        let mut data = data;
+        data[0] = DISCRIMINATOR + 1;
+        assert!(RandomnessResult::try_from_account(&consts::VRF_PROGRAM_ID, &data).is_none());
+    }
+}
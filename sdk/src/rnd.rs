@@ -1,3 +1,6 @@
+use crate::consts::VRF_PREFIX_RANDOM_WORD;
+use solana_program::hash::hashv;
+
 /// Generates a random u8 value from a 32-byte random seed
 ///
 /// # Arguments
@@ -109,3 +112,89 @@ pub fn random_i64(bytes: &[u8; 32]) -> i64 {
 pub fn random_bool(bytes: &[u8; 32]) -> bool {
     (bytes[31] % 2) == 0
 }
+
+/// Derives the random words of a request from its randomness, as passed to its callback
+///
+/// # Arguments
+///
+/// * `randomness` - The 32-byte randomness of the request, e.g. read from its result PDA
+/// * `num_words` - The number of words of the request
+///
+/// # Returns
+///
+/// The randomness itself for a single word, otherwise each word hashed from the randomness
+/// and its index
+pub fn random_words(randomness: &[u8; 32], num_words: u8) -> Vec<[u8; 32]> {
+    if num_words <= 1 {
+        return vec![*randomness];
+    }
+    (0..num_words)
+        .map(|index| hashv(&[VRF_PREFIX_RANDOM_WORD, randomness, &[index]]).to_bytes())
+        .collect()
+}
+
+/// Decodes the random words at the start of callback data, after its discriminator
+///
+/// # Arguments
+///
+/// * `data` - The callback instruction data without its discriminator
+/// * `num_words` - The number of words of the request
+///
+/// # Returns
+///
+/// The random words and the remaining callback arguments, or `None` if the data is too short
+pub fn decode_random_words(data: &[u8], num_words: u8) -> Option<(Vec<[u8; 32]>, &[u8])> {
+    let words_len = 32 * num_words.max(1) as usize;
+    if data.len() < words_len {
+        return None;
+    }
+    let (words, args) = data.split_at(words_len);
+    let words = words
+        .chunks_exact(32)
+        .map(|word| word.try_into().expect("chunk of 32 bytes"))
+        .collect();
+    Some((words, args))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn single_word_is_the_randomness() {
+        assert_eq!(random_words(&[5; 32], 0), vec![[5; 32]]);
+        assert_eq!(random_words(&[5; 32], 1), vec![[5; 32]]);
+    }
+
+    #[test]
+    fn words_are_hashed_from_the_randomness_and_their_index() {
+        let words = random_words(&[5; 32], 3);
+        assert_eq!(words.len(), 3);
+        assert_eq!(
+            words[2],
+            hashv(&[VRF_PREFIX_RANDOM_WORD, &[5; 32], &[2]]).to_bytes()
+        );
+    }
+
+    #[test]
+    fn decodes_the_words_before_the_args() {
+        let words = random_words(&[5; 32], 2);
+        let data = [words.concat(), vec![7, 8]].concat();
+        assert_eq!(decode_random_words(&data, 2), Some((words, &[7, 8][..])));
+    }
+
+    #[test]
+    fn decodes_a_single_word_when_none_requested() {
+        let data = [[5; 32].as_slice(), &[7]].concat();
+        assert_eq!(
+            decode_random_words(&data, 0),
+            Some((vec![[5; 32]], &[7][..]))
+        );
+    }
+
+    #[test]
+    fn short_data_has_no_words() {
+        assert_eq!(decode_random_words(&[0; 63], 2), None);
+        assert_eq!(decode_random_words(&[], 0), None);
+    }
+}
@@ -12,6 +12,17 @@ pub struct RequestRandomness {
     pub callback_discriminator: Vec<u8>,
     pub callback_accounts_metas: Vec<SerializableAccountMeta>,
     pub callback_args: Vec<u8>,
+    pub fee_payment: FeePayment,
+    pub tip: u64,
+    pub pull: bool,
+    pub callback_pda_metas: Vec<PdaAccountMeta>,
+    pub lookup_table: Option<compat::Pubkey>,
+    pub num_words: u8,
+    pub callback_abi: CallbackAbi,
+    pub not_before_slot: u64,
+    pub not_before_timestamp: i64,
+    pub receipt: bool,
+    pub fallback: bool,
 }
 
 impl RequestRandomness {
@@ -22,6 +33,176 @@ impl RequestRandomness {
     }
 }
 
+/// Creation of a recurring request, fulfilled every `interval_slots` without a new request
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq, Default)]
+#[cfg_attr(
+    not(feature = "backward-compat"),
+    borsh(crate = "crate::compat::borsh")
+)]
+pub struct CreateSubscription {
+    pub caller_seed: [u8; 32],
+    pub callback_program_id: compat::Pubkey,
+    pub callback_discriminator: Vec<u8>,
+    pub callback_accounts_metas: Vec<SerializableAccountMeta>,
+    pub callback_args: Vec<u8>,
+    pub callback_pda_metas: Vec<PdaAccountMeta>,
+    pub num_words: u8,
+    pub callback_abi: CallbackAbi,
+    pub interval_slots: u64,
+    pub first_round_slot: u64,
+    pub budget: u64,
+}
+
+impl CreateSubscription {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![21, 0, 0, 0, 0, 0, 0, 0];
+        self.serialize(&mut bytes).unwrap();
+        bytes
+    }
+}
+
+/// How the fee of a randomness request is paid.
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq, Default, Clone, Copy)]
+#[cfg_attr(
+    not(feature = "backward-compat"),
+    borsh(crate = "crate::compat::borsh")
+)]
+pub enum FeePayment {
+    /// Lamports transferred from the payer to the queue.
+    #[default]
+    Lamports,
+    /// Tokens of the queue's fee mint transferred from the payer to the queue's escrow.
+    Token,
+    /// Lamports debited from the payer's delegated ephemeral balance, required on the default
+    /// ephemeral queue.
+    EphemeralBalance,
+    /// Lamports paid by a sponsor whitelisting the callback program.
+    Sponsor,
+}
+
+/// Layout of the data passed to the callback of a request. The data of requests reassigned to
+/// another oracle is followed by `consts::REASSIGNED_CALLBACK_FLAG` in every layout, see
+/// `split_reassigned_flag`.
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq, Default, Clone, Copy)]
+#[cfg_attr(
+    not(feature = "backward-compat"),
+    borsh(crate = "crate::compat::borsh")
+)]
+pub enum CallbackAbi {
+    /// `discriminator || random words || callback_args`.
+    #[default]
+    Randomness,
+    /// `discriminator || FulfillmentHeader || random words || callback_args`.
+    Header,
+    /// `discriminator || FulfillmentHeader || FulfillmentProof || random words || callback_args`.
+    Proof,
+}
+
+/// Fulfillment of a request, passed to callbacks of requests opting into `CallbackAbi::Header`
+/// before the random words. Anchor callbacks take it as their first argument, native ones
+/// decode it with `FulfillmentHeader::decode`
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq, Default, Clone, Copy)]
+#[cfg_attr(
+    not(feature = "backward-compat"),
+    borsh(crate = "crate::compat::borsh")
+)]
+pub struct FulfillmentHeader {
+    /// Id of the request, also the VRF input
+    pub request_id: [u8; 32],
+    /// Slot in which the request was made
+    pub request_slot: u64,
+    /// Slot in which the request was fulfilled
+    pub fulfillment_slot: u64,
+    /// Oracle which fulfilled the request
+    pub oracle: compat::Pubkey,
+    /// Queue the request was fulfilled from, differs from the queue of its `RequestReturnData`
+    /// once reassigned to another oracle
+    pub queue: compat::Pubkey,
+    /// VRF output, the randomness is its hash
+    pub output: [u8; 32],
+}
+
+impl FulfillmentHeader {
+    /// Size of the encoded header
+    pub const LEN: usize = 144;
+
+    /// Decodes the header at the start of callback data, after its discriminator, returning it
+    /// with the random words and callback arguments following it
+    pub fn decode(mut data: &[u8]) -> Option<(Self, &[u8])> {
+        let header = Self::deserialize(&mut data).ok()?;
+        Some((header, data))
+    }
+
+    /// Returns true if the request was reassigned from the queue it was made on and fulfilled
+    /// by the oracle of another queue
+    pub fn is_reassigned(&self, request: &RequestReturnData) -> bool {
+        self.queue != request.queue
+    }
+}
+
+/// Return data of a randomness request, identifying the request a callback answers
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct RequestReturnData {
+    /// Id of the request, passed back in the fulfillment header
+    pub request_id: [u8; 32],
+    /// Queue of the request
+    pub queue: compat::Pubkey,
+}
+
+impl RequestReturnData {
+    /// Decodes the return data set by a randomness request, `request_id || queue`
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        if data.len() != 64 {
+            return None;
+        }
+        let (request_id, queue) = data.split_at(32);
+        Some(Self {
+            request_id: request_id.try_into().ok()?,
+            queue: compat::Pubkey::new_from_array(queue.try_into().ok()?),
+        })
+    }
+}
+
+/// Proof of the output of the `FulfillmentHeader`, passed after it to callbacks of requests
+/// opting into `CallbackAbi::Proof`, to be checked with `verify::verify_fulfillment`
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq, Default, Clone, Copy)]
+#[cfg_attr(
+    not(feature = "backward-compat"),
+    borsh(crate = "crate::compat::borsh")
+)]
+pub struct FulfillmentProof {
+    pub commitment_base_compressed: [u8; 32],
+    pub commitment_hash_compressed: [u8; 32],
+    pub scalar: [u8; 32],
+    /// VRF public key of the oracle which fulfilled the request
+    pub vrf_pubkey: [u8; 32],
+}
+
+impl FulfillmentProof {
+    /// Size of the encoded proof
+    pub const LEN: usize = 128;
+
+    /// Decodes the proof following the header in callback data, returning it with the random
+    /// words and callback arguments following it
+    pub fn decode(mut data: &[u8]) -> Option<(Self, &[u8])> {
+        let proof = Self::deserialize(&mut data).ok()?;
+        Some((proof, data))
+    }
+}
+
+/// Splits the callback arguments at the end of callback data, of `args_len` bytes, from the
+/// `consts::REASSIGNED_CALLBACK_FLAG` following them when the request was reassigned to another
+/// oracle. Returns the arguments and whether the request was reassigned, `None` if `data` is
+/// shorter than `args_len`. Anchor callbacks ignore the flag, they detect reassignments with
+/// `FulfillmentHeader::is_reassigned`
+pub fn split_reassigned_flag(data: &[u8], args_len: usize) -> Option<(&[u8], bool)> {
+    if data.len() < args_len {
+        return None;
+    }
+    let (args, rest) = data.split_at(args_len);
+    Some((args, rest == [crate::consts::REASSIGNED_CALLBACK_FLAG]))
+}
+
 #[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq, Default, Clone)]
 #[cfg_attr(
     not(feature = "backward-compat"),
@@ -32,3 +213,103 @@ pub struct SerializableAccountMeta {
     pub is_signer: bool,
     pub is_writable: bool,
 }
+
+/// Callback account written as the PDA of `program_id` with `seeds`, derived with the canonical
+/// bump when the request is fulfilled, for accounts that only exist after the request is made
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq, Default, Clone)]
+#[cfg_attr(
+    not(feature = "backward-compat"),
+    borsh(crate = "crate::compat::borsh")
+)]
+pub struct PdaAccountMeta {
+    pub program_id: compat::Pubkey,
+    pub seeds: Vec<Vec<u8>>,
+    pub is_writable: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header() -> Vec<u8> {
+        let mut data = vec![0u8; FulfillmentHeader::LEN];
+        data[..32].copy_from_slice(&[1; 32]);
+        data[32..40].copy_from_slice(&7u64.to_le_bytes());
+        data[40..48].copy_from_slice(&9u64.to_le_bytes());
+        data[48..80].copy_from_slice(&[2; 32]);
+        data[80..112].copy_from_slice(&[3; 32]);
+        data[112..144].copy_from_slice(&[4; 32]);
+        data
+    }
+
+    #[test]
+    fn decodes_the_header_and_returns_the_rest() {
+        let mut data = header();
+        data.extend_from_slice(&[5; 40]);
+        let (header, rest) = FulfillmentHeader::decode(&data).unwrap();
+        assert_eq!(header.request_id, [1; 32]);
+        assert_eq!(header.request_slot, 7);
+        assert_eq!(header.fulfillment_slot, 9);
+        assert_eq!(header.oracle, compat::Pubkey::new_from_array([2; 32]));
+        assert_eq!(header.queue, compat::Pubkey::new_from_array([3; 32]));
+        assert_eq!(header.output, [4; 32]);
+        assert_eq!(rest, &[5; 40]);
+    }
+
+    #[test]
+    fn header_has_its_encoded_size() {
+        let data = header();
+        let (header, rest) = FulfillmentHeader::decode(&data).unwrap();
+        assert!(rest.is_empty());
+        let mut encoded = Vec::new();
+        header.serialize(&mut encoded).unwrap();
+        assert_eq!(encoded.len(), FulfillmentHeader::LEN);
+    }
+
+    #[test]
+    fn rejects_a_truncated_header() {
+        assert!(FulfillmentHeader::decode(&header()[..FulfillmentHeader::LEN - 1]).is_none());
+        assert!(FulfillmentHeader::decode(&[]).is_none());
+    }
+
+    #[test]
+    fn header_naming_another_queue_is_reassigned() {
+        let (header, _) = FulfillmentHeader::decode(&header()).unwrap();
+        let mut request = RequestReturnData {
+            request_id: [1; 32],
+            queue: header.queue,
+        };
+        assert!(!header.is_reassigned(&request));
+        request.queue = compat::Pubkey::new_unique();
+        assert!(header.is_reassigned(&request));
+    }
+
+    #[test]
+    fn reassigned_requests_are_flagged_after_their_args() {
+        let args = [6; 3];
+        assert_eq!(split_reassigned_flag(&args, 3), Some((&args[..], false)));
+        let flagged = [6, 6, 6, crate::consts::REASSIGNED_CALLBACK_FLAG];
+        assert_eq!(split_reassigned_flag(&flagged, 3), Some((&args[..], true)));
+        assert_eq!(
+            split_reassigned_flag(&flagged[..1], 0),
+            Some((&[][..], false))
+        );
+        assert_eq!(split_reassigned_flag(&args, 4), None);
+    }
+
+    #[test]
+    fn decodes_the_request_id_and_queue() {
+        let mut data = [1u8; 64];
+        data[32..].copy_from_slice(&[2; 32]);
+        let request = RequestReturnData::decode(&data).unwrap();
+        assert_eq!(request.request_id, [1; 32]);
+        assert_eq!(request.queue, compat::Pubkey::new_from_array([2; 32]));
+    }
+
+    #[test]
+    fn rejects_return_data_of_another_size() {
+        assert!(RequestReturnData::decode(&[1; 63]).is_none());
+        assert!(RequestReturnData::decode(&[1; 65]).is_none());
+        assert!(RequestReturnData::decode(&[]).is_none());
+    }
+}
@@ -0,0 +1,120 @@
+//! On-chain verification of VRF proofs, the same as performed by the VRF program, for
+//! consumers re-checking the randomness passed to their callbacks (see
+//! `types::CallbackAbi::Proof`). Uses the curve25519 syscalls, so it can run inside a CPI.
+
+use crate::consts::{VRF_PREFIX_CHALLENGE, VRF_PREFIX_HASH_TO_POINT, VRF_PREFIX_HASH_TO_SCALAR};
+use crate::types::{FulfillmentHeader, FulfillmentProof};
+use curve25519_dalek::Scalar;
+use solana_curve25519::ristretto::{add_ristretto, multiply_ristretto, PodRistrettoPoint};
+use solana_curve25519::scalar::PodScalar;
+use solana_program::hash::{hash, hashv};
+
+const RISTRETTO_BASEPOINT_POINT: PodRistrettoPoint = PodRistrettoPoint([
+    226, 242, 174, 10, 106, 188, 78, 113, 168, 132, 169, 97, 197, 0, 81, 95, 88, 227, 11, 106, 165,
+    130, 221, 141, 182, 166, 89, 69, 224, 141, 45, 118,
+]);
+
+/// Verifies a VRF proof
+///
+/// # Arguments
+///
+/// * `pk` - The VRF public key of the oracle
+/// * `input` - The VRF input, the id of the request
+/// * `output` - The VRF output
+/// * `proof` - The commitments to the base point and to the hash point, and the scalar
+///
+/// # Returns
+///
+/// Whether `output` is the VRF output of `input` under `pk`
+pub fn verify_vrf(
+    pk: &[u8; 32],
+    input: &[u8; 32],
+    output: &[u8; 32],
+    proof: (&[u8; 32], &[u8; 32], &[u8; 32]),
+) -> bool {
+    let (commitment_base, commitment_hash, s) = proof;
+    let (pk, output) = (PodRistrettoPoint(*pk), PodRistrettoPoint(*output));
+    let (commitment_base, commitment_hash) = (
+        PodRistrettoPoint(*commitment_base),
+        PodRistrettoPoint(*commitment_hash),
+    );
+    let s = PodScalar(*s);
+
+    let h = hash_to_point(input);
+    let c = hash_to_scalar(
+        &hashv(&[
+            VRF_PREFIX_CHALLENGE,
+            &output.0,
+            &commitment_base.0,
+            &commitment_hash.0,
+            &pk.0,
+            input,
+        ])
+        .to_bytes(),
+    );
+
+    // s·G == commitment_base + c·pk
+    let base_check = multiply_ristretto(&s, &RISTRETTO_BASEPOINT_POINT).and_then(|lhs| {
+        let rhs =
+            multiply_ristretto(&c, &pk).and_then(|c_pk| add_ristretto(&commitment_base, &c_pk))?;
+        Some(lhs == rhs)
+    });
+    // s·h == commitment_hash + c·output
+    let hash_check = multiply_ristretto(&s, &h).and_then(|lhs| {
+        let rhs = multiply_ristretto(&c, &output)
+            .and_then(|c_output| add_ristretto(&commitment_hash, &c_output))?;
+        Some(lhs == rhs)
+    });
+
+    base_check == Some(true) && hash_check == Some(true)
+}
+
+/// Verifies the randomness passed to a callback of a request made with
+/// `types::CallbackAbi::Proof`
+///
+/// # Arguments
+///
+/// * `header` - The fulfillment header passed to the callback
+/// * `proof` - The fulfillment proof passed to the callback
+/// * `accepted_vrf_pubkeys` - The VRF public keys of the oracles trusted by the consumer
+///
+/// # Returns
+///
+/// Whether the proof is valid and made by an accepted oracle. The randomness is then
+/// `randomness(&header.output)`.
+pub fn verify_fulfillment(
+    header: &FulfillmentHeader,
+    proof: &FulfillmentProof,
+    accepted_vrf_pubkeys: &[[u8; 32]],
+) -> bool {
+    accepted_vrf_pubkeys.contains(&proof.vrf_pubkey)
+        && verify_vrf(
+            &proof.vrf_pubkey,
+            &header.request_id,
+            &header.output,
+            (
+                &proof.commitment_base_compressed,
+                &proof.commitment_hash_compressed,
+                &proof.scalar,
+            ),
+        )
+}
+
+/// Computes the randomness of a VRF output, the value passed to callbacks
+pub fn randomness(output: &[u8; 32]) -> [u8; 32] {
+    hash(output).to_bytes()
+}
+
+fn hash_to_point(input: &[u8; 32]) -> PodRistrettoPoint {
+    let hashed_input = hashv(&[VRF_PREFIX_HASH_TO_POINT, input]);
+    multiply_ristretto(
+        &PodScalar(Scalar::from_bytes_mod_order(hashed_input.to_bytes()).to_bytes()),
+        &RISTRETTO_BASEPOINT_POINT,
+    )
+    .expect("Failed to multiply scalar with base point")
+}
+
+fn hash_to_scalar(input: &[u8; 32]) -> PodScalar {
+    let hashed_input = hashv(&[VRF_PREFIX_HASH_TO_SCALAR, input]);
+    PodScalar(Scalar::from_bytes_mod_order(hashed_input.to_bytes()).to_bytes())
+}
@@ -0,0 +1,123 @@
+#![cfg(feature = "anchor")]
+#![allow(unexpected_cfgs)]
+
+extern crate anchor_lang_current as anchor_lang;
+
+use anchor_lang::prelude::*;
+use anchor_lang::system_program;
+use ephemeral_vrf_sdk::anchor::vrf;
+use std::collections::BTreeSet;
+
+declare_id!("CDiutifqugEkabdqwc5TK3FmSAgFpkP3RPE1642BCEhi");
+
+#[vrf]
+#[derive(Accounts)]
+pub struct RequestCtx<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: The oracle queue
+    #[account(mut)]
+    pub oracle_queue: UncheckedAccount<'info>,
+    /// CHECK: Result PDA of the request
+    #[account(mut)]
+    pub randomness_result: UncheckedAccount<'info>,
+}
+
+#[test]
+fn vrf_forwards_every_declared_account() {
+    let payer = Pubkey::new_unique();
+    let oracle_queue = Pubkey::new_unique();
+    let randomness_result = Pubkey::new_unique();
+    let program_identity = Pubkey::find_program_address(&[b"identity"], &ID).0;
+    let vrf_program = Pubkey::from(ephemeral_vrf_sdk::consts::VRF_PROGRAM_ID.to_bytes());
+    let slot_hashes = Pubkey::from(ephemeral_vrf_sdk::compat::slot_hashes::ID.to_bytes());
+    let system = system_program::ID;
+    let loader = Pubkey::new_unique();
+
+    let keys = [
+        (payer, true, system, false),
+        (oracle_queue, false, vrf_program, false),
+        (randomness_result, false, vrf_program, false),
+        (program_identity, false, system, false),
+        (vrf_program, false, loader, true),
+        (slot_hashes, false, system, false),
+        (system, false, loader, true),
+    ];
+    let mut lamports = [0u64; 7];
+    let mut data = vec![Vec::<u8>::new(); 7];
+    let infos = keys
+        .iter()
+        .zip(lamports.iter_mut())
+        .zip(data.iter_mut())
+        .map(|(((key, is_signer, owner, executable), lamports), data)| {
+            AccountInfo::new(key, *is_signer, true, lamports, data, owner, *executable)
+        })
+        .collect::<Vec<_>>();
+
+    let mut bumps = RequestCtxBumps::default();
+    let ctx = RequestCtx::try_accounts(&ID, &mut &infos[..], &[], &mut bumps, &mut BTreeSet::new())
+        .unwrap();
+    let forwarded = ctx
+        .to_account_infos()
+        .iter()
+        .map(|info| *info.key)
+        .collect::<Vec<_>>();
+
+    for key in [
+        oracle_queue,
+        randomness_result,
+        program_identity,
+        slot_hashes,
+    ] {
+        assert!(forwarded.contains(&key));
+    }
+}
+
+#[vrf(callback)]
+#[derive(Accounts)]
+pub struct CallbackCtx<'info> {
+    /// CHECK: Account written by the callback
+    #[account(mut)]
+    pub state: UncheckedAccount<'info>,
+}
+
+fn callback_accounts(identity_signed: bool) -> Result<()> {
+    let identity = Pubkey::from(ephemeral_vrf_sdk::consts::VRF_PROGRAM_IDENTITY.to_bytes());
+    let state = Pubkey::new_unique();
+    let owner = system_program::ID;
+    let (mut identity_lamports, mut state_lamports) = (0u64, 0u64);
+    let (mut identity_data, mut state_data) = (Vec::new(), Vec::new());
+    let infos = [
+        AccountInfo::new(
+            &identity,
+            identity_signed,
+            false,
+            &mut identity_lamports,
+            &mut identity_data,
+            &owner,
+            false,
+        ),
+        AccountInfo::new(
+            &state,
+            false,
+            true,
+            &mut state_lamports,
+            &mut state_data,
+            &owner,
+            false,
+        ),
+    ];
+
+    let mut bumps = CallbackCtxBumps::default();
+    let ctx =
+        CallbackCtx::try_accounts(&ID, &mut &infos[..], &[], &mut bumps, &mut BTreeSet::new())?;
+    assert_eq!(ctx.vrf_program_identity.key(), identity);
+    assert_eq!(ctx.state.key(), state);
+    Ok(())
+}
+
+#[test]
+fn vrf_callback_requires_the_vrf_program_identity() {
+    assert!(callback_accounts(true).is_ok());
+    assert!(callback_accounts(false).is_err());
+}
@@ -1,10 +1,22 @@
 use proc_macro::TokenStream;
 use quote::quote;
-use syn::{parse_macro_input, ItemStruct};
+use syn::parse::{Parse, ParseStream};
+use syn::{parse_macro_input, Expr, Ident, ItemStruct, Token};
 
+/// Adds the accounts of a randomness request to the accounts of an instruction, with
+/// `invoke_signed_vrf` forwarding them to the request.
+///
+/// With `#[vrf(callback)]`, makes the accounts of a callback instead: adds the VRF program
+/// identity signer, first account of every callback. Requests opting into the header callback
+/// ABI pass a `::ephemeral_vrf_sdk::types::FulfillmentHeader` as first argument of the callback,
+/// identifying the request it answers.
 #[proc_macro_attribute]
-pub fn vrf(_attr: TokenStream, item: TokenStream) -> TokenStream {
+pub fn vrf(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(attr as VrfArgs);
     let input = parse_macro_input!(item as ItemStruct);
+    if args.callback {
+        return expand_callback(input, None);
+    }
 
     let unchecked_account = generated_unchecked_account_type();
     let struct_name = &input.ident;
@@ -86,25 +98,160 @@ pub fn vrf(_attr: TokenStream, item: TokenStream) -> TokenStream {
         }
 
         impl<'info> #struct_name<'info> {
+            /// Invokes the request with every account of the struct, so that the accounts of
+            /// its fee payment, result PDA and receipt are forwarded when declared as fields
             fn invoke_signed_vrf<'a>(&self, payer: &'a AccountInfo<'info>, ix: &::ephemeral_vrf_sdk::compat::Instruction) -> ::ephemeral_vrf_sdk::compat::anchor_lang::solana_program::entrypoint::ProgramResult {
                 let bump = Pubkey::try_find_program_address(&[ephemeral_vrf_sdk::consts::IDENTITY], &crate::ID).ok_or(::ephemeral_vrf_sdk::compat::anchor_lang::prelude::ProgramError::InvalidSeeds)?;
+                let mut account_infos = ::ephemeral_vrf_sdk::compat::anchor_lang::ToAccountInfos::to_account_infos(self);
+                account_infos.push(payer.clone());
                 ::ephemeral_vrf_sdk::compat::anchor_lang::solana_program::program::invoke_signed(
                     ix,
-                    &[
-                        payer.clone(),
-                        self.program_identity.to_account_info(),
-                        self.oracle_queue.to_account_info(),
-                        self.slot_hashes.to_account_info(),
-                    ],
+                    &account_infos,
                     &[&[ephemeral_vrf_sdk::consts::IDENTITY, &[bump.1]]],
                 )
             }
+
+            /// Invokes the request and returns its id and queue, to match the callback
+            #[allow(dead_code)]
+            fn invoke_signed_vrf_request<'a>(&self, payer: &'a AccountInfo<'info>, ix: &::ephemeral_vrf_sdk::compat::Instruction) -> ::core::result::Result<::ephemeral_vrf_sdk::types::RequestReturnData, ::ephemeral_vrf_sdk::compat::anchor_lang::prelude::ProgramError> {
+                self.invoke_signed_vrf(payer, ix)?;
+                ::ephemeral_vrf_sdk::instructions::read_request_return_data()
+                    .ok_or(::ephemeral_vrf_sdk::compat::anchor_lang::prelude::ProgramError::InvalidAccountData)
+            }
+        }
+    };
+
+    TokenStream::from(expanded)
+}
+
+/// Same accounts as `#[vrf(callback)]`. With `#[vrf_callback(pending = account.field)]`, where
+/// `field` is the `[u8; 32]` id of the pending request stored by the consumer, generates
+/// `consume_vrf_request(&mut self, &header)` rejecting callbacks of other requests and replays.
+#[proc_macro_attribute]
+pub fn vrf_callback(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(attr as VrfCallbackArgs);
+    let input = parse_macro_input!(item as ItemStruct);
+    expand_callback(input, args.pending)
+}
+
+fn expand_callback(input: ItemStruct, pending: Option<Expr>) -> TokenStream {
+    let struct_name = &input.ident;
+    let original_attrs = &input.attrs;
+    let mut new_fields = Vec::new();
+    let mut has_vrf_program_identity = false;
+
+    for field in input.fields.iter() {
+        let field_attrs = field.attrs.clone();
+
+        let field_name = match &field.ident {
+            Some(name) => name,
+            None => {
+                return syn::Error::new_spanned(
+                    field,
+                    "Unnamed fields are not supported in this macro",
+                )
+                .to_compile_error()
+                .into();
+            }
+        };
+
+        let field_type = &field.ty;
+        new_fields.push(quote! {
+            #(#field_attrs)*
+            pub #field_name: #field_type,
+        });
+
+        if field_name.eq("vrf_program_identity") {
+            has_vrf_program_identity = true;
+        }
+    }
+
+    // The identity is the first account passed to callbacks
+    if !has_vrf_program_identity {
+        new_fields.insert(
+            0,
+            quote! {
+                /// Signer PDA of the VRF program
+                #[account(address = ::ephemeral_vrf_sdk::consts::VRF_PROGRAM_IDENTITY)]
+                pub vrf_program_identity: Signer<'info>,
+            },
+        );
+    }
+
+    // Match the callback against the pending request stored by the consumer
+    let consume_request = match &pending {
+        Some(pending) => quote! {
+            impl<'info> #struct_name<'info> {
+                /// Authenticates the callback and consumes the pending request it answers
+                #[allow(dead_code)]
+                fn consume_vrf_request(&mut self, header: &::ephemeral_vrf_sdk::types::FulfillmentHeader) -> ::core::result::Result<(), ::ephemeral_vrf_sdk::compat::ProgramError> {
+                    ::ephemeral_vrf_sdk::callback::verify_callback(
+                        self.vrf_program_identity.key,
+                        self.vrf_program_identity.is_signer,
+                        &mut self.#pending,
+                        header,
+                    )
+                    .map_err(Into::into)
+                }
+            }
+        },
+        None => quote! {},
+    };
+
+    let expanded = quote! {
+        #(#original_attrs)*
+        pub struct #struct_name<'info> {
+            #(#new_fields)*
         }
+
+        #consume_request
     };
 
     TokenStream::from(expanded)
 }
 
+/// Arguments of `#[vrf]`: optionally `callback`.
+struct VrfArgs {
+    callback: bool,
+}
+
+impl Parse for VrfArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        if input.is_empty() {
+            return Ok(Self { callback: false });
+        }
+        let name: Ident = input.parse()?;
+        if name != "callback" {
+            return Err(syn::Error::new_spanned(name, "expected `callback`"));
+        }
+        Ok(Self { callback: true })
+    }
+}
+
+/// Arguments of `#[vrf_callback]`: optionally `pending = <path to the pending request id>`.
+struct VrfCallbackArgs {
+    pending: Option<Expr>,
+}
+
+impl Parse for VrfCallbackArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        if input.is_empty() {
+            return Ok(Self { pending: None });
+        }
+        let name: Ident = input.parse()?;
+        if name != "pending" {
+            return Err(syn::Error::new_spanned(
+                name,
+                "expected `pending = <field>`",
+            ));
+        }
+        input.parse::<Token![=]>()?;
+        Ok(Self {
+            pending: Some(input.parse()?),
+        })
+    }
+}
+
 fn generated_unchecked_account_type() -> proc_macro2::TokenStream {
     if cfg!(feature = "backward-compat") {
         quote! { AccountInfo<'info> }
@@ -1,6 +1,7 @@
 use anyhow::Result;
 use clap::{Parser, Subcommand};
 use ephemeral_vrf::vrf::generate_vrf_keypair;
+use ephemeral_vrf_api::client::{OracleRegistry, QueueHealth};
 use ephemeral_vrf_api::prelude::*;
 use solana_client::rpc_client::RpcClient;
 use solana_commitment_config::CommitmentConfig;
@@ -67,6 +68,17 @@ enum Commands {
         bytes_to_allocate: Option<u32>,
     },
 
+    /// Initialize a queue shared by the registered oracles, signed by the admin
+    InitializeSharedQueue {
+        /// Queue index
+        #[arg(long)]
+        index: u8,
+
+        /// Bytes to allocate
+        #[arg(short, long)]
+        bytes_to_allocate: Option<u32>,
+    },
+
     /// Delegate an oracle queue
     DelegateOracleQueue {
         /// Queue pubkey
@@ -88,11 +100,115 @@ enum Commands {
         queue: String,
     },
 
+    /// Claim the fees accrued in an undelegated oracle queue
+    ClaimQueueFees {
+        /// Queue pubkey
+        #[arg(short, long)]
+        queue: String,
+    },
+
+    /// Accept fee payments in an SPL or Token-2022 mint for one of the signer's queues
+    ConfigureTokenFee {
+        /// Queue index
+        #[arg(long)]
+        index: u8,
+
+        /// Fee mint pubkey
+        #[arg(short, long)]
+        mint: String,
+
+        /// Price of a request without callback accounts or data in base units of the mint,
+        /// scaled by the size of requests like the lamports fee, 0 disables token payments
+        #[arg(short, long)]
+        price: u64,
+    },
+
+    /// Propose a new admin authority, signed by the current one
+    ProposeAdmin {
+        /// New authority pubkey, the default pubkey cancels a pending transfer
+        #[arg(short, long)]
+        authority: String,
+    },
+
+    /// Accept the admin authority proposed to the signer
+    AcceptAdmin {},
+
+    /// Assign an admin role, signed by the admin authority
+    SetAdminRole {
+        /// Role: 0 = oracle manager, 1 = fee manager, 2 = pauser
+        #[arg(short, long)]
+        role: u8,
+
+        /// Member pubkey, the default pubkey returns the role to the authority
+        #[arg(short, long)]
+        member: String,
+    },
+
+    /// Pause or resume the intake of requests of a queue, signed by the admin pauser
+    SetQueuePaused {
+        /// Queue pubkey
+        #[arg(short, long)]
+        queue: String,
+
+        /// Pause the queue, resume it if false
+        #[arg(short, long)]
+        paused: bool,
+    },
+
+    /// Set the share of the TTL after which pending requests can be reassigned, signed by the
+    /// admin oracle manager
+    SetReassignDelay {
+        /// Share of the TTL, in basis points
+        #[arg(short, long)]
+        after_ttl_bps: u64,
+    },
+
+    /// Create or update the signer's randomness beacon, published by its oracle
+    ConfigureBeacon {
+        /// Slots between two rounds of the beacon
+        #[arg(short, long)]
+        interval_slots: u64,
+    },
+
     /// Derive the current oracle pubkey for the given identity.
     DerivePubkey {},
 
     /// List all existing oracle's queues.
     ListQueue {},
+
+    /// List the registered oracles with the health of their queues.
+    ListOracles {},
+
+    /// Show the outcome of a request tracked by its receipt.
+    ShowReceipt {
+        /// Receipt account pubkey
+        #[arg(short, long)]
+        receipt: String,
+    },
+}
+
+fn print_queue(queue: &QueueHealth, registry: &OracleRegistry) {
+    let unknown = || "-".to_string();
+    let load = queue.load.as_ref();
+    let stalled = queue.is_stalled(
+        registry.slot,
+        registry.timestamp,
+        registry.reassign_after_ttl_bps,
+    );
+    println!(
+        "Queue address: {}, items: {}, index: {}, delegated: {}, needs migration: {}, used bytes: {}/{}, oldest item age: {}, stalled: {}",
+        queue.address,
+        load.map_or_else(unknown, |load| load.item_count.to_string()),
+        queue.index,
+        queue.delegated,
+        queue.needs_migration,
+        load.map_or_else(unknown, |load| load.used_bytes.to_string()),
+        queue.size,
+        queue
+            .oldest_item_age(registry.slot, registry.timestamp)
+            .map_or_else(unknown, |age| format!("{age} slots")),
+        load.map_or_else(unknown, |_| stalled.to_string()),
+    );
 }
 
 fn get_signer(keypair: &str) -> Keypair {
@@ -146,6 +262,16 @@ async fn main() -> Result<()> {
             println!("Initializing oracle queue for identity: {identity} with index: {index}");
             initialize_oracle_queue(signer.pubkey(), identity, *index, *bytes_to_allocate).to_vec()
         }
+        Commands::InitializeSharedQueue {
+            index,
+            bytes_to_allocate,
+        } => {
+            println!(
+                "Initializing shared queue {} with index: {index}",
+                shared_queue_pda(*index).0
+            );
+            initialize_shared_queue(signer.pubkey(), *index, *bytes_to_allocate)
+        }
         Commands::DelegateOracleQueue { queue } => {
             let queue = Pubkey::from_str(queue)?;
             let queue_account = rpc_client.get_account(&queue)?;
@@ -191,6 +317,61 @@ async fn main() -> Result<()> {
             );
             vec![close_oracle_queue(signer.pubkey(), queue_struct.index)]
         }
+        Commands::ClaimQueueFees { queue } => {
+            let queue = Pubkey::from_str(queue)?;
+            let queue_account = rpc_client.get_account(&queue)?;
+            let queue_struct = Queue::try_from_bytes(queue_account.data.as_slice())?;
+            println!(
+                "Claiming fees of oracle queue: {} with index: {}",
+                queue, queue_struct.index
+            );
+            vec![claim_queue_fees(signer.pubkey(), queue_struct.index)]
+        }
+        Commands::ConfigureTokenFee { index, mint, price } => {
+            let mint = Pubkey::from_str(mint)?;
+            let token_program = rpc_client.get_account(&mint)?.owner;
+            println!(
+                "Configuring token fee for queue index: {index}, mint: {mint}, price: {price}"
+            );
+            vec![configure_token_fee(
+                signer.pubkey(),
+                *index,
+                mint,
+                token_program,
+                *price,
+            )]
+        }
+        Commands::ProposeAdmin { authority } => {
+            let authority = Pubkey::from_str(authority)?;
+            println!("Proposing admin authority: {authority}");
+            vec![propose_admin(signer.pubkey(), authority)]
+        }
+        Commands::AcceptAdmin {} => {
+            println!("Accepting admin authority");
+            vec![accept_admin(signer.pubkey())]
+        }
+        Commands::SetAdminRole { role, member } => {
+            let role = AdminRole::try_from(*role)?;
+            let member = Pubkey::from_str(member)?;
+            println!("Assigning admin role {role:?} to: {member}");
+            vec![set_admin_role(signer.pubkey(), role, member)]
+        }
+        Commands::SetQueuePaused { queue, paused } => {
+            let queue = Pubkey::from_str(queue)?;
+            println!("Setting queue {queue} paused: {paused}");
+            vec![set_queue_paused(signer.pubkey(), queue, *paused)]
+        }
+        Commands::SetReassignDelay { after_ttl_bps } => {
+            println!("Setting reassign delay to {after_ttl_bps} bps of the TTL");
+            vec![set_reassign_delay(signer.pubkey(), *after_ttl_bps)]
+        }
+        Commands::ConfigureBeacon { interval_slots } => {
+            println!(
+                "Configuring beacon: {} with interval: {interval_slots} slots",
+                beacon_pda(&signer.pubkey()).0
+            );
+            vec![configure_beacon(signer.pubkey(), *interval_slots)]
+        }
         Commands::DerivePubkey {} => {
             let (_, oracle_vrf_pk) = generate_vrf_keypair(&signer);
             let pk = Pubkey::from(oracle_vrf_pk.compress().to_bytes());
@@ -198,29 +379,60 @@ async fn main() -> Result<()> {
             exit(0)
         }
         Commands::ListQueue {} => {
-            for i in 0..20 {
-                let queue = oracle_queue_pda(&signer.pubkey(), i).0;
-                let acc = rpc_client.get_account(&queue);
-                if acc.is_ok() {
-                    let account = acc?;
-                    let queue_struct = match Queue::try_from_bytes(account.data.as_slice()) {
-                        Ok(q) => q,
-                        Err(_) => {
-                            println!("Failed to parse queue account: {}", queue);
-                            continue;
-                        }
-                    };
-                    println!(
-                        "Queue address: {}, items: {}, index: {}, delegated: {}",
-                        queue,
-                        queue_struct.item_count,
-                        queue_struct.index,
-                        !account.owner.eq(&ephemeral_vrf_api::ID)
-                    );
+            let registry = OracleRegistry::fetch(&rpc_client)?;
+            let queues = registry
+                .queues()
+                .filter(|queue| queue.oracle == Some(signer.pubkey()));
+            for queue in queues {
+                print_queue(queue, &registry);
+            }
+            exit(0)
+        }
+        Commands::ListOracles {} => {
+            let registry = OracleRegistry::fetch(&rpc_client)?;
+            for oracle in &registry.oracles {
+                println!(
+                    "Oracle: {}, registered at slot: {}",
+                    oracle.identity, oracle.registration_slot
+                );
+                for queue in &oracle.queues {
+                    print_queue(queue, &registry);
+                }
+            }
+            println!("Shared queues:");
+            for queue in &registry.shared_queues {
+                print_queue(queue, &registry);
+            }
+            for delegated in [false, true] {
+                if let Some(queue) = registry.best_queue(delegated) {
+                    println!("Best queue (delegated: {}): {}", delegated, queue.address);
                 }
             }
             exit(0)
         }
+        Commands::ShowReceipt { receipt } => {
+            let receipt = Pubkey::from_str(receipt)?;
+            let account = rpc_client.get_account(&receipt)?;
+            let receipt = Receipt::try_from_bytes(&account.data)?;
+            let status = receipt
+                .status()
+                .map_or("Unknown".to_string(), |status| format!("{status:?}"));
+            println!(
+                "Request: {}, queue: {}, callback program: {}, status: {}",
+                Pubkey::new_from_array(receipt.request_id),
+                receipt.queue,
+                receipt.callback_program_id,
+                status
+            );
+            println!(
+                "Requested in slot {}, completed in slot {} by {}, randomness: {}",
+                receipt.request_slot,
+                receipt.completion_slot,
+                receipt.oracle,
+                Pubkey::new_from_array(receipt.randomness)
+            );
+            exit(0)
+        }
     };
 
     let mut ixs = Vec::with_capacity(1 + instructions.len());
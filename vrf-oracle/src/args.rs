@@ -23,4 +23,22 @@ pub struct Args {
 
     #[arg(long, env = "VRF_ORACLE_SKIP_PREFLIGHT", default_value_t = true)]
     pub skip_preflight: bool,
+
+    /// Share of a request's tip, in basis points, spent on the compute-unit price of its
+    /// fulfillment, at most the whole tip
+    #[arg(
+        long,
+        env = "VRF_ORACLE_TIP_PRIORITY_FEE_BPS",
+        default_value_t = 5_000,
+        value_parser = clap::value_parser!(u16).range(0..=10_000)
+    )]
+    pub tip_priority_fee_bps: u16,
+
+    /// Maximum number of requests of a queue fulfilled in one transaction, 1 disables batching
+    #[arg(long, env = "VRF_ORACLE_MAX_BATCH_SIZE", default_value_t = 4)]
+    pub max_batch_size: usize,
+
+    /// Maximum number of fulfillment transactions in flight, waiting requests are sent by tip
+    #[arg(long, env = "VRF_ORACLE_MAX_IN_FLIGHT", default_value_t = 32)]
+    pub max_in_flight: usize,
 }
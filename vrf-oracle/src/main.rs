@@ -76,6 +76,9 @@ async fn main() -> Result<()> {
         args.laserstream_endpoint,
         args.laserstream_api_key,
         args.skip_preflight,
+        args.tip_priority_fee_bps,
+        args.max_batch_size,
+        args.max_in_flight,
     ));
 
     // Start minimal HTTP server exposing /stats
@@ -0,0 +1,99 @@
+use crate::oracle::client::OracleClient;
+use anyhow::Result;
+use ephemeral_vrf::vrf::compute_vrf;
+use ephemeral_vrf_api::{
+    prelude::{update_beacon, AccountDeserialize, Beacon, RoundHash},
+    state::beacon_pda,
+    steel::sysvar,
+    ID as PROGRAM_ID,
+};
+use log::{info, warn};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_config::RpcSendTransactionConfig;
+use solana_commitment_config::{CommitmentConfig, CommitmentLevel};
+use solana_curve25519::{ristretto::PodRistrettoPoint, scalar::PodScalar};
+use solana_sdk::{pubkey::Pubkey, signature::Signer, transaction::Transaction};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::sleep;
+
+/// Delay between two checks of whether a beacon round is due.
+const BEACON_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Publish the rounds of the oracle's beacon as they become due. Beacons are created with
+/// `ConfigureBeacon`, oracles without one publish nothing.
+pub async fn publish_beacon_rounds(oracle_client: Arc<OracleClient>, rpc_client: Arc<RpcClient>) {
+    let beacon = beacon_pda(&oracle_client.keypair.pubkey()).0;
+    loop {
+        if let Err(err) = publish_due_round(&oracle_client, &rpc_client, &beacon).await {
+            warn!("Failed to publish beacon round: {err}");
+        }
+        sleep(BEACON_POLL_INTERVAL).await;
+    }
+}
+
+async fn publish_due_round(
+    oracle_client: &OracleClient,
+    rpc_client: &RpcClient,
+    beacon: &Pubkey,
+) -> Result<()> {
+    let account = rpc_client
+        .get_account_with_commitment(beacon, CommitmentConfig::processed())
+        .await?
+        .value;
+    let Some(account) = account.filter(|account| account.owner == PROGRAM_ID) else {
+        return Ok(());
+    };
+    let beacon_state = *Beacon::try_from_bytes(&account.data)?;
+
+    // The round is computed over the hash of its scheduled slot, the first round over the most
+    // recent slot hash
+    let slot_hashes = rpc_client
+        .get_account_with_commitment(&sysvar::slot_hashes::ID, CommitmentConfig::processed())
+        .await?
+        .value
+        .ok_or_else(|| anyhow::anyhow!("SlotHashes sysvar unavailable"))?;
+    let (slot, slothash) = match beacon_state.next_round_hash(&slot_hashes.data) {
+        None => (
+            u64::from_le_bytes(slot_hashes.data[8..16].try_into()?),
+            slot_hashes.data[16..48].try_into()?,
+        ),
+        Some(RoundHash::Found(slot, slothash)) => (slot, slothash),
+        Some(_) => return Ok(()),
+    };
+
+    let oracle = oracle_client.keypair.pubkey();
+    let input = Beacon::round_input(&oracle, slot, &slothash);
+    let (output, (commitment_base, commitment_hash, s)) =
+        compute_vrf(oracle_client.oracle_vrf_sk, &input);
+    let ix = update_beacon(
+        oracle,
+        slot,
+        PodRistrettoPoint(output.to_bytes()),
+        PodRistrettoPoint(commitment_base.to_bytes()),
+        PodRistrettoPoint(commitment_hash.to_bytes()),
+        PodScalar(s.to_bytes()),
+    );
+    let blockhash = rpc_client.get_latest_blockhash().await?;
+    let tx = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&oracle),
+        &[&oracle_client.keypair],
+        blockhash,
+    );
+    let sig = rpc_client
+        .send_transaction_with_config(
+            &tx,
+            RpcSendTransactionConfig {
+                skip_preflight: oracle_client.skip_preflight,
+                preflight_commitment: Some(CommitmentLevel::Processed),
+                ..Default::default()
+            },
+        )
+        .await?;
+    info!(
+        "Published beacon round {} for slot {slot}: {sig}",
+        beacon_state.round_count
+    );
+    Ok(())
+}
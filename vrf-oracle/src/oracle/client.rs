@@ -21,8 +21,11 @@ use helius_laserstream::{
 };
 
 use crate::blockhash_cache::BlockhashCache;
+use crate::oracle::beacon::publish_beacon_rounds;
+use crate::oracle::dispatch::Dispatcher;
 use crate::oracle::processor::{fetch_and_process_program_accounts, process_oracle_queue};
 use crate::oracle::sources::{LaserstreamSource, WebSocketSource};
+use crate::oracle::subscriptions::trigger_due_subscriptions;
 use crate::oracle::utils::queue_memcmp_filter;
 use curve25519_dalek::{RistrettoPoint, Scalar};
 use ephemeral_vrf::vrf::generate_vrf_keypair;
@@ -58,6 +61,12 @@ pub struct OracleClient {
     pub active_tasks: Arc<RwLock<ActiveTasksMap>>,
     // Whether to skip preflight when sending transactions
     pub skip_preflight: bool,
+    // Share of a request's tip (in bps) spent on the compute-unit price of its fulfillment
+    pub tip_priority_fee_bps: u16,
+    // Maximum number of requests of a queue fulfilled in one transaction
+    pub max_batch_size: usize,
+    // Fulfillments in flight, admitted by tip
+    pub dispatcher: Arc<Dispatcher>,
 }
 
 #[async_trait]
@@ -67,6 +76,7 @@ pub trait QueueUpdateSource: Send {
 }
 
 impl OracleClient {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         keypair: Keypair,
         rpc_url: String,
@@ -74,6 +84,9 @@ impl OracleClient {
         laserstream_endpoint: Option<String>,
         laserstream_api_key: Option<String>,
         skip_preflight: bool,
+        tip_priority_fee_bps: u16,
+        max_batch_size: usize,
+        max_in_flight: usize,
     ) -> Self {
         let (oracle_vrf_sk, oracle_vrf_pk) = generate_vrf_keypair(&keypair);
         Self {
@@ -90,6 +103,9 @@ impl OracleClient {
             inflight_requests: Arc::new(RwLock::new(HashMap::new())),
             active_tasks: Arc::new(RwLock::new(HashMap::new())),
             skip_preflight,
+            tip_priority_fee_bps,
+            max_batch_size,
+            dispatcher: Dispatcher::new(max_in_flight),
         }
     }
 
@@ -134,6 +150,34 @@ impl OracleClient {
             });
         }
 
+        // Periodically enqueue the due rounds of subscriptions every 30 seconds
+        {
+            let self_clone = Arc::clone(&self);
+            let rpc_client_clone = Arc::clone(&rpc_client);
+            let blockhash_cache_clone = Arc::clone(&blockhash_cache);
+            tokio::spawn(async move {
+                let mut interval = tokio::time::interval(std::time::Duration::from_secs(30));
+                loop {
+                    interval.tick().await;
+                    if let Err(err) = trigger_due_subscriptions(
+                        &self_clone,
+                        &rpc_client_clone,
+                        &blockhash_cache_clone,
+                    )
+                    .await
+                    {
+                        error!("Periodic trigger_due_subscriptions failed: {err:?}");
+                    }
+                }
+            });
+        }
+
+        // Publish the rounds of the oracle's randomness beacon, if configured
+        tokio::spawn(publish_beacon_rounds(
+            Arc::clone(&self),
+            Arc::clone(&rpc_client),
+        ));
+
         loop {
             match self.create_update_source().await {
                 Ok(mut source) => {
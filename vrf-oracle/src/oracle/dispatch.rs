@@ -0,0 +1,166 @@
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::oneshot;
+
+/// Limits the fulfillments sent concurrently by the oracle. Waiting requests are admitted by
+/// tip, then by age, so that paying more gets a request sent first under load.
+pub struct Dispatcher {
+    state: Mutex<DispatchState>,
+}
+
+struct DispatchState {
+    available: usize,
+    waiting: BinaryHeap<Waiter>,
+    sequence: u64,
+}
+
+/// Request waiting for a slot, ordered by highest tip, then lowest request slot, then arrival.
+struct Waiter {
+    priority: (u64, Reverse<u64>, Reverse<u64>),
+    admit: oneshot::Sender<DispatchPermit>,
+}
+
+impl PartialEq for Waiter {
+    fn eq(&self, other: &Self) -> bool {
+        self.priority == other.priority
+    }
+}
+
+impl Eq for Waiter {}
+
+impl PartialOrd for Waiter {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Waiter {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority.cmp(&other.priority)
+    }
+}
+
+/// Slot of a fulfillment in flight, handed to the next waiting request when dropped.
+pub struct DispatchPermit {
+    dispatcher: Option<Arc<Dispatcher>>,
+}
+
+impl Dispatcher {
+    pub fn new(max_in_flight: usize) -> Arc<Self> {
+        Arc::new(Self {
+            state: Mutex::new(DispatchState {
+                available: max_in_flight.max(1),
+                waiting: BinaryHeap::new(),
+                sequence: 0,
+            }),
+        })
+    }
+
+    /// Waits for a slot for a request with `tip` made at `slot`. Waiters dropped before being
+    /// admitted, e.g. aborted tasks, give their turn to the next one.
+    pub async fn acquire(self: &Arc<Self>, tip: u64, slot: u64) -> DispatchPermit {
+        let admitted = {
+            let mut state = self.state.lock().unwrap();
+            if state.available > 0 && state.waiting.is_empty() {
+                state.available -= 1;
+                return DispatchPermit {
+                    dispatcher: Some(Arc::clone(self)),
+                };
+            }
+            let (admit, admitted) = oneshot::channel();
+            state.sequence += 1;
+            let priority = (tip, Reverse(slot), Reverse(state.sequence));
+            state.waiting.push(Waiter { priority, admit });
+            admitted
+        };
+        // Waiters are only removed when admitted, a permit received and dropped unused by an
+        // aborted waiter is passed on
+        admitted
+            .await
+            .expect("waiters are admitted before being dropped")
+    }
+
+    fn release(self: &Arc<Self>) {
+        let mut state = self.state.lock().unwrap();
+        while let Some(waiter) = state.waiting.pop() {
+            let permit = DispatchPermit {
+                dispatcher: Some(Arc::clone(self)),
+            };
+            match waiter.admit.send(permit) {
+                Ok(()) => return,
+                // The waiter was dropped, the permit must not release again
+                Err(mut permit) => permit.dispatcher = None,
+            }
+        }
+        state.available += 1;
+    }
+
+    #[cfg(test)]
+    fn waiting(&self) -> usize {
+        self.state.lock().unwrap().waiting.len()
+    }
+}
+
+impl Drop for DispatchPermit {
+    fn drop(&mut self) {
+        if let Some(dispatcher) = self.dispatcher.take() {
+            dispatcher.release();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn waiting_requests_are_dispatched_by_tip_then_age() {
+        let dispatcher = Dispatcher::new(1);
+        let held = dispatcher.acquire(0, 0).await;
+        let dispatched = Arc::new(Mutex::new(Vec::new()));
+
+        let mut tasks = Vec::new();
+        for (tip, slot) in [(1, 10), (5, 12), (3, 10), (5, 11), (0, 1)] {
+            let dispatcher = Arc::clone(&dispatcher);
+            let dispatched = Arc::clone(&dispatched);
+            tasks.push(tokio::spawn(async move {
+                let _permit = dispatcher.acquire(tip, slot).await;
+                dispatched.lock().unwrap().push((tip, slot));
+            }));
+        }
+        while dispatcher.waiting() < 5 {
+            tokio::task::yield_now().await;
+        }
+
+        drop(held);
+        for task in tasks {
+            task.await.unwrap();
+        }
+        assert_eq!(
+            *dispatched.lock().unwrap(),
+            [(5, 11), (5, 12), (3, 10), (1, 10), (0, 1)]
+        );
+    }
+
+    #[tokio::test]
+    async fn aborted_waiters_give_their_turn() {
+        let dispatcher = Dispatcher::new(1);
+        let held = dispatcher.acquire(0, 0).await;
+        let aborted = {
+            let dispatcher = Arc::clone(&dispatcher);
+            tokio::spawn(async move {
+                let _permit = dispatcher.acquire(10, 0).await;
+            })
+        };
+        while dispatcher.waiting() < 1 {
+            tokio::task::yield_now().await;
+        }
+        aborted.abort();
+        let _ = aborted.await;
+
+        drop(held);
+        let _permit = dispatcher.acquire(1, 0).await;
+        assert_eq!(dispatcher.waiting(), 0);
+    }
+}
@@ -1,4 +1,7 @@
+pub mod beacon;
 pub mod client;
+pub mod dispatch;
 pub mod processor;
 pub mod sources;
+pub mod subscriptions;
 pub mod utils;
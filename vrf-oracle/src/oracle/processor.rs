@@ -4,28 +4,71 @@ use anyhow::Result;
 use ephemeral_vrf::vrf::{compute_vrf, verify_vrf};
 use ephemeral_vrf_api::{
     prelude::{
-        provide_randomness, purge_expired_requests, Queue, QueueAccount, QueueItem, QUEUE_TTL_SLOTS,
+        migrate_queue, provide_randomness, provide_randomness_batch, provide_randomness_fallback,
+        purge_expired_requests, purge_expired_shared_requests, token_fee_accounts,
+        AccountDeserialize, Oracles, ProvideRandomness, Queue, QueueAccount, QueueItem, Receipt,
+        TokenFeeConfig, Zeroable, SHARED_QUEUE_CLAIM_DELAY_MS,
     },
-    state::oracle_queue_pda,
+    state::{oracle_queue_pda, oracles_pda, receipt_pda, shared_queue_pda, token_fee_config_pda},
     ID as PROGRAM_ID,
 };
 use futures_util::future::join_all;
 use futures_util::FutureExt;
 use log::{error, info, trace, warn};
 use solana_account_decoder::UiAccountEncoding;
+use solana_address_lookup_table_interface::state::AddressLookupTable;
 use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_client::rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig};
 use solana_client::rpc_filter::RpcFilterType;
 use solana_commitment_config::{CommitmentConfig, CommitmentLevel};
 use solana_compute_budget_interface::ComputeBudgetInstruction;
 use solana_curve25519::{ristretto::PodRistrettoPoint, scalar::PodScalar};
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::message::{v0, AddressLookupTableAccount, Message, VersionedMessage};
+use solana_sdk::transaction::VersionedTransaction;
 use solana_sdk::{pubkey::Pubkey, signature::Signer, transaction::Transaction};
+use spl_associated_token_account_interface::address::get_associated_token_address_with_program_id;
+use spl_associated_token_account_interface::instruction::create_associated_token_account_idempotent;
 use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tokio::task;
 use tokio::time::sleep;
 
+/// Simulated callback failures after which a request is fulfilled without its callback.
+const CALLBACK_FAILURES_BEFORE_FALLBACK: u32 = 3;
+
+/// Lower bound of the slot duration, used to wait for scheduled requests without overshooting.
+const MIN_SLOT_DURATION_MS: u64 = 50;
+
+/// Longest wait before checking again whether a scheduled request is due.
+const MAX_SCHEDULE_POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Attempts to confirm a batch before retrying its requests individually.
+const BATCH_ATTEMPTS: u32 = 5;
+
+/// Compute units available to a transaction.
+const MAX_TRANSACTION_COMPUTE_UNITS: u32 = 1_400_000;
+
+/// Maximum size of a serialized transaction.
+const PACKET_DATA_SIZE: usize = 1232;
+
+/// Receipts of expired requests passed to a purge, with the token accounts their fees paid in
+/// tokens are refunded to. Others stay pending until closed by their payer.
+const MAX_PURGE_RECEIPTS: usize = 24;
+
+/// The callback of a request failed in simulation.
+#[derive(Debug)]
+pub struct CallbackFailed;
+
+impl std::fmt::Display for CallbackFailed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "callback failed in simulation")
+    }
+}
+
+impl std::error::Error for CallbackFailed {}
+
 pub async fn fetch_and_process_program_accounts(
     oracle_client: &Arc<OracleClient>,
     rpc_client: &Arc<RpcClient>,
@@ -99,7 +142,8 @@ pub async fn process_oracle_queue(
     account_bytes: Arc<Vec<u8>>,
     notification_slot: Option<u64>,
 ) {
-    if oracle_queue_pda(&oracle_client.keypair.pubkey(), oracle_queue.index).0 == *queue {
+    let shared = oracle_queue.shared == 1 && shared_queue_pda(oracle_queue.index).0 == *queue;
+    if shared || oracle_queue_pda(&oracle_client.keypair.pubkey(), oracle_queue.index).0 == *queue {
         if oracle_queue.item_count > 0 {
             info!(
                 "Processing queue: {}, with len: {}",
@@ -107,6 +151,17 @@ pub async fn process_oracle_queue(
             );
         }
 
+        // Requests of queues created before the layout version are converted first
+        if QueueAccount::is_legacy(&account_bytes[8..]) {
+            info!("Migrating queue {queue} to the current layout");
+            if let Err(err) =
+                send_migrate_queue(oracle_client, rpc_client, blockhash_cache, queue).await
+            {
+                warn!("Failed to migrate queue {queue}: {err}");
+            }
+            return;
+        }
+
         // Update web-exposed queue size map
         {
             let mut stats = oracle_client.queue_stats.write().await;
@@ -174,25 +229,19 @@ pub async fn process_oracle_queue(
         // Process items (send transactions)
         // Take an owned snapshot of the queue metadata and items so spawned tasks don't borrow `oracle_queue`.
         let queue_meta = Arc::new(*oracle_queue);
-        let items: Vec<QueueItem> = queue_account.iter_items().collect();
+        // Serve higher tips first, then older requests, the dispatcher keeps this order across
+        // queues once fulfillments wait for a slot
+        let mut items: Vec<QueueItem> = queue_account.iter_items().collect();
+        items.sort_by(|a, b| b.tip.cmp(&a.tip).then(a.slot.cmp(&b.slot)));
 
+        let (_, current_slot) = blockhash_cache.get_blockhash_and_slot().await;
+        let now = unix_timestamp();
+        let mut batchable = Vec::new();
         for item in items.into_iter() {
-            let oracle_client = Arc::clone(oracle_client);
-            let rpc_client = Arc::clone(rpc_client);
-            let blockhash_cache = blockhash_cache.clone();
-            let queue = *queue;
-            let oracle_queue = Arc::clone(&queue_meta);
-            let account_bytes_task = Arc::clone(&account_bytes);
-            let input_seed = item.id;
-            let queue_key_spawn = queue_key.clone();
-            // Separate clones to satisfy borrow checker across awaits
-            let oracle_client_for_proc = Arc::clone(&oracle_client);
-            let oracle_client_for_cleanup = Arc::clone(&oracle_client);
-
             // Only spawn a task if this request is not already in-flight for this queue
             let should_spawn = {
                 let mut inflight_all = oracle_client.inflight_requests.write().await;
-                let inflight_for_queue = inflight_all.entry(queue_key_spawn.clone()).or_default();
+                let inflight_for_queue = inflight_all.entry(queue_key.clone()).or_default();
                 if let std::collections::hash_map::Entry::Vacant(e) =
                     inflight_for_queue.entry(item.id)
                 {
@@ -207,112 +256,591 @@ pub async fn process_oracle_queue(
                 continue;
             }
 
-            let handle = tokio::spawn(async move {
+            // Requests of shared queues are claimed one by one, staggered between the oracles
+            if oracle_client.max_batch_size > 1
+                && !shared
+                && is_batchable(&item, &account_bytes[8..], current_slot, now)
+            {
+                batchable.push(item);
+                continue;
+            }
+
+            spawn_item_task(
+                oracle_client,
+                rpc_client,
+                blockhash_cache,
+                queue,
+                &queue_meta,
+                &account_bytes,
+                item,
+            )
+            .await;
+        }
+
+        if batchable.is_empty() {
+            return;
+        }
+
+        // Fulfill the remaining requests in batches fitting in a transaction
+        let token_fee_config = if batchable.iter().any(QueueItem::is_token_fee) {
+            match fetch_token_fee_config(rpc_client, queue).await {
+                Ok(config) => config,
+                Err(err) => {
+                    warn!("Failed to fetch token fee config of {queue}: {err}");
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        let batches = pack_batches(
+            oracle_client,
+            queue,
+            &account_bytes[8..],
+            token_fee_config.as_ref(),
+            batchable,
+        );
+        for batch in batches {
+            if let [item] = batch[..] {
+                spawn_item_task(
+                    oracle_client,
+                    rpc_client,
+                    blockhash_cache,
+                    queue,
+                    &queue_meta,
+                    &account_bytes,
+                    item,
+                )
+                .await;
+                continue;
+            }
+
+            let oracle_client = Arc::clone(oracle_client);
+            let rpc_client = Arc::clone(rpc_client);
+            let blockhash_cache = blockhash_cache.clone();
+            let queue = *queue;
+            let oracle_queue = Arc::clone(&queue_meta);
+            let account_bytes = Arc::clone(&account_bytes);
+
+            // Batch tasks are not tracked for cancellation, their requests stay in-flight until
+            // they leave the queue or fall back to individual tasks
+            tokio::spawn(async move {
+                // A batch is sent with the priority of its best request
+                let tip = batch.iter().map(|item| item.tip).max().unwrap_or_default();
+                let slot = batch.iter().map(|item| item.slot).min().unwrap_or_default();
+                let permit = oracle_client.dispatcher.acquire(tip, slot).await;
+
                 let mut attempts = 0;
                 let mut confirmed_success = false;
 
-                while attempts < 100 {
-                    match ProcessableItem(item)
-                        .process_item(
-                            &oracle_client_for_proc,
-                            &rpc_client,
-                            &blockhash_cache,
-                            &input_seed,
-                            &queue,
-                            &oracle_queue,
-                            account_bytes_task.as_slice(),
-                        )
+                while attempts < BATCH_ATTEMPTS {
+                    let result = process_batch(
+                        &oracle_client,
+                        &rpc_client,
+                        &blockhash_cache,
+                        &queue,
+                        account_bytes.as_slice(),
+                        token_fee_config.as_ref(),
+                        &batch,
+                        attempts > 0,
+                    )
+                    .await;
+                    let signature = match result {
+                        Ok(signature) => signature,
+                        Err(err) => {
+                            warn!("Batch of {} requests failed: {err}", batch.len());
+                            break;
+                        }
+                    };
+                    let Ok(sig) = signature.parse::<solana_sdk::signature::Signature>() else {
+                        break;
+                    };
+                    match rpc_client
+                        .confirm_transaction_with_commitment(&sig, CommitmentConfig::processed())
                         .await
                     {
-                        Ok(signature) => {
-                            trace!(
-                                "Transaction: {}, for id {}",
+                        Ok(success) if success.value => {
+                            info!(
+                                "Batch transaction successfully confirmed: {}, for {} requests",
                                 signature,
-                                Pubkey::new_from_array(item.id)
+                                batch.len()
                             );
-                            let sig = match signature.parse::<solana_sdk::signature::Signature>() {
-                                Ok(sig) => sig,
-                                Err(_) => {
-                                    continue;
-                                }
-                            };
-
-                            let result = rpc_client
-                                .confirm_transaction_with_commitment(
-                                    &sig,
-                                    CommitmentConfig::processed(),
-                                )
-                                .await;
-
-                            match result {
-                                Ok(success) => {
-                                    if success.value {
-                                        info!(
-                                            "Transaction successfully confirmed: {}, for id: {}",
-                                            signature,
-                                            Pubkey::new_from_array(item.id)
-                                        );
-                                        confirmed_success = true;
-                                        break;
-                                    } else {
-                                        attempts += 1;
-                                        blockhash_cache.refresh_blockhash().await;
-                                        if attempts > 20 {
-                                            let delay_ms = 10 * (attempts - 20);
-                                            sleep(Duration::from_millis(delay_ms)).await;
-                                        }
-                                    }
-                                }
-                                Err(err) => {
-                                    warn!("Transaction {sig} failed to confirm: {err}");
-                                    attempts += 3;
-                                    blockhash_cache.refresh_blockhash().await;
-                                }
-                            }
+                            confirmed_success = true;
+                            break;
                         }
-                        Err(_) => {
-                            // Response may be in the same slot, we retry with linear backoff
-                            blockhash_cache.refresh_blockhash().await;
-                            if attempts > 5 {
-                                let delay_ms = 20 * (attempts - 5);
-                                sleep(Duration::from_millis(delay_ms)).await;
-                            }
+                        Ok(_) => {
                             attempts += 1;
+                            blockhash_cache.refresh_blockhash().await;
+                            sleep(Duration::from_millis(20 * attempts as u64)).await;
+                        }
+                        Err(err) => {
+                            warn!("Batch transaction {sig} failed to confirm: {err}");
+                            break;
                         }
                     }
                 }
 
-                // Task finished. Remove from active_tasks. If not confirmed, also clear inflight to allow retry.
-                {
-                    let mut tasks_all = oracle_client_for_cleanup.active_tasks.write().await;
-                    if let Some(tasks_for_queue) = tasks_all.get_mut(&queue_key_spawn) {
-                        tasks_for_queue.remove(&item.id);
-                    }
+                drop(permit);
+                if confirmed_success {
+                    return;
                 }
 
-                if !confirmed_success {
-                    let mut inflight_all =
-                        oracle_client_for_cleanup.inflight_requests.write().await;
-                    if let Some(inflight_for_queue) = inflight_all.get_mut(&queue_key_spawn) {
-                        inflight_for_queue.remove(&item.id);
-                    }
+                // Retry the requests individually, falling back for failing callbacks
+                for item in batch {
+                    spawn_item_task(
+                        &oracle_client,
+                        &rpc_client,
+                        &blockhash_cache,
+                        &queue,
+                        &oracle_queue,
+                        &account_bytes,
+                        item,
+                    )
+                    .await;
                 }
             });
+        }
+    }
+}
 
-            // Track the task handle for potential cancellation if the item disappears from the queue
-            {
-                let mut tasks_all = oracle_client.active_tasks.write().await;
-                let tasks_for_queue = tasks_all.entry(queue_key.clone()).or_default();
-                tasks_for_queue.insert(item.id, handle);
+/// Sends a step of the migration of a legacy queue, repeated on the next update of the queue
+/// until it is migrated.
+async fn send_migrate_queue(
+    oracle_client: &OracleClient,
+    rpc_client: &RpcClient,
+    blockhash_cache: &BlockhashCache,
+    queue: &Pubkey,
+) -> Result<()> {
+    let oracle = oracle_client.keypair.pubkey();
+    let (blockhash, _) = blockhash_cache.get_blockhash_and_slot().await;
+    let tx = Transaction::new_signed_with_payer(
+        &[migrate_queue(oracle, *queue)],
+        Some(&oracle),
+        &[&oracle_client.keypair],
+        blockhash,
+    );
+    let sig = rpc_client.send_and_confirm_transaction(&tx).await?;
+    info!("Migration step of queue {queue} sent: {sig}");
+    Ok(())
+}
+
+/// Spawn the task fulfilling a single in-flight request, tracked for cancellation once the
+/// request leaves the queue.
+async fn spawn_item_task(
+    oracle_client: &Arc<OracleClient>,
+    rpc_client: &Arc<RpcClient>,
+    blockhash_cache: &BlockhashCache,
+    queue: &Pubkey,
+    queue_meta: &Arc<Queue>,
+    account_bytes: &Arc<Vec<u8>>,
+    item: QueueItem,
+) {
+    let oracle_client_for_task = Arc::clone(oracle_client);
+    let rpc_client = Arc::clone(rpc_client);
+    let blockhash_cache = blockhash_cache.clone();
+    let queue = *queue;
+    let oracle_queue = Arc::clone(queue_meta);
+    let account_bytes = Arc::clone(account_bytes);
+    let queue_key = queue.to_string();
+    let queue_key_spawn = queue_key.clone();
+
+    let handle = tokio::spawn(async move {
+        let confirmed_success = fulfill_with_retries(
+            &oracle_client_for_task,
+            &rpc_client,
+            &blockhash_cache,
+            &queue,
+            &oracle_queue,
+            account_bytes.as_slice(),
+            item,
+        )
+        .await;
+
+        // Task finished. Remove from active_tasks. If not confirmed, also clear inflight to allow retry.
+        {
+            let mut tasks_all = oracle_client_for_task.active_tasks.write().await;
+            if let Some(tasks_for_queue) = tasks_all.get_mut(&queue_key_spawn) {
+                tasks_for_queue.remove(&item.id);
+            }
+        }
+
+        if !confirmed_success {
+            let mut inflight_all = oracle_client_for_task.inflight_requests.write().await;
+            if let Some(inflight_for_queue) = inflight_all.get_mut(&queue_key_spawn) {
+                inflight_for_queue.remove(&item.id);
+            }
+        }
+    });
+
+    // Track the task handle for potential cancellation if the item disappears from the queue
+    {
+        let mut tasks_all = oracle_client.active_tasks.write().await;
+        let tasks_for_queue = tasks_all.entry(queue_key).or_default();
+        tasks_for_queue.insert(item.id, handle);
+    }
+}
+
+/// Send the fulfillment of a request until it is confirmed, returning whether it was.
+async fn fulfill_with_retries(
+    oracle_client: &OracleClient,
+    rpc_client: &Arc<RpcClient>,
+    blockhash_cache: &BlockhashCache,
+    queue: &Pubkey,
+    oracle_queue: &Queue,
+    account_bytes: &[u8],
+    item: QueueItem,
+) -> bool {
+    // Scheduled requests are held until due
+    wait_until_due(blockhash_cache, &item).await;
+
+    // Oracles ranked first for a request of a shared queue claim it first, the task is
+    // cancelled once another oracle fulfilled it
+    if oracle_queue.shared == 1 {
+        sleep(claim_delay(oracle_client, rpc_client, &item).await).await;
+    }
+
+    // Requests are sent by tip once the oracle has fulfillments in flight
+    let _permit = oracle_client.dispatcher.acquire(item.tip, item.slot).await;
+
+    let mut attempts = 0;
+    let mut callback_failures = 0;
+    let mut fallback = false;
+
+    while attempts < 100 {
+        match ProcessableItem(item)
+            .process_item(
+                oracle_client,
+                rpc_client,
+                blockhash_cache,
+                &item.id,
+                queue,
+                oracle_queue,
+                account_bytes,
+                fallback,
+                attempts > 0,
+            )
+            .await
+        {
+            Ok(signature) => {
+                trace!(
+                    "Transaction: {}, for id {}",
+                    signature,
+                    Pubkey::new_from_array(item.id)
+                );
+                let sig = match signature.parse::<solana_sdk::signature::Signature>() {
+                    Ok(sig) => sig,
+                    Err(_) => {
+                        continue;
+                    }
+                };
+
+                let result = rpc_client
+                    .confirm_transaction_with_commitment(&sig, CommitmentConfig::processed())
+                    .await;
+
+                match result {
+                    Ok(success) => {
+                        if success.value {
+                            info!(
+                                "Transaction successfully confirmed: {}, for id: {}",
+                                signature,
+                                Pubkey::new_from_array(item.id)
+                            );
+                            return true;
+                        } else {
+                            attempts += 1;
+                            blockhash_cache.refresh_blockhash().await;
+                            if attempts > 20 {
+                                let delay_ms = 10 * (attempts - 20);
+                                sleep(Duration::from_millis(delay_ms)).await;
+                            }
+                        }
+                    }
+                    Err(err) => {
+                        warn!("Transaction {sig} failed to confirm: {err}");
+                        attempts += 3;
+                        blockhash_cache.refresh_blockhash().await;
+                    }
+                }
+            }
+            Err(err) if err.is::<CallbackFailed>() => {
+                // Consume the request without its callback once it keeps failing, if it opted
+                // into the fallback
+                callback_failures += 1;
+                if item.fallback == 1 && callback_failures >= CALLBACK_FAILURES_BEFORE_FALLBACK {
+                    warn!(
+                        "Callback keeps failing for id {}, providing fallback result",
+                        Pubkey::new_from_array(item.id)
+                    );
+                    fallback = true;
+                }
+                attempts += 1;
+            }
+            Err(_) => {
+                // Response may be in the same slot, we retry with linear backoff
+                blockhash_cache.refresh_blockhash().await;
+                if attempts > 5 {
+                    let delay_ms = 20 * (attempts - 5);
+                    sleep(Duration::from_millis(delay_ms)).await;
+                }
+                attempts += 1;
             }
         }
     }
+    false
+}
+
+/// Delay before claiming a request of a shared queue, from the rank of the oracle among the
+/// registered oracles.
+async fn claim_delay(
+    oracle_client: &OracleClient,
+    rpc_client: &RpcClient,
+    item: &QueueItem,
+) -> Duration {
+    let rank = match rpc_client.get_account_data(&oracles_pda().0).await {
+        Ok(data) => Oracles::try_from_bytes_with_discriminator(&data)
+            .ok()
+            .and_then(|oracles| oracles.claim_rank(&oracle_client.keypair.pubkey(), &item.id))
+            .unwrap_or(0),
+        Err(err) => {
+            warn!("Failed to fetch the registered oracles: {err}");
+            0
+        }
+    };
+    Duration::from_millis(SHARED_QUEUE_CLAIM_DELAY_MS.saturating_mul(rank as u64))
+}
+
+/// Wait until the schedule of a request allows fulfilling it, refreshing the slot as it nears.
+async fn wait_until_due(blockhash_cache: &BlockhashCache, item: &QueueItem) {
+    loop {
+        let (_, slot) = blockhash_cache.get_blockhash_and_slot().await;
+        let now = unix_timestamp();
+        if item.is_due(slot, now) {
+            return;
+        }
+        let slots_left = item.not_before_slot.saturating_sub(slot);
+        let seconds_left = item.not_before_timestamp.saturating_sub(now).max(0) as u64;
+        let wait = Duration::from_millis(slots_left.saturating_mul(MIN_SLOT_DURATION_MS))
+            .max(Duration::from_secs(seconds_left))
+            .clamp(
+                Duration::from_millis(MIN_SLOT_DURATION_MS),
+                MAX_SCHEDULE_POLL_INTERVAL,
+            );
+        sleep(wait).await;
+        blockhash_cache.refresh_blockhash().await;
+    }
+}
+
+/// Current unix timestamp, compared against the schedule of requests.
+fn unix_timestamp() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() as i64)
+}
+
+/// Whether a request can be fulfilled in a batch: due, not expired, sent as a legacy
+/// transaction and with resolvable PDA metas.
+fn is_batchable(item: &QueueItem, queue_data: &[u8], current_slot: u64, now: i64) -> bool {
+    item.is_due(current_slot, now)
+        && !item.is_expired(current_slot, now)
+        && item.lookup_table().is_none()
+        && item.pda_account_metas(queue_data).is_ok()
+}
+
+/// Split requests, in order, into batches of at most `max_batch_size` requests fitting in the
+/// compute and size limits of a transaction.
+fn pack_batches(
+    oracle_client: &OracleClient,
+    queue: &Pubkey,
+    queue_data: &[u8],
+    token_fee_config: Option<&TokenFeeConfig>,
+    items: Vec<QueueItem>,
+) -> Vec<Vec<QueueItem>> {
+    let fits = |batch: &[QueueItem]| {
+        if batch.len() > oracle_client.max_batch_size
+            || batch.iter().map(compute_budget).sum::<u32>() > MAX_TRANSACTION_COMPUTE_UNITS
+        {
+            return false;
+        }
+        // Proofs don't change the size of the transaction
+        let proofs = vec![ProvideRandomness::zeroed(); batch.len()];
+        let Ok(instructions) = batch_instructions(
+            oracle_client,
+            queue,
+            queue_data,
+            token_fee_config,
+            batch,
+            &proofs,
+        ) else {
+            return false;
+        };
+        let message = Message::new(&instructions, Some(&oracle_client.keypair.pubkey()));
+        // Compact length and signature of the oracle
+        1 + 64 + message.serialize().len() <= PACKET_DATA_SIZE
+    };
+
+    let mut batches = Vec::new();
+    let mut current: Vec<QueueItem> = Vec::new();
+    for item in items {
+        current.push(item);
+        if current.len() > 1 && !fits(&current) {
+            current.pop();
+            batches.push(std::mem::take(&mut current));
+            current.push(item);
+        }
+    }
+    if !current.is_empty() {
+        batches.push(current);
+    }
+    batches
+}
+
+/// Build the instructions fulfilling a batch of requests with their proofs.
+fn batch_instructions(
+    oracle_client: &OracleClient,
+    queue: &Pubkey,
+    queue_data: &[u8],
+    token_fee_config: Option<&TokenFeeConfig>,
+    batch: &[QueueItem],
+    proofs: &[ProvideRandomness],
+) -> Result<Vec<Instruction>> {
+    let oracle = oracle_client.keypair.pubkey();
+    let oracle_token_account = token_fee_config.map(|config| {
+        get_associated_token_address_with_program_id(&oracle, &config.mint, &config.token_program)
+    });
+
+    let mut requests = Vec::with_capacity(batch.len());
+    for (item, proof) in batch.iter().zip(proofs) {
+        let mut accounts: Vec<AccountMeta> = item
+            .account_metas(queue_data)
+            .iter()
+            .map(|a| a.to_account_meta())
+            .collect();
+        accounts.extend(
+            item.pda_account_metas(queue_data)?
+                .iter()
+                .map(|a| a.to_account_meta()),
+        );
+        if item.is_token_fee() {
+            let (Some(config), Some(oracle_token_account)) =
+                (token_fee_config, oracle_token_account)
+            else {
+                anyhow::bail!("Token fee of {queue} is not configured");
+            };
+            accounts.extend(token_fee_accounts(
+                queue,
+                oracle_token_account,
+                config.mint,
+                config.token_program,
+            ));
+        }
+        accounts.extend(receipt_account(item));
+        requests.push((
+            *proof,
+            Pubkey::new_from_array(item.callback_program_id),
+            accounts,
+        ));
+    }
+
+    let budget = batch.iter().map(compute_budget).sum();
+    let tip = batch.iter().map(|item| item.tip).sum::<u64>();
+    let mut instructions = vec![ComputeBudgetInstruction::set_compute_unit_limit(budget)];
+    if tip > 0 {
+        instructions.push(ComputeBudgetInstruction::set_compute_unit_price(
+            compute_unit_price(tip, oracle_client.tip_priority_fee_bps, budget),
+        ));
+    }
+    if let (Some(config), true) = (token_fee_config, batch.iter().any(QueueItem::is_token_fee)) {
+        instructions.push(create_associated_token_account_idempotent(
+            &oracle,
+            &oracle,
+            &config.mint,
+            &config.token_program,
+        ));
+    }
+    instructions.push(provide_randomness_batch(oracle, *queue, &requests));
+    Ok(instructions)
+}
+
+/// Sends the fulfillment of a batch of requests. With `simulate` the transaction is simulated
+/// first, returning an error if it fails.
+#[allow(clippy::too_many_arguments)]
+async fn process_batch(
+    oracle_client: &OracleClient,
+    rpc_client: &Arc<RpcClient>,
+    blockhash_cache: &BlockhashCache,
+    queue: &Pubkey,
+    account_bytes: &[u8],
+    token_fee_config: Option<&TokenFeeConfig>,
+    batch: &[QueueItem],
+    simulate: bool,
+) -> Result<String> {
+    let proofs = batch
+        .iter()
+        .map(|item| {
+            let (output, (commitment_base, commitment_hash, s)) =
+                compute_vrf(oracle_client.oracle_vrf_sk, &item.id);
+            ProvideRandomness {
+                input: item.id,
+                output: PodRistrettoPoint(output.to_bytes()),
+                commitment_base_compressed: PodRistrettoPoint(commitment_base.to_bytes()),
+                commitment_hash_compressed: PodRistrettoPoint(commitment_hash.to_bytes()),
+                scalar: PodScalar(s.to_bytes()),
+            }
+        })
+        .collect::<Vec<_>>();
+    let instructions = batch_instructions(
+        oracle_client,
+        queue,
+        &account_bytes[8..],
+        token_fee_config,
+        batch,
+        &proofs,
+    )?;
+
+    let (blockhash, _) = blockhash_cache.get_blockhash_and_slot().await;
+    let tx = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&oracle_client.keypair.pubkey()),
+        &[&oracle_client.keypair],
+        blockhash,
+    );
+
+    if simulate {
+        if let Some(err) = rpc_client.simulate_transaction(&tx).await?.value.err {
+            anyhow::bail!("simulation failed: {err}");
+        }
+    }
+
+    use solana_client::rpc_config::RpcSendTransactionConfig;
+    let sig = rpc_client
+        .send_transaction_with_config(
+            &tx,
+            RpcSendTransactionConfig {
+                skip_preflight: oracle_client.skip_preflight,
+                preflight_commitment: Some(CommitmentLevel::Processed),
+                ..Default::default()
+            },
+        )
+        .await?;
+    Ok(sig.to_string())
+}
+
+/// Compute budget of the fulfillment of a request.
+fn compute_budget(item: &QueueItem) -> u32 {
+    match item.priority_request {
+        1 => 300_000,
+        _ => 180_000,
+    }
 }
 
 #[repr(transparent)]
 pub struct ProcessableItem(pub QueueItem);
 
 impl ProcessableItem {
+    /// Sends the fulfillment of the item. With `fallback` the randomness is recorded in the
+    /// requester's result PDA instead of invoking the callback. With `simulate` the transaction
+    /// is simulated first, returning `CallbackFailed` if the callback is what fails.
     #[allow(clippy::too_many_arguments)]
     pub async fn process_item(
         &self,
@@ -323,6 +851,8 @@ impl ProcessableItem {
         queue_pubkey: &Pubkey,
         queue_meta: &Queue,
         account_bytes: &[u8],
+        fallback: bool,
+        simulate: bool,
     ) -> Result<String> {
         let (output, (commitment_base, commitment_hash, s)) =
             compute_vrf(oracle_client.oracle_vrf_sk, vrf_input);
@@ -337,11 +867,27 @@ impl ProcessableItem {
         let (blockhash, current_slot) = blockhash_cache.get_blockhash_and_slot().await;
 
         // Check whether the request is expired
-        let age = current_slot.saturating_sub(self.0.slot);
-        let is_purge = age > QUEUE_TTL_SLOTS;
-        let ix = if is_purge {
+        let is_purge = self.0.is_expired(current_slot, unix_timestamp());
+        let is_fallback = !is_purge && fallback && self.0.pull == 0 && self.0.fallback == 1;
+        let mut ix = if is_purge {
             // Build purge instruction for the queue index
-            purge_expired_requests(oracle_client.keypair.pubkey(), queue_meta.index)
+            if queue_meta.shared == 1 {
+                purge_expired_shared_requests(oracle_client.keypair.pubkey(), queue_meta.index)
+            } else {
+                purge_expired_requests(oracle_client.keypair.pubkey(), queue_meta.index)
+            }
+        } else if is_fallback {
+            provide_randomness_fallback(
+                oracle_client.keypair.pubkey(),
+                *queue_pubkey,
+                Pubkey::new_from_array(self.0.callback_program_id),
+                self.0.caller_seed,
+                *vrf_input,
+                PodRistrettoPoint(output.to_bytes()),
+                PodRistrettoPoint(commitment_base.to_bytes()),
+                PodRistrettoPoint(commitment_hash.to_bytes()),
+                PodScalar(s.to_bytes()),
+            )
         } else {
             // Build provide_randomness instruction
             let mut ix = provide_randomness(
@@ -357,23 +903,134 @@ impl ProcessableItem {
             let metas = self.0.account_metas(&account_bytes[8..]);
             ix.accounts
                 .extend(metas.iter().map(|a| a.to_account_meta()));
+            // Accounts written as PDA seeds are derived now, as they may not have existed at request time
+            let pda_metas = self.0.pda_account_metas(&account_bytes[8..])?;
+            ix.accounts
+                .extend(pda_metas.iter().map(|a| a.to_account_meta()));
             ix
         };
 
+        // Token fees are paid to the oracle's associated token account for the fee mint.
+        // Purges may include token-paid requests, so attach the accounts whenever configured.
         let budget = if is_purge {
             1_000_000
         } else {
-            match self.0.priority_request {
-                1 => 300_000,
-                _ => 180_000,
+            compute_budget(&self.0)
+        };
+        let mut instructions: Vec<Instruction> =
+            vec![ComputeBudgetInstruction::set_compute_unit_limit(budget)];
+        if !is_purge && self.0.tip > 0 {
+            instructions.push(ComputeBudgetInstruction::set_compute_unit_price(
+                compute_unit_price(self.0.tip, oracle_client.tip_priority_fee_bps, budget),
+            ));
+        }
+        let token_fee_config = if is_purge || self.0.is_token_fee() {
+            fetch_token_fee_config(rpc_client, queue_pubkey).await?
+        } else {
+            None
+        };
+        if let Some(config) = &token_fee_config {
+            let oracle = oracle_client.keypair.pubkey();
+            let oracle_token_account = get_associated_token_address_with_program_id(
+                &oracle,
+                &config.mint,
+                &config.token_program,
+            );
+            instructions.push(create_associated_token_account_idempotent(
+                &oracle,
+                &oracle,
+                &config.mint,
+                &config.token_program,
+            ));
+            ix.accounts.extend(token_fee_accounts(
+                queue_pubkey,
+                oracle_token_account,
+                config.mint,
+                config.token_program,
+            ));
+        }
+
+        // Receipts follow all the other accounts
+        if is_purge {
+            let mut acc_bytes = account_bytes[8..].to_vec();
+            let queue_account = QueueAccount::load(&mut acc_bytes[..])?;
+            let now = unix_timestamp();
+            let expired: Vec<QueueItem> = queue_account
+                .iter_items()
+                .filter(|item| item.is_expired(current_slot, now))
+                .collect();
+            let mut receipts: Vec<AccountMeta> = Vec::new();
+            for item in &expired {
+                if receipts.len() >= MAX_PURGE_RECEIPTS {
+                    break;
+                }
+                let Some(receipt) = receipt_account(item) else {
+                    continue;
+                };
+                // Fees paid in tokens are refunded to the token account of the receipt payer
+                let refunded = token_fee_config.as_ref().filter(|_| item.is_token_fee());
+                if let Some(config) = refunded {
+                    if let Some(payer) = fetch_receipt_payer(rpc_client, &receipt.pubkey).await {
+                        receipts.push(AccountMeta::new(
+                            get_associated_token_address_with_program_id(
+                                &payer,
+                                &config.mint,
+                                &config.token_program,
+                            ),
+                            false,
+                        ));
+                    }
+                }
+                receipts.push(receipt);
             }
+            ix.accounts.extend(receipts);
+        } else {
+            ix.accounts.extend(receipt_account(&self.0));
+        }
+
+        instructions.push(ix);
+        // Requests naming a lookup table are sent as v0 transactions to fit their accounts
+        let lookup_table = if is_purge {
+            None
+        } else {
+            self.0.lookup_table()
+        };
+        let tx: VersionedTransaction = match lookup_table {
+            Some(lookup_table) => {
+                let lookup_table = fetch_lookup_table(rpc_client, &lookup_table).await?;
+                let message = v0::Message::try_compile(
+                    &oracle_client.keypair.pubkey(),
+                    &instructions,
+                    &[lookup_table],
+                    blockhash,
+                )?;
+                VersionedTransaction::try_new(
+                    VersionedMessage::V0(message),
+                    &[&oracle_client.keypair],
+                )?
+            }
+            None => Transaction::new_signed_with_payer(
+                &instructions,
+                Some(&oracle_client.keypair.pubkey()),
+                &[&oracle_client.keypair],
+                blockhash,
+            )
+            .into(),
         };
-        let tx = Transaction::new_signed_with_payer(
-            &[ComputeBudgetInstruction::set_compute_unit_limit(budget), ix],
-            Some(&oracle_client.keypair.pubkey()),
-            &[&oracle_client.keypair],
-            blockhash,
-        );
+
+        if simulate && !is_purge && !is_fallback && self.0.pull == 0 {
+            let callback_program = Pubkey::new_from_array(self.0.callback_program_id);
+            let simulation = rpc_client.simulate_transaction(&tx).await?.value;
+            let callback_failed = simulation.err.is_some()
+                && simulation
+                    .logs
+                    .unwrap_or_default()
+                    .iter()
+                    .any(|log| log.starts_with(&format!("Program {callback_program} failed")));
+            if callback_failed {
+                return Err(CallbackFailed.into());
+            }
+        }
 
         use solana_client::rpc_config::RpcSendTransactionConfig;
         let sig = rpc_client
@@ -389,3 +1046,96 @@ impl ProcessableItem {
         Ok(sig.to_string())
     }
 }
+
+/// Receipt PDA of a request, if it was created with one.
+fn receipt_account(item: &QueueItem) -> Option<AccountMeta> {
+    (item.receipt == 1).then(|| {
+        let callback_program_id = Pubkey::new_from_array(item.callback_program_id);
+        AccountMeta::new(
+            receipt_pda(&callback_program_id, &item.caller_seed).0,
+            false,
+        )
+    })
+}
+
+/// Fetch the payer of a receipt, `None` if it can't be read.
+async fn fetch_receipt_payer(rpc_client: &RpcClient, receipt: &Pubkey) -> Option<Pubkey> {
+    let account = rpc_client
+        .get_account_with_commitment(receipt, CommitmentConfig::processed())
+        .await
+        .ok()?
+        .value?;
+    Some(Receipt::try_from_bytes(&account.data).ok()?.payer)
+}
+
+/// Fetch the token fee configuration of a queue, if token payments were ever configured.
+async fn fetch_token_fee_config(
+    rpc_client: &RpcClient,
+    queue: &Pubkey,
+) -> Result<Option<TokenFeeConfig>> {
+    let account = rpc_client
+        .get_account_with_commitment(
+            &token_fee_config_pda(queue).0,
+            CommitmentConfig::processed(),
+        )
+        .await?
+        .value;
+    match account {
+        Some(account) if account.owner == PROGRAM_ID => {
+            Ok(Some(*TokenFeeConfig::try_from_bytes(&account.data)?))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Fetch the addresses of an address lookup table.
+async fn fetch_lookup_table(
+    rpc_client: &RpcClient,
+    key: &Pubkey,
+) -> Result<AddressLookupTableAccount> {
+    let account = rpc_client
+        .get_account_with_commitment(key, CommitmentConfig::processed())
+        .await?
+        .value
+        .ok_or_else(|| anyhow::anyhow!("Lookup table {key} not found"))?;
+    let table = AddressLookupTable::deserialize(&account.data)
+        .map_err(|e| anyhow::anyhow!("Invalid lookup table {key}: {e}"))?;
+    Ok(AddressLookupTableAccount {
+        key: *key,
+        addresses: table.addresses.to_vec(),
+    })
+}
+
+/// Compute-unit price (in micro-lamports) spending `tip_bps` of the tip over the compute budget,
+/// never more than the tip.
+fn compute_unit_price(tip: u64, tip_bps: u16, budget: u32) -> u64 {
+    let micro_lamports = (tip as u128) * (tip_bps.min(10_000) as u128) * 1_000_000 / 10_000;
+    u64::try_from(micro_lamports / budget.max(1) as u128).unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compute_unit_price_spends_the_share_of_the_tip() {
+        // 50% of 300_000 lamports over 150_000 CUs is 1 lamport per CU
+        assert_eq!(compute_unit_price(300_000, 5_000, 150_000), 1_000_000);
+        assert_eq!(compute_unit_price(0, 5_000, 150_000), 0);
+        assert_eq!(compute_unit_price(1_000, 10_000, 0), 1_000_000_000);
+    }
+
+    #[test]
+    fn compute_unit_price_spends_at_most_the_tip() {
+        assert_eq!(
+            compute_unit_price(1_000, u16::MAX, 1_000),
+            compute_unit_price(1_000, 10_000, 1_000)
+        );
+        assert_eq!(compute_unit_price(1_000, 10_001, 1_000), 1_000_000);
+    }
+
+    #[test]
+    fn compute_unit_price_saturates() {
+        assert_eq!(compute_unit_price(u64::MAX, 10_000, 1), u64::MAX);
+    }
+}
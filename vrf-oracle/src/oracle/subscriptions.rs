@@ -0,0 +1,106 @@
+use crate::blockhash_cache::BlockhashCache;
+use crate::oracle::client::OracleClient;
+use crate::oracle::utils::subscription_memcmp_filter;
+use anyhow::Result;
+use ephemeral_vrf_api::{
+    prelude::{request_data_len, request_fee_lamports, trigger_subscription, Subscription},
+    state::{oracle_queue_pda, shared_queue_pda},
+    ID as PROGRAM_ID,
+};
+use log::{info, warn};
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_config::{
+    RpcAccountInfoConfig, RpcProgramAccountsConfig, RpcSendTransactionConfig,
+};
+use solana_commitment_config::{CommitmentConfig, CommitmentLevel};
+use solana_sdk::{pubkey::Pubkey, signature::Signer, transaction::Transaction};
+use std::collections::HashSet;
+
+/// Enqueue the due rounds of the subscriptions of the oracle's queues and of the shared queues.
+/// Rounds are then fulfilled like any other request of the queue.
+pub async fn trigger_due_subscriptions(
+    oracle_client: &OracleClient,
+    rpc_client: &RpcClient,
+    blockhash_cache: &BlockhashCache,
+) -> Result<()> {
+    let oracle = oracle_client.keypair.pubkey();
+    let queues: HashSet<Pubkey> = (0..=u8::MAX)
+        .map(|index| oracle_queue_pda(&oracle, index).0)
+        .chain((0..=u8::MAX).map(|index| shared_queue_pda(index).0))
+        .collect();
+
+    let config = RpcProgramAccountsConfig {
+        account_config: RpcAccountInfoConfig {
+            commitment: Some(CommitmentConfig::processed()),
+            encoding: Some(UiAccountEncoding::Base64),
+            ..Default::default()
+        },
+        filters: Some(subscription_memcmp_filter()),
+        ..Default::default()
+    };
+    let accounts = rpc_client
+        .get_program_accounts_with_config(&PROGRAM_ID, config)
+        .await?;
+    if accounts.is_empty() {
+        return Ok(());
+    }
+
+    blockhash_cache.refresh_blockhash().await;
+    let (blockhash, current_slot) = blockhash_cache.get_blockhash_and_slot().await;
+    for (pubkey, account) in accounts {
+        let subscription = match Subscription::try_from_bytes_with_discriminator(&account.data) {
+            Ok(subscription) => subscription,
+            Err(err) => {
+                warn!("Invalid subscription {pubkey}: {err}");
+                continue;
+            }
+        };
+        // The input of a round is the hash of its slot, known once the slot is past
+        if !queues.contains(&subscription.queue) || current_slot <= subscription.next_round_slot {
+            continue;
+        }
+
+        // Rounds are paid from the lamports of the subscription above rent
+        let fee = request_fee_lamports(
+            false,
+            subscription.callback_accounts_metas.len() + subscription.callback_pda_metas.len(),
+            request_data_len(
+                &subscription.callback_args,
+                &subscription.callback_pda_metas,
+            ),
+        );
+        let rent = rpc_client
+            .get_minimum_balance_for_rent_exemption(account.data.len())
+            .await?;
+        if account.lamports.saturating_sub(rent) < fee {
+            warn!("Subscription {pubkey} can't pay its next round");
+            continue;
+        }
+
+        let tx = Transaction::new_signed_with_payer(
+            &[trigger_subscription(oracle, subscription.queue, pubkey)],
+            Some(&oracle),
+            &[&oracle_client.keypair],
+            blockhash,
+        );
+        match rpc_client
+            .send_transaction_with_config(
+                &tx,
+                RpcSendTransactionConfig {
+                    skip_preflight: oracle_client.skip_preflight,
+                    preflight_commitment: Some(CommitmentLevel::Processed),
+                    ..Default::default()
+                },
+            )
+            .await
+        {
+            Ok(sig) => info!(
+                "Triggered round {} of subscription {pubkey}: {sig}",
+                subscription.sequence
+            ),
+            Err(err) => warn!("Failed to trigger subscription {pubkey}: {err}"),
+        }
+    }
+    Ok(())
+}
@@ -7,3 +7,10 @@ pub fn queue_memcmp_filter() -> Vec<RpcFilterType> {
         MemcmpEncodedBytes::Bytes(AccountDiscriminator::Queue.to_bytes().to_vec()),
     ))]
 }
+
+pub fn subscription_memcmp_filter() -> Vec<RpcFilterType> {
+    vec![RpcFilterType::Memcmp(Memcmp::new(
+        0,
+        MemcmpEncodedBytes::Bytes(AccountDiscriminator::Subscription.to_bytes().to_vec()),
+    ))]
+}